@@ -1,7 +1,9 @@
+use circle_plonk_dsl_channel::{Poseidon31ChannelTracer, TranscriptEntry};
 use itertools::Itertools;
 use num_traits::{One, Zero};
 use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Add, Mul, Neg};
+use stwo_prover::constraint_framework::preprocessed_columns::PreProcessedColumnId;
 use stwo_prover::constraint_framework::{Relation, PREPROCESSED_TRACE_IDX};
 use stwo_prover::core::air::Components;
 use stwo_prover::core::channel::{Channel, MerkleChannel};
@@ -13,6 +15,7 @@ use stwo_prover::core::fields::{Field, FieldExpOps};
 use stwo_prover::core::fri::{CirclePolyDegreeBound, FriVerifier};
 use stwo_prover::core::pcs::{CommitmentSchemeVerifier, PcsConfig, TreeSubspan, TreeVec};
 use stwo_prover::core::vcs::ops::MerkleHasher;
+use stwo_prover::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
 use stwo_prover::core::ColumnVec;
 use stwo_prover::examples::plonk_with_poseidon::air::{
     PlonkWithPoseidonComponents, PlonkWithPoseidonProof,
@@ -41,6 +44,9 @@ pub struct FiatShamirHints<MC: MerkleChannel> {
     pub max_first_layer_column_log_size: u32,
     pub sorted_query_positions_per_log_size: BTreeMap<u32, Vec<usize>>,
     pub unsorted_query_positions_per_log_size: BTreeMap<u32, Vec<usize>>,
+    /// The query positions in increasing order with one entry per query, which is the order in
+    /// which the circuit holds the queries.
+    pub query_positions_per_log_size: BTreeMap<u32, Vec<usize>>,
     pub column_log_sizes: TreeVec<Vec<u32>>,
     pub n_columns_per_log_size: TreeVec<BTreeMap<u32, usize>>,
     pub trees_log_sizes: TreeVec<Vec<u32>>,
@@ -53,6 +59,9 @@ pub struct FiatShamirHints<MC: MerkleChannel> {
     pub plonk_prepared_column_indices: Vec<usize>,
     pub poseidon_prepared_column_indices: Vec<usize>,
 
+    pub plonk_preprocessed_column_ids: Vec<PreProcessedColumnId>,
+    pub poseidon_preprocessed_column_ids: Vec<PreProcessedColumnId>,
+
     pub sample_points: TreeVec<ColumnVec<Vec<CirclePoint<SecureField>>>>,
     pub mask_plonk: TreeVec<Vec<Vec<isize>>>,
     pub mask_poseidon: TreeVec<Vec<Vec<isize>>>,
@@ -94,6 +103,9 @@ impl<MC: MerkleChannel> FiatShamirHints<MC> {
         let poseidon_tree_subspan = components.poseidon.trace_locations().to_vec();
         let poseidon_prepared_column_indices =
             components.poseidon.preproccessed_column_indices().to_vec();
+        let plonk_preprocessed_column_ids = components.plonk.info.preprocessed_columns.clone();
+        let poseidon_preprocessed_column_ids =
+            components.poseidon.info.preprocessed_columns.clone();
 
         // Get the mask relations
         let mask_plonk = components.plonk.info.mask_offsets.clone();
@@ -242,6 +254,10 @@ impl<MC: MerkleChannel> FiatShamirHints<MC> {
             }
             map
         };
+        let query_positions_per_log_size = unsorted_query_positions_per_log_size
+            .iter()
+            .map(|(&log_size, queries)| (log_size, queries.iter().copied().sorted().collect_vec()))
+            .collect();
         let sorted_query_positions_per_log_size = fri_verifier.sample_query_positions(channel);
 
         let column_log_sizes = commitment_scheme
@@ -274,6 +290,7 @@ impl<MC: MerkleChannel> FiatShamirHints<MC> {
             all_log_sizes,
             max_first_layer_column_log_size,
             unsorted_query_positions_per_log_size,
+            query_positions_per_log_size,
             sorted_query_positions_per_log_size,
             column_log_sizes,
             n_columns_per_log_size,
@@ -284,6 +301,8 @@ impl<MC: MerkleChannel> FiatShamirHints<MC> {
             poseidon_tree_subspan,
             plonk_prepared_column_indices,
             poseidon_prepared_column_indices,
+            plonk_preprocessed_column_ids,
+            poseidon_preprocessed_column_ids,
             sample_points,
             mask_plonk,
             mask_poseidon,
@@ -291,3 +310,68 @@ impl<MC: MerkleChannel> FiatShamirHints<MC> {
         }
     }
 }
+
+impl FiatShamirHints<Poseidon31MerkleChannel> {
+    /// Replays the transcript of the proof on a `Poseidon31ChannelTracer` with the operations of
+    /// `FiatShamirResults::compute`, checking the drawn values against the hints, so that the
+    /// transcript recorded in the circuit can be compared with it operation by operation.
+    pub fn transcript(
+        &self,
+        proof: &PlonkWithPoseidonProof<Poseidon31MerkleHasher>,
+    ) -> Vec<TranscriptEntry> {
+        let mut channel = Poseidon31ChannelTracer::default();
+        let mix_felts = |channel: &mut Poseidon31ChannelTracer, felts: &[QM31]| {
+            for chunk in felts.chunks(2) {
+                match chunk {
+                    [felt1, felt2] => channel.mix_two_felts(*felt1, *felt2),
+                    [felt] => channel.mix_one_felt(*felt),
+                    _ => unreachable!(),
+                }
+            }
+        };
+
+        channel.mix_root(proof.stark_proof.commitments[0]);
+        channel.mix_u64(proof.stmt0.log_size_plonk as u64);
+        channel.mix_u64(proof.stmt0.log_size_poseidon as u64);
+        channel.mix_root(proof.stark_proof.commitments[1]);
+        assert_eq!(channel.draw_felts(), [self.z, self.alpha]);
+
+        channel.mix_two_felts(proof.stmt1.plonk_total_sum, proof.stmt1.poseidon_total_sum);
+        channel.mix_root(proof.stark_proof.commitments[2]);
+        assert_eq!(channel.draw_felts()[0], self.random_coeff);
+
+        channel.mix_root(proof.stark_proof.commitments[3]);
+        assert_eq!(channel.draw_felts()[0], self.oods_t);
+
+        mix_felts(
+            &mut channel,
+            &proof.stark_proof.sampled_values.clone().flatten_cols(),
+        );
+        assert_eq!(
+            channel.draw_felts()[0],
+            self.after_sampled_values_random_coeff
+        );
+
+        let fri_proof = &proof.stark_proof.fri_proof;
+        let commitments = std::iter::once(fri_proof.first_layer.commitment)
+            .chain(fri_proof.inner_layers.iter().map(|layer| layer.commitment));
+        for (commitment, fri_alpha) in commitments.zip_eq(self.fri_alphas.iter()) {
+            channel.mix_root(commitment);
+            assert_eq!(channel.draw_felts()[0], *fri_alpha);
+        }
+        mix_felts(&mut channel, &fri_proof.last_layer_poly.coeffs);
+
+        channel.mix_nonce(proof.stark_proof.proof_of_work);
+        let queries =
+            &self.unsorted_query_positions_per_log_size[&self.max_first_layer_column_log_size];
+        let raw_queries = channel.draw_raw_queries(queries.len());
+        for (raw_query, &query) in raw_queries.iter().zip_eq(queries.iter()) {
+            assert_eq!(
+                raw_query.0 as usize & ((1 << self.max_first_layer_column_log_size) - 1),
+                query
+            );
+        }
+
+        channel.trace
+    }
+}
@@ -398,7 +398,7 @@ impl FirstLayerHints {
             &log_sizes_with_data,
             proof.stark_proof.fri_proof.first_layer.commitment,
             &fiat_shamir_hints
-                .unsorted_query_positions_per_log_size
+                .query_positions_per_log_size
                 .get(&fiat_shamir_hints.max_first_layer_column_log_size)
                 .unwrap(),
             &decommitmented_values,
@@ -418,8 +418,8 @@ impl FirstLayerHints {
         mut witness_evals: impl Iterator<Item = SecureField>,
     ) -> (Vec<usize>, SparseEvaluation) {
         let mut queries = queries.to_vec();
-        queries.dedup();
         queries.sort_unstable();
+        queries.dedup();
 
         let mut query_evals = query_evals.iter().copied();
 
@@ -466,7 +466,7 @@ impl InnerLayersHints {
 
         let mut folded = BTreeMap::new();
         for i in fiat_shamir_hints
-            .unsorted_query_positions_per_log_size
+            .query_positions_per_log_size
             .get(&log_size)
             .unwrap()
             .iter()
@@ -547,7 +547,7 @@ impl InnerLayersHints {
                 &BTreeSet::from([log_size]),
                 inner_layer.commitment.clone(),
                 &fiat_shamir_hints
-                    .unsorted_query_positions_per_log_size
+                    .query_positions_per_log_size
                     .get(&fiat_shamir_hints.max_first_layer_column_log_size)
                     .unwrap()
                     .iter()
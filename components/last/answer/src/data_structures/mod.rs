@@ -1,16 +1,15 @@
-use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::var::Var;
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_hints::FiatShamirHints;
-use merkle_proofs::{
-    LastSinglePathMerkleProof, LastSinglePathMerkleProofInput, LastSinglePathMerkleProofInputVar,
-    LastSinglePathMerkleProofVar,
-};
+use circle_plonk_dsl_sha256::{Sha256SinglePathMerkleProof, Sha256SinglePathMerkleProofVar};
 use stwo_prover::core::vcs::sha256_poseidon31_merkle::{
     Sha256Poseidon31MerkleChannel, Sha256Poseidon31MerkleHasher,
 };
 use stwo_prover::examples::plonk_with_poseidon::air::PlonkWithPoseidonProof;
 
-pub mod merkle_proofs;
+pub type LastSinglePathMerkleProof = Sha256SinglePathMerkleProof<Sha256Poseidon31MerkleHasher>;
+pub type LastSinglePathMerkleProofVar =
+    Sha256SinglePathMerkleProofVar<Sha256Poseidon31MerkleHasher>;
 
 #[derive(Debug, Clone)]
 pub struct LastDecommitHints {
@@ -47,10 +46,11 @@ impl LastDecommitHints {
             **v = LastSinglePathMerkleProof::from_stwo_proof(
                 max_log_size,
                 &fiat_shamir_hints
-                    .unsorted_query_positions_per_log_size
+                    .query_positions_per_log_size
                     .get(&max_log_size)
                     .unwrap(),
                 &proof.stark_proof.queried_values[i],
+                proof.stark_proof.commitments[i],
                 &fiat_shamir_hints.n_columns_per_log_size[i],
                 &proof.stark_proof.decommitments[i],
             );
@@ -66,90 +66,42 @@ impl LastDecommitHints {
 }
 
 #[derive(Clone)]
-pub struct LastDecommitInput {
-    pub precomputed_proofs: Vec<LastSinglePathMerkleProofInput>,
-    pub trace_proofs: Vec<LastSinglePathMerkleProofInput>,
-    pub interaction_proofs: Vec<LastSinglePathMerkleProofInput>,
-    pub composition_proofs: Vec<LastSinglePathMerkleProofInput>,
-}
-
-impl LastDecommitInput {
-    pub fn from_hints(hints: &LastDecommitHints) -> Self {
-        let mut precomputed_proofs = vec![];
-        let mut trace_proofs = vec![];
-        let mut interaction_proofs = vec![];
-        let mut composition_proofs = vec![];
-
-        for v in hints.precomputed_proofs.iter() {
-            precomputed_proofs.push(LastSinglePathMerkleProofInput::from_proof(v));
-        }
-
-        for v in hints.trace_proofs.iter() {
-            trace_proofs.push(LastSinglePathMerkleProofInput::from_proof(v));
-        }
-
-        for v in hints.interaction_proofs.iter() {
-            interaction_proofs.push(LastSinglePathMerkleProofInput::from_proof(v));
-        }
-
-        for v in hints.composition_proofs.iter() {
-            composition_proofs.push(LastSinglePathMerkleProofInput::from_proof(v));
-        }
-
-        Self {
-            precomputed_proofs,
-            trace_proofs,
-            interaction_proofs,
-            composition_proofs,
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct LastDecommitInputVar {
+pub struct LastDecommitVar {
     pub cs: ConstraintSystemRef,
-    pub precomputed_proofs: Vec<LastSinglePathMerkleProofInputVar>,
-    pub trace_proofs: Vec<LastSinglePathMerkleProofInputVar>,
-    pub interaction_proofs: Vec<LastSinglePathMerkleProofInputVar>,
-    pub composition_proofs: Vec<LastSinglePathMerkleProofInputVar>,
+    pub precomputed_proofs: Vec<LastSinglePathMerkleProofVar>,
+    pub trace_proofs: Vec<LastSinglePathMerkleProofVar>,
+    pub interaction_proofs: Vec<LastSinglePathMerkleProofVar>,
+    pub composition_proofs: Vec<LastSinglePathMerkleProofVar>,
 }
 
-impl Var for LastDecommitInputVar {
-    type Value = LastDecommitInput;
+impl Var for LastDecommitVar {
+    type Value = LastDecommitHints;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl AllocVar for LastDecommitInputVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+impl LastDecommitVar {
+    pub fn new(cs: &ConstraintSystemRef, value: &LastDecommitHints) -> Self {
         let mut precomputed_proofs = vec![];
         for proof in value.precomputed_proofs.iter() {
-            precomputed_proofs.push(LastSinglePathMerkleProofInputVar::new_variables(
-                cs, proof, mode,
-            ));
+            precomputed_proofs.push(LastSinglePathMerkleProofVar::new(cs, proof));
         }
 
         let mut trace_proofs = vec![];
         for proof in value.trace_proofs.iter() {
-            trace_proofs.push(LastSinglePathMerkleProofInputVar::new_variables(
-                cs, proof, mode,
-            ));
+            trace_proofs.push(LastSinglePathMerkleProofVar::new(cs, proof));
         }
 
         let mut interaction_proofs = vec![];
         for proof in value.interaction_proofs.iter() {
-            interaction_proofs.push(LastSinglePathMerkleProofInputVar::new_variables(
-                cs, proof, mode,
-            ));
+            interaction_proofs.push(LastSinglePathMerkleProofVar::new(cs, proof));
         }
 
         let mut composition_proofs = vec![];
         for proof in value.composition_proofs.iter() {
-            composition_proofs.push(LastSinglePathMerkleProofInputVar::new_variables(
-                cs, proof, mode,
-            ));
+            composition_proofs.push(LastSinglePathMerkleProofVar::new(cs, proof));
         }
 
         Self {
@@ -161,66 +113,3 @@ impl AllocVar for LastDecommitInputVar {
         }
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct LastDecommitVar {
-    pub cs: ConstraintSystemRef,
-    pub precomputed_proofs: Vec<LastSinglePathMerkleProofVar>,
-    pub trace_proofs: Vec<LastSinglePathMerkleProofVar>,
-    pub interaction_proofs: Vec<LastSinglePathMerkleProofVar>,
-    pub composition_proofs: Vec<LastSinglePathMerkleProofVar>,
-}
-
-impl LastDecommitVar {
-    pub fn compute(input_var: &LastDecommitInputVar, hints: &LastDecommitHints) -> Self {
-        let cs = input_var.cs();
-
-        let mut precomputed_proofs = vec![];
-        for (var, proof) in input_var
-            .precomputed_proofs
-            .iter()
-            .zip(hints.precomputed_proofs.iter())
-        {
-            precomputed_proofs.push(LastSinglePathMerkleProofVar::from_proof_and_input(
-                var, proof,
-            ));
-        }
-
-        let mut trace_proofs = vec![];
-        for (var, proof) in input_var.trace_proofs.iter().zip(hints.trace_proofs.iter()) {
-            trace_proofs.push(LastSinglePathMerkleProofVar::from_proof_and_input(
-                var, proof,
-            ));
-        }
-
-        let mut interaction_proofs = vec![];
-        for (var, proof) in input_var
-            .interaction_proofs
-            .iter()
-            .zip(hints.interaction_proofs.iter())
-        {
-            interaction_proofs.push(LastSinglePathMerkleProofVar::from_proof_and_input(
-                var, proof,
-            ));
-        }
-
-        let mut composition_proofs = vec![];
-        for (var, proof) in input_var
-            .composition_proofs
-            .iter()
-            .zip(hints.composition_proofs.iter())
-        {
-            composition_proofs.push(LastSinglePathMerkleProofVar::from_proof_and_input(
-                var, proof,
-            ));
-        }
-
-        Self {
-            cs,
-            precomputed_proofs,
-            trace_proofs,
-            interaction_proofs,
-            composition_proofs,
-        }
-    }
-}
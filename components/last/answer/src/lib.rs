@@ -1,9 +1,10 @@
-use crate::data_structures::{LastDecommitHints, LastDecommitInputVar, LastDecommitVar};
-use circle_plonk_dsl_answer::data_structures::{PointSampleVar, ShiftIndex};
+use crate::data_structures::{LastDecommitHints, LastDecommitVar, LastSinglePathMerkleProofVar};
+use circle_plonk_dsl_answer::data_structures::{place_mask_points, PointSampleVar, ShiftIndex};
 use circle_plonk_dsl_answer::AnswerResults;
 use circle_plonk_dsl_circle::{CirclePointM31Var, CirclePointQM31Var};
 use circle_plonk_dsl_constraint_system::var::Var;
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_domain::CosetVar;
 use circle_plonk_dsl_fields::QM31Var;
 use circle_plonk_dsl_hints::{AnswerHints, FiatShamirHints};
 use circle_plonk_dsl_last_data_structures::LastPlonkWithPoseidonProofVar;
@@ -11,12 +12,9 @@ use circle_plonk_dsl_last_fiat_shamir::LastFiatShamirResults;
 use circle_plonk_dsl_query::QueryPositionsPerLogSizeVar;
 use itertools::{izip, multiunzip, Itertools};
 use std::cmp::Reverse;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::BTreeMap;
 use std::iter::zip;
-use std::ops::Add;
-use stwo_prover::constraint_framework::PREPROCESSED_TRACE_IDX;
 use stwo_prover::core::pcs::{PcsConfig, TreeVec};
-use stwo_prover::core::poly::circle::CanonicCoset;
 use stwo_prover::core::vcs::sha256_poseidon31_merkle::Sha256Poseidon31MerkleChannel;
 use stwo_prover::core::ColumnVec;
 
@@ -35,132 +33,54 @@ impl LastAnswerResults {
         decommit_hints: &LastDecommitHints,
         fri_answer_hints: &AnswerHints<Sha256Poseidon31MerkleChannel>,
         last_fiat_shamir_results: &LastFiatShamirResults,
-        last_decommit_input_var: &LastDecommitInputVar,
         proof: &LastPlonkWithPoseidonProofVar,
         pcs_config: PcsConfig,
     ) -> Self {
         let cs = last_fiat_shamir_results.oods_point.cs();
 
-        let mut all_shifts_plonk = HashSet::new();
-        let mut all_shifts_poseidon = HashSet::new();
-        for round in fiat_shamir_hints.mask_plonk.iter() {
-            for column in round.iter() {
-                for &shift in column.iter() {
-                    all_shifts_plonk.insert(shift);
-                }
-            }
-        }
-        for round in fiat_shamir_hints.mask_poseidon.iter() {
-            for column in round.iter() {
-                for &shift in column.iter() {
-                    all_shifts_poseidon.insert(shift);
-                }
-            }
-        }
-
-        let trace_step_plonk = CanonicCoset::new(fiat_shamir_hints.log_size_plonk).step();
-        let trace_step_poseidon = CanonicCoset::new(fiat_shamir_hints.log_size_poseidon).step();
+        let trace_coset_plonk = CosetVar::canonic(&cs, fiat_shamir_hints.log_size_plonk);
+        let trace_coset_poseidon = CosetVar::canonic(&cs, fiat_shamir_hints.log_size_poseidon);
 
         let oods_point = &last_fiat_shamir_results.oods_point;
 
-        let mut shifted_points_plonk = HashMap::<isize, CirclePointQM31Var>::new();
-        let mut shifted_points_poseidon = HashMap::<isize, CirclePointQM31Var>::new();
-        for &i in all_shifts_plonk.iter() {
-            shifted_points_plonk.insert(i, oods_point.add(&trace_step_plonk.mul_signed(i)));
-        }
-        for &i in all_shifts_poseidon.iter() {
-            shifted_points_poseidon.insert(i, oods_point.add(&trace_step_poseidon.mul_signed(i)));
-        }
-
-        let mut mask_points_plonk: TreeVec<ColumnVec<Vec<(ShiftIndex, CirclePointQM31Var)>>> =
-            fiat_shamir_hints.mask_plonk.as_ref().map_cols(|column| {
-                column
-                    .iter()
-                    .map(|shift| {
-                        (
-                            ShiftIndex::from_shift(*shift, fiat_shamir_hints.log_size_plonk),
-                            shifted_points_plonk.get(shift).unwrap().clone(),
-                        )
-                    })
-                    .collect_vec()
-            });
-        mask_points_plonk[PREPROCESSED_TRACE_IDX] =
-            vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 10];
-        let mut mask_points_poseidon: TreeVec<ColumnVec<Vec<(ShiftIndex, CirclePointQM31Var)>>> =
-            fiat_shamir_hints.mask_poseidon.as_ref().map_cols(|column| {
-                column
+        let mut sampled_points: TreeVec<ColumnVec<Vec<(ShiftIndex, CirclePointQM31Var)>>> =
+            TreeVec::new(
+                fiat_shamir_hints
+                    .sample_points
                     .iter()
-                    .map(|shift| {
-                        (
-                            ShiftIndex::from_shift(*shift, fiat_shamir_hints.log_size_poseidon),
-                            shifted_points_poseidon.get(shift).unwrap().clone(),
-                        )
-                    })
-                    .collect_vec()
-            });
-        mask_points_poseidon[PREPROCESSED_TRACE_IDX] =
-            vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 40];
-
-        assert_eq!(
-            mask_points_plonk.len(),
-            fiat_shamir_hints.sample_points.len() - 1
+                    .take(fiat_shamir_hints.sample_points.len() - 1)
+                    .map(|tree| vec![vec![]; tree.len()])
+                    .collect_vec(),
+            );
+        place_mask_points(
+            &mut sampled_points,
+            oods_point,
+            &trace_coset_plonk,
+            &fiat_shamir_hints.mask_plonk,
+            &fiat_shamir_hints.plonk_tree_subspan,
+            &fiat_shamir_hints.plonk_prepared_column_indices,
+        );
+        place_mask_points(
+            &mut sampled_points,
+            oods_point,
+            &trace_coset_poseidon,
+            &fiat_shamir_hints.mask_poseidon,
+            &fiat_shamir_hints.poseidon_tree_subspan,
+            &fiat_shamir_hints.poseidon_prepared_column_indices,
         );
-        for (round_idx, (round_plonk, round_poseidon)) in mask_points_plonk
+        sampled_points.push(vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 4]);
+
+        for (column, expected_column) in sampled_points
             .iter()
-            .zip(mask_points_poseidon.iter())
-            .enumerate()
-            .take(3)
+            .flatten()
+            .zip_eq(fiat_shamir_hints.sample_points.iter().flatten())
         {
-            assert_eq!(
-                round_plonk.len() + round_poseidon.len(),
-                fiat_shamir_hints.sample_points[round_idx].len(),
-                "round_idx = {}",
-                round_idx
-            );
-            for (column_idx, column) in round_plonk.iter().enumerate() {
-                assert_eq!(
-                    column.len(),
-                    fiat_shamir_hints.sample_points[round_idx][column_idx].len()
-                );
-                for (shift_idx, (_, shifted_point)) in column.iter().enumerate() {
-                    assert_eq!(
-                        shifted_point.x.value(),
-                        fiat_shamir_hints.sample_points[round_idx][column_idx][shift_idx].x
-                    );
-                    assert_eq!(
-                        shifted_point.y.value(),
-                        fiat_shamir_hints.sample_points[round_idx][column_idx][shift_idx].y
-                    );
-                }
-            }
-
-            for (column_idx, column) in round_poseidon.iter().enumerate() {
-                assert_eq!(
-                    column.len(),
-                    fiat_shamir_hints.sample_points[round_idx][round_plonk.len() + column_idx]
-                        .len()
-                );
-                for (shift_idx, (_, shifted_point)) in column.iter().enumerate() {
-                    assert_eq!(
-                        shifted_point.x.value(),
-                        fiat_shamir_hints.sample_points[round_idx][round_plonk.len() + column_idx]
-                            [shift_idx]
-                            .x
-                    );
-                    assert_eq!(
-                        shifted_point.y.value(),
-                        fiat_shamir_hints.sample_points[round_idx][round_plonk.len() + column_idx]
-                            [shift_idx]
-                            .y
-                    );
-                }
+            assert_eq!(column.len(), expected_column.len());
+            for ((_, point), expected_point) in column.iter().zip(expected_column.iter()) {
+                assert_eq!(point.value(), *expected_point);
             }
         }
 
-        let mut sampled_points =
-            TreeVec::concat_cols([mask_points_plonk, mask_points_poseidon].into_iter());
-        sampled_points.push(vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 4]);
-
         let samples = sampled_points
             .zip_cols(proof.stark_proof.sampled_values.clone())
             .map_cols(|(sampled_points, sampled_values)| {
@@ -173,26 +93,22 @@ impl LastAnswerResults {
                     .collect_vec()
             });
 
-        let query_positions_per_log_size = QueryPositionsPerLogSizeVar::new(
-            pcs_config.fri_config.log_blowup_factor + 1
-                ..=fiat_shamir_hints.max_first_layer_column_log_size,
+        let query_positions_per_log_size = QueryPositionsPerLogSizeVar::from_pcs_config(
+            pcs_config,
+            &fiat_shamir_hints.all_log_sizes,
             &last_fiat_shamir_results.queries_at_max_first_layer_column_log_size,
         );
 
+        // the distinct positions are those of the slots that start a new position
         for &column_log_size in fiat_shamir_hints.all_log_sizes.iter() {
-            let mut sorted_queries = vec![];
-            for query in query_positions_per_log_size[column_log_size].iter() {
-                sorted_queries.push(query.bits.get_value().0 as usize);
-            }
-            sorted_queries.sort_unstable();
-            sorted_queries.dedup();
-
-            if column_log_size == fiat_shamir_hints.max_first_layer_column_log_size {
-                assert_eq!(sorted_queries.len(), pcs_config.fri_config.n_queries,
-                           "The implementation does not support the situation when the first {} attempts in sampling queries end up duplicated queries",
-                           pcs_config.fri_config.n_queries
-                );
-            }
+            let sorted = query_positions_per_log_size.sorted_positions(column_log_size);
+            let sorted_queries = sorted
+                .positions
+                .iter()
+                .zip(sorted.is_first.iter())
+                .filter(|(_, is_first)| is_first.value.0 == 1)
+                .map(|(position, _)| position.value.0 as usize)
+                .collect_vec();
 
             assert_eq!(
                 sorted_queries,
@@ -200,18 +116,41 @@ impl LastAnswerResults {
             );
         }
         for &column_log_size in fiat_shamir_hints.all_log_sizes.iter() {
-            let mut unsorted_queries = vec![];
+            let mut queries = vec![];
             for query in query_positions_per_log_size[column_log_size].iter() {
-                unsorted_queries.push(query.bits.get_value().0 as usize);
+                queries.push(query.bits.get_value().0 as usize);
             }
 
             assert_eq!(
-                unsorted_queries,
-                fiat_shamir_hints.unsorted_query_positions_per_log_size[&column_log_size]
+                queries,
+                fiat_shamir_hints.query_positions_per_log_size[&column_log_size]
             );
         }
 
-        let last_decommit_var = LastDecommitVar::compute(&last_decommit_input_var, &decommit_hints);
+        let last_decommit_var = LastDecommitVar::new(&cs, &decommit_hints);
+        for (tree_idx, proofs) in [
+            &last_decommit_var.precomputed_proofs,
+            &last_decommit_var.trace_proofs,
+            &last_decommit_var.interaction_proofs,
+            &last_decommit_var.composition_proofs,
+        ]
+        .iter()
+        .enumerate()
+        {
+            let max_log_size = *fiat_shamir_hints.n_columns_per_log_size[tree_idx]
+                .keys()
+                .max()
+                .unwrap();
+            let queries = query_positions_per_log_size[max_log_size]
+                .iter()
+                .map(|query| query.bits.clone())
+                .collect_vec();
+            LastSinglePathMerkleProofVar::verify_all(
+                proofs,
+                &last_fiat_shamir_results.commitments[tree_idx],
+                &queries,
+            );
+        }
 
         let mut queried_values = BTreeMap::new();
         for &log_size in fiat_shamir_hints.all_log_sizes.iter() {
@@ -263,7 +202,8 @@ impl LastAnswerResults {
                 AnswerResults::fri_answers_for_log_size(
                     &samples,
                     &last_fiat_shamir_results.after_sampled_values_random_coeff,
-                    &query_positions_per_log_size[log_size],
+                    &query_positions_per_log_size,
+                    log_size,
                     &queried_values[&log_size],
                 );
             domain_points.push(domain_points_per_log_size);
@@ -312,7 +252,7 @@ impl LastAnswerResults {
 
 #[cfg(test)]
 mod test {
-    use crate::data_structures::{LastDecommitHints, LastDecommitInput, LastDecommitInputVar};
+    use crate::data_structures::LastDecommitHints;
     use crate::LastAnswerResults;
     use circle_plonk_dsl_constraint_system::var::AllocVar;
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
@@ -370,22 +310,19 @@ mod test {
         let decommit_hints = LastDecommitHints::from_proof(&fiat_shamir_hints, &proof);
 
         let fiat_shamir_input = LastFiatShamirInput::from_proof(&proof, &fiat_shamir_hints);
-        let decommit_input = LastDecommitInput::from_hints(&decommit_hints);
         let fiat_shamir_input_var =
             LastFiatShamirInputVar::new_public_input(&cs, &fiat_shamir_input);
-        let decommit_input_var = LastDecommitInputVar::new_public_input(&cs, &decommit_input);
         let fri_answer_hints = AnswerHints::compute(&fiat_shamir_hints, &proof);
 
         let proof_var = LastPlonkWithPoseidonProofVar::new_witness(&cs, &proof);
         let fiat_shamir_results =
-            LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var);
+            LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var, config);
 
         let _last_answer_results = LastAnswerResults::compute(
             &fiat_shamir_hints,
             &decommit_hints,
             &fri_answer_hints,
             &fiat_shamir_results,
-            &decommit_input_var,
             &proof_var,
             config,
         );
@@ -453,20 +390,6 @@ mod test {
         for fri_alpha in fiat_shamir_input.fri_alphas.iter() {
             add_input(&mut inputs, *fri_alpha);
         }
-        for proof in decommit_input
-            .precomputed_proofs
-            .iter()
-            .chain(decommit_input.trace_proofs.iter())
-            .chain(decommit_input.interaction_proofs.iter())
-            .chain(decommit_input.composition_proofs.iter())
-        {
-            for (_, column) in proof.packed_columns.iter() {
-                for elem in column.iter() {
-                    add_input(&mut inputs, *elem);
-                }
-            }
-        }
-
         println!("input length: {} QM31", inputs.len());
 
         let circuit = cs.generate_plonk_without_poseidon_circuit();
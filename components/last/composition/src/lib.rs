@@ -26,18 +26,14 @@ impl LastCompositionCheck {
         let poseidon_tree_subspan = &fiat_shamir_hints.poseidon_tree_subspan;
         let poseidon_prepared_column_indices = &fiat_shamir_hints.poseidon_prepared_column_indices;
 
-        // enforce that the columns are separate, which would be the case if the Poseidon circuit is
-        // much smaller than the Plonk circuit (expected), so there is_first column does not overlap.
-        assert_eq!(
-            *plonk_prepared_column_indices,
-            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
-        );
-        assert_eq!(
-            *poseidon_prepared_column_indices,
-            vec![
-                10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30,
-                31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49,
-            ]
+        // enforce that the components do not share preprocessed columns, which is the case if
+        // the Poseidon circuit is smaller than the Plonk circuit, so that their is_first columns
+        // are separate
+        assert!(
+            plonk_prepared_column_indices
+                .iter()
+                .all(|idx| !poseidon_prepared_column_indices.contains(idx)),
+            "the Plonk and Poseidon components share preprocessed columns"
         );
 
         let mut evaluation_accumulator = PointEvaluationAccumulatorVar::new(random_coeff);
@@ -56,6 +52,8 @@ impl LastCompositionCheck {
 
             EvalAtRowVar::new(
                 mask_points,
+                &fiat_shamir_hints.mask_plonk,
+                &fiat_shamir_hints.plonk_preprocessed_column_ids,
                 proof.stmt1.plonk_total_sum.clone(),
                 coset_vanishing(&oods_point, proof.stmt0.log_size_plonk.value.0).inv(),
                 proof.stmt0.log_size_plonk.value.0,
@@ -78,6 +76,8 @@ impl LastCompositionCheck {
 
             EvalAtRowVar::new(
                 mask_points,
+                &fiat_shamir_hints.mask_poseidon,
+                &fiat_shamir_hints.poseidon_preprocessed_column_ids,
                 proof.stmt1.poseidon_total_sum.clone(),
                 coset_vanishing(&oods_point, proof.stmt0.log_size_poseidon.value.0).inv(),
                 proof.stmt0.log_size_poseidon.value.0,
@@ -158,7 +158,7 @@ mod test {
         let proof_var = LastPlonkWithPoseidonProofVar::new_witness(&cs, &proof);
 
         let fiat_shamir_results =
-            LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var);
+            LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var, config);
         LastCompositionCheck::compute(
             &fiat_shamir_hints,
             &fiat_shamir_results.lookup_elements,
@@ -5,6 +5,8 @@ use circle_plonk_dsl_data_structures::{
 };
 use circle_plonk_dsl_fields::QM31Var;
 use circle_plonk_dsl_line::LinePolyVar;
+use circle_plonk_dsl_sha256::Sha256DigestVar;
+use circle_plonk_dsl_uint::U32Var;
 use stwo_prover::core::pcs::TreeVec;
 use stwo_prover::core::prover::StarkProof;
 use stwo_prover::core::vcs::sha256_poseidon31_merkle::Sha256Poseidon31MerkleHasher;
@@ -46,8 +48,13 @@ impl AllocVar for LastPlonkWithPoseidonProofVar {
 #[derive(Debug, Clone)]
 pub struct LastStarkProofVar {
     pub cs: ConstraintSystemRef,
+    pub commitments: Vec<Sha256DigestVar>,
     pub sampled_values: TreeVec<ColumnVec<Vec<QM31Var>>>,
+    pub first_layer_commitment: Sha256DigestVar,
+    pub inner_layer_commitments: Vec<Sha256DigestVar>,
     pub last_poly: LinePolyVar,
+    /// The lower and upper words of the proof-of-work nonce.
+    pub proof_of_work: [U32Var; 2],
 }
 
 impl Var for LastStarkProofVar {
@@ -60,6 +67,11 @@ impl Var for LastStarkProofVar {
 
 impl AllocVar for LastStarkProofVar {
     fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let mut commitments = Vec::with_capacity(value.commitments.len());
+        for commitment in value.commitments.iter() {
+            commitments.push(Sha256DigestVar::new_variables(cs, &commitment.0, mode));
+        }
+
         let mut sampled_values = TreeVec::new(vec![]);
         for round in value.sampled_values.iter() {
             let mut round_res = ColumnVec::new();
@@ -72,12 +84,32 @@ impl AllocVar for LastStarkProofVar {
             }
             sampled_values.push(round_res);
         }
+        let first_layer_commitment =
+            Sha256DigestVar::new_variables(cs, &value.fri_proof.first_layer.commitment.0, mode);
+        let mut inner_layer_commitments = vec![];
+        for layer in value.fri_proof.inner_layers.iter() {
+            inner_layer_commitments.push(Sha256DigestVar::new_variables(
+                cs,
+                &layer.commitment.0,
+                mode,
+            ));
+        }
         let last_poly = LinePolyVar::new_variables(cs, &value.fri_proof.last_layer_poly, mode);
 
+        let proof_of_work = [
+            value.proof_of_work as u32,
+            (value.proof_of_work >> 32) as u32,
+        ]
+        .map(|word| U32Var::new_variables(cs, &word, mode));
+
         Self {
             cs: cs.clone(),
+            commitments,
             sampled_values,
+            first_layer_commitment,
+            inner_layer_commitments,
             last_poly,
+            proof_of_work,
         }
     }
 }
@@ -1,3 +1,4 @@
+use circle_plonk_dsl_bits::BitsVar;
 use circle_plonk_dsl_channel::HashVar;
 use circle_plonk_dsl_circle::CirclePointQM31Var;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
@@ -7,8 +8,11 @@ use circle_plonk_dsl_fields::{M31Var, QM31Var};
 use circle_plonk_dsl_hints::FiatShamirHints;
 use circle_plonk_dsl_last_data_structures::LastPlonkWithPoseidonProofVar;
 use circle_plonk_dsl_merkle::Poseidon31MerkleHasherVar;
+use circle_plonk_dsl_sha256::{Sha256ChannelVar, Sha256DigestVar};
+use circle_plonk_dsl_uint::U32Var;
 use itertools::Itertools;
 use stwo_prover::core::fields::qm31::QM31;
+use stwo_prover::core::pcs::PcsConfig;
 use stwo_prover::core::vcs::poseidon31_hash::Poseidon31Hash;
 use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
 use stwo_prover::core::vcs::sha256_poseidon31_merkle::{
@@ -26,6 +30,7 @@ pub struct LastFiatShamirInput {
     pub lookup_element_alpha: QM31,
     pub random_coeff: QM31,
     pub after_sampled_values_random_coeff: QM31,
+    pub queries_log_size: u32,
     pub queries_at_max_first_layer_column_log_size: Vec<usize>,
     pub fri_alphas: Vec<QM31>,
 }
@@ -48,6 +53,7 @@ impl LastFiatShamirInput {
         let lookup_element_alpha = fiat_shamir_hints.alpha;
         let random_coeff = fiat_shamir_hints.random_coeff;
         let after_sampled_values_random_coeff = fiat_shamir_hints.after_sampled_values_random_coeff;
+        let queries_log_size = fiat_shamir_hints.max_first_layer_column_log_size;
         let queries_at_max_first_layer_column_log_size = fiat_shamir_hints
             .unsorted_query_positions_per_log_size
             [&fiat_shamir_hints.max_first_layer_column_log_size]
@@ -62,6 +68,7 @@ impl LastFiatShamirInput {
             lookup_element_alpha,
             random_coeff,
             after_sampled_values_random_coeff,
+            queries_log_size,
             queries_at_max_first_layer_column_log_size,
             fri_alphas,
         }
@@ -79,6 +86,7 @@ pub struct LastFiatShamirInputVar {
     pub lookup_element_alpha: QM31Var,
     pub random_coeff: QM31Var,
     pub after_sampled_values_random_coeff: QM31Var,
+    pub queries_log_size: u32,
     pub queries_len: usize,
     pub packed_queries_at_max_first_layer_column_log_size: Vec<QM31Var>,
     pub fri_alphas: Vec<QM31Var>,
@@ -136,6 +144,7 @@ impl AllocVar for LastFiatShamirInputVar {
             lookup_element_alpha,
             random_coeff,
             after_sampled_values_random_coeff,
+            queries_log_size: value.queries_log_size,
             queries_len,
             packed_queries_at_max_first_layer_column_log_size,
             fri_alphas,
@@ -144,6 +153,7 @@ impl AllocVar for LastFiatShamirInputVar {
 }
 
 pub struct LastFiatShamirResults {
+    pub commitments: Vec<Sha256DigestVar>,
     pub oods_point: CirclePointQM31Var,
     pub plonk_total_sum: QM31Var,
     pub poseidon_total_sum: QM31Var,
@@ -155,39 +165,121 @@ pub struct LastFiatShamirResults {
 }
 
 impl LastFiatShamirResults {
+    /// Replays the SHA-256 channel of the proof and checks that it draws the Fiat-Shamir values
+    /// in the input.
     pub fn compute(
         proof_var: &LastPlonkWithPoseidonProofVar,
         last_fiat_shamir_input_var: &LastFiatShamirInputVar,
+        pcs_config: PcsConfig,
     ) -> LastFiatShamirResults {
-        let oods_point = CirclePointQM31Var::from_t(&&last_fiat_shamir_input_var.t);
-        let sampled_values_flattened = proof_var.stark_proof.sampled_values.clone().flatten_cols();
+        let cs = last_fiat_shamir_input_var.cs();
+        let input = last_fiat_shamir_input_var;
+        let proof = &proof_var.stark_proof;
+
+        proof_var
+            .stmt1
+            .plonk_total_sum
+            .equalverify(&input.plonk_total_sum);
+        proof_var
+            .stmt1
+            .poseidon_total_sum
+            .equalverify(&input.poseidon_total_sum);
+
+        let mut channel = Sha256ChannelVar::default(&cs);
+
+        // Preprocessed trace.
+        channel.mix_root(&proof.commitments[0]);
+
+        // Trace, where each log size of the statement is mixed as a u64.
+        for log_size in [
+            &proof_var.stmt0.log_size_plonk,
+            &proof_var.stmt0.log_size_poseidon,
+        ] {
+            channel.mix_u32s(&[U32Var::from_m31(log_size), U32Var::new_constant(&cs, &0)]);
+        }
+        channel.mix_root(&proof.commitments[1]);
+
+        // Draw interaction elements.
+        let lookup_elements = channel.draw_felts(2);
+        lookup_elements[0].equalverify(&input.lookup_element_z);
+        lookup_elements[1].equalverify(&input.lookup_element_alpha);
+
+        // Interaction trace.
+        channel.mix_felts(&[
+            proof_var.stmt1.plonk_total_sum.clone(),
+            proof_var.stmt1.poseidon_total_sum.clone(),
+        ]);
+        channel.mix_root(&proof.commitments[2]);
+
+        channel.draw_felt().equalverify(&input.random_coeff);
+
+        // Read composition polynomial commitment.
+        channel.mix_root(&proof.commitments[3]);
+
+        // Draw OODS point.
+        channel.draw_felt().equalverify(&input.t);
+        let oods_point = CirclePointQM31Var::from_t(&input.t);
+
+        let sampled_values_flattened = proof.sampled_values.clone().flatten_cols();
         let sampled_values_hash =
             Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&sampled_values_flattened);
-        sampled_values_hash.equalverify(&last_fiat_shamir_input_var.sampled_values_hash);
+        sampled_values_hash.equalverify(&input.sampled_values_hash);
+        channel.mix_felts(&sampled_values_flattened);
+
+        channel
+            .draw_felt()
+            .equalverify(&input.after_sampled_values_random_coeff);
+
+        // FRI layers commitments and alphas
+        channel.mix_root(&proof.first_layer_commitment);
+        channel.draw_felt().equalverify(&input.fri_alphas[0]);
+        for (commitment, fri_alpha) in proof
+            .inner_layer_commitments
+            .iter()
+            .zip_eq(input.fri_alphas.iter().skip(1))
+        {
+            channel.mix_root(commitment);
+            channel.draw_felt().equalverify(fri_alpha);
+        }
+        channel.mix_felts(&proof.last_poly.coeffs);
+
+        channel.mix_nonce(&proof.proof_of_work);
+        channel.verify_pow_nonce(pcs_config.pow_bits);
+
         let lookup_elements = LookupElementsVar::from_z_and_alpha(
-            last_fiat_shamir_input_var.lookup_element_z.clone(),
-            last_fiat_shamir_input_var.lookup_element_alpha.clone(),
+            input.lookup_element_z.clone(),
+            input.lookup_element_alpha.clone(),
         );
         let mut queries_at_max_first_layer_column_log_size = vec![];
-        for packed in last_fiat_shamir_input_var
+        for packed in input
             .packed_queries_at_max_first_layer_column_log_size
             .iter()
         {
             queries_at_max_first_layer_column_log_size.extend_from_slice(&packed.decompose_m31());
         }
-        queries_at_max_first_layer_column_log_size.truncate(last_fiat_shamir_input_var.queries_len);
+        queries_at_max_first_layer_column_log_size.truncate(input.queries_len);
+
+        // the queries are the lower bits of the raw queries
+        for (raw_query, query) in channel
+            .draw_raw_queries(input.queries_len)
+            .iter()
+            .zip_eq(queries_at_max_first_layer_column_log_size.iter())
+        {
+            BitsVar::from_m31(raw_query, 31)
+                .compose_range(0..input.queries_log_size as usize)
+                .equalverify(query);
+        }
 
         LastFiatShamirResults {
+            commitments: proof.commitments.clone(),
             oods_point,
-            plonk_total_sum: last_fiat_shamir_input_var.plonk_total_sum.clone(),
-            poseidon_total_sum: last_fiat_shamir_input_var.poseidon_total_sum.clone(),
+            plonk_total_sum: input.plonk_total_sum.clone(),
+            poseidon_total_sum: input.poseidon_total_sum.clone(),
             lookup_elements,
-            random_coeff: last_fiat_shamir_input_var.random_coeff.clone(),
-            after_sampled_values_random_coeff: last_fiat_shamir_input_var
-                .after_sampled_values_random_coeff
-                .clone(),
+            random_coeff: input.random_coeff.clone(),
+            after_sampled_values_random_coeff: input.after_sampled_values_random_coeff.clone(),
             queries_at_max_first_layer_column_log_size,
-            fri_alphas: last_fiat_shamir_input_var.fri_alphas.clone(),
+            fri_alphas: input.fri_alphas.clone(),
         }
     }
 }
@@ -250,7 +342,7 @@ mod test {
             LastFiatShamirInputVar::new_public_input(&cs, &fiat_shamir_input);
         let proof_var = LastPlonkWithPoseidonProofVar::new_witness(&cs, &proof);
 
-        let _res = LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var);
+        let _res = LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var, config);
 
         cs.pad();
         cs.check_arithmetics();
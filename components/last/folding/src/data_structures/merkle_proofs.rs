@@ -245,7 +245,7 @@ impl LastFirstLayerHints {
         let merkle_proofs = LastSinglePairMerkleProof::from_stwo_proof(
             &log_sizes_with_data,
             &fiat_shamir_hints
-                .unsorted_query_positions_per_log_size
+                .query_positions_per_log_size
                 .get(&fiat_shamir_hints.max_first_layer_column_log_size)
                 .unwrap(),
             &decommitmented_values,
@@ -303,7 +303,7 @@ impl LastInnerLayersHints {
 
         let mut folded = BTreeMap::new();
         for i in fiat_shamir_hints
-            .unsorted_query_positions_per_log_size
+            .query_positions_per_log_size
             .get(&log_size)
             .unwrap()
             .iter()
@@ -369,7 +369,7 @@ impl LastInnerLayersHints {
             let merkle_proofs = LastSinglePairMerkleProof::from_stwo_proof(
                 &BTreeSet::from([log_size]),
                 &fiat_shamir_hints
-                    .unsorted_query_positions_per_log_size
+                    .query_positions_per_log_size
                     .get(&fiat_shamir_hints.max_first_layer_column_log_size)
                     .unwrap()
                     .iter()
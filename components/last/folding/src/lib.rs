@@ -33,7 +33,7 @@ impl LastFoldingResults {
             .zip(answer_results.fri_answers.iter())
         {
             for (i, (_, fri_answer)) in fiat_shamir_hints
-                .unsorted_query_positions_per_log_size
+                .query_positions_per_log_size
                 .get(&log_size)
                 .unwrap()
                 .iter()
@@ -52,43 +52,28 @@ impl LastFoldingResults {
         // compute the first layer folding results
         let mut folded_results = BTreeMap::new();
         for &log_size in fiat_shamir_hints.all_log_sizes.iter() {
-            let mut folded_results_per_log_size = Vec::new();
-            for (proof, query) in first_layer_input_var
-                .merkle_proofs
+            let folded_results_per_log_size: Vec<QM31Var> = answer_results
+                .query_positions_per_log_size[log_size]
                 .iter()
-                .zip(answer_results.query_positions_per_log_size[log_size].iter())
-            {
-                let self_val = proof.self_columns.get(&(log_size as usize)).unwrap();
-                let sibling_val = proof.siblings_columns.get(&(log_size as usize)).unwrap();
-
-                let point = query.get_absolute_point().double();
-                let y_inv = point.y.inv();
-
-                let (left_val, right_val) = QM31Var::swap(
-                    &self_val,
-                    &sibling_val,
-                    query.bits.value[0],
-                    query.bits.variables[0],
-                );
-
-                let new_left_val = &left_val + &right_val;
-                let new_right_val = &(&left_val - &right_val) * &y_inv;
-
-                let folded_result = &new_left_val
-                    + &(&new_right_val
-                        * &fiat_shamir_results.fri_alphas[(fiat_shamir_hints
+                .enumerate()
+                .map(|(i, query)| {
+                    let proof = &first_layer_input_var.merkle_proofs[i];
+                    query.fold_circle(
+                        proof.self_columns.get(&(log_size as usize)).unwrap(),
+                        proof.siblings_columns.get(&(log_size as usize)).unwrap(),
+                        &fiat_shamir_results.fri_alphas[(fiat_shamir_hints
                             .max_first_layer_column_log_size
                             - log_size)
-                            as usize]);
-
-                folded_results_per_log_size.push(folded_result);
-            }
+                            as usize],
+                    )
+                })
+                .collect();
             folded_results.insert(log_size, folded_results_per_log_size);
         }
 
         for (log_size, folded_evals) in first_layer_hints.folded_evals_by_column.iter() {
             let folded_queries = fiat_shamir_hints
-                .unsorted_query_positions_per_log_size
+                .query_positions_per_log_size
                 .get(&log_size)
                 .unwrap()
                 .iter()
@@ -121,7 +106,7 @@ impl LastFoldingResults {
 
         let mut folded = Vec::new();
         for _ in 0..fiat_shamir_hints
-            .unsorted_query_positions_per_log_size
+            .query_positions_per_log_size
             .get(&log_size)
             .unwrap()
             .len()
@@ -142,40 +127,27 @@ impl LastFoldingResults {
 
             log_size -= 1;
 
-            let queries = answer_results.query_positions_per_log_size[log_size].clone();
-
             let merkle_proofs = inner_layers_input_var.merkle_proofs.get(&log_size).unwrap();
 
-            let mut new_folded = vec![];
-            for ((folded_result, query), proof) in
-                folded.iter().zip(queries.iter()).zip(merkle_proofs.iter())
-            {
+            // every query checks its own folded value and folds its own Merkle proof, so the
+            // queries at the same position do not depend on each other
+            for (folded_result, proof) in folded.iter().zip(merkle_proofs.iter()) {
                 let self_val = proof.self_columns.get(&(log_size as usize)).unwrap();
-                let sibling_val = proof.siblings_columns.get(&(log_size as usize)).unwrap();
                 folded_result.equalverify(&self_val);
-
-                let mut left_query = query.bits.clone();
-                left_query.value[0] = false;
-                left_query.variables[0] = 0;
-
-                let point = query.get_absolute_point();
-                let x_inv = point.x.inv();
-
-                let (left_val, right_val) = QM31Var::swap(
-                    &self_val,
-                    &sibling_val,
-                    query.bits.value[0],
-                    query.bits.variables[0],
-                );
-
-                let new_left_val = &left_val + &right_val;
-                let new_right_val = &(&left_val - &right_val) * &x_inv;
-
-                let folded_result =
-                    &new_left_val + &(&new_right_val * &fiat_shamir_results.fri_alphas[i + 1]);
-                new_folded.push(folded_result);
             }
-            folded = new_folded;
+
+            folded = answer_results.query_positions_per_log_size[log_size]
+                .iter()
+                .enumerate()
+                .map(|(j, query)| {
+                    let proof = &merkle_proofs[j];
+                    query.fold_line(
+                        proof.self_columns.get(&(log_size as usize)).unwrap(),
+                        proof.siblings_columns.get(&(log_size as usize)).unwrap(),
+                        &fiat_shamir_results.fri_alphas[i + 1],
+                    )
+                })
+                .collect();
         }
 
         let queries = answer_results.query_positions_per_log_size[log_size].clone();
@@ -200,19 +172,25 @@ mod test {
     use crate::LastFoldingResults;
     use circle_plonk_dsl_constraint_system::var::AllocVar;
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::{M31Var, QM31Var};
     use circle_plonk_dsl_hints::{AnswerHints, FiatShamirHints};
-    use circle_plonk_dsl_last_answer::data_structures::{
-        LastDecommitHints, LastDecommitInput, LastDecommitInputVar,
-    };
+    use circle_plonk_dsl_last_answer::data_structures::LastDecommitHints;
     use circle_plonk_dsl_last_answer::LastAnswerResults;
     use circle_plonk_dsl_last_data_structures::LastPlonkWithPoseidonProofVar;
     use circle_plonk_dsl_last_fiat_shamir::{
         LastFiatShamirInput, LastFiatShamirInputVar, LastFiatShamirResults,
     };
+    use circle_plonk_dsl_query::{PointCarryingQueryVar, QueryPositionsPerLogSizeVar};
+    use itertools::Itertools;
     use num_traits::One;
+    use stwo_prover::core::circle::Coset;
+    use stwo_prover::core::fields::m31::M31;
     use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::fields::FieldExpOps;
     use stwo_prover::core::fri::FriConfig;
     use stwo_prover::core::pcs::PcsConfig;
+    use stwo_prover::core::poly::circle::CanonicCoset;
+    use stwo_prover::core::utils::bit_reverse_index;
     use stwo_prover::core::vcs::sha256_merkle::Sha256MerkleChannel;
     use stwo_prover::core::vcs::sha256_poseidon31_merkle::{
         Sha256Poseidon31MerkleChannel, Sha256Poseidon31MerkleHasher,
@@ -265,10 +243,8 @@ mod test {
         );
 
         let fiat_shamir_input = LastFiatShamirInput::from_proof(&proof, &fiat_shamir_hints);
-        let decommit_input = LastDecommitInput::from_hints(&decommit_hints);
         let fiat_shamir_input_var =
             LastFiatShamirInputVar::new_public_input(&cs, &fiat_shamir_input);
-        let decommit_input_var = LastDecommitInputVar::new_public_input(&cs, &decommit_input);
         let first_layer_input_var =
             LastFirstLayerInputVar::new_public_input(&cs, &first_layer_hints);
         let inner_layers_input_var =
@@ -276,14 +252,13 @@ mod test {
 
         let proof_var = LastPlonkWithPoseidonProofVar::new_witness(&cs, &proof);
         let fiat_shamir_results =
-            LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var);
+            LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var, config);
 
         let last_answer_results = LastAnswerResults::compute(
             &fiat_shamir_hints,
             &decommit_hints,
             &fri_answer_hints,
             &fiat_shamir_results,
-            &decommit_input_var,
             &proof_var,
             config,
         );
@@ -361,19 +336,6 @@ mod test {
         for fri_alpha in fiat_shamir_input.fri_alphas.iter() {
             add_input(&mut inputs, *fri_alpha);
         }
-        for proof in decommit_input
-            .precomputed_proofs
-            .iter()
-            .chain(decommit_input.trace_proofs.iter())
-            .chain(decommit_input.interaction_proofs.iter())
-            .chain(decommit_input.composition_proofs.iter())
-        {
-            for (_, column) in proof.packed_columns.iter() {
-                for elem in column.iter() {
-                    add_input(&mut inputs, *elem);
-                }
-            }
-        }
         for proof in first_layer_hints.merkle_proofs.iter() {
             for (_, elem) in proof.self_columns.iter() {
                 add_input(&mut inputs, *elem);
@@ -398,4 +360,58 @@ mod test {
         let proof = prove_plonk_without_poseidon::<Sha256MerkleChannel>(config, &circuit);
         verify_plonk_without_poseidon::<Sha256MerkleChannel>(proof, config, &inputs).unwrap();
     }
+
+    #[test]
+    fn test_last_folding_colliding_queries() {
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+
+        // 5 and 37 are at the same position at log size 5, and 12 meets 13 one log size below
+        let raw_queries = [5u32, 37, 12, 13].map(|q| M31Var::new_witness(&cs, &M31::from(q)));
+        let queries = QueryPositionsPerLogSizeVar::new(4..=5, &raw_queries);
+
+        let alpha = QM31::from_u32_unchecked(3, 5, 7, 11);
+        let alpha_var = QM31Var::new_witness(&cs, &alpha);
+        let eval = |position: usize| QM31::from_u32_unchecked(position as u32, 1, 2, 3);
+        let native_fold = |position: usize, t: M31| {
+            let (left, right) = (eval(position & !1), eval(position | 1));
+            left + right + alpha * (left - right) * t.inverse()
+        };
+
+        // every query folds its own copy of the values at its position, as with its own proof
+        type Fold = fn(&PointCarryingQueryVar, &QM31Var, &QM31Var, &QM31Var) -> QM31Var;
+        let fold = |log_size: u32, f: Fold| {
+            queries[log_size]
+                .iter()
+                .map(|query| {
+                    let position = query.bits.get_value().0 as usize;
+                    let self_val = QM31Var::new_witness(&cs, &eval(position));
+                    let sibling_val = QM31Var::new_witness(&cs, &eval(position ^ 1));
+                    (
+                        position,
+                        f(query, &self_val, &sibling_val, &alpha_var).value,
+                    )
+                })
+                .collect_vec()
+        };
+
+        let circle_domain = CanonicCoset::new(5).circle_domain();
+        let folded = fold(5, PointCarryingQueryVar::fold_circle);
+        for &(position, value) in folded.iter() {
+            let y = circle_domain.at(bit_reverse_index(position & !1, 5)).y;
+            assert_eq!(value, native_fold(position, y));
+        }
+        assert_eq!(folded[0], folded[1]);
+
+        let line_domain = Coset::half_odds(4);
+        let folded = fold(4, PointCarryingQueryVar::fold_line);
+        for &(position, value) in folded.iter() {
+            let x = line_domain.at(bit_reverse_index(position & !1, 4)).x;
+            assert_eq!(value, native_fold(position, x));
+        }
+        assert_eq!(folded[0], folded[1]);
+        assert_eq!(folded[2], folded[3]);
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
 }
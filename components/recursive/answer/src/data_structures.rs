@@ -1,14 +1,19 @@
 use circle_plonk_dsl_circle::{CirclePointM31Var, CirclePointQM31Var};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_domain::CosetVar;
 use circle_plonk_dsl_fields::{CM31Var, M31Var, QM31Var};
 use indexmap::IndexMap;
-use itertools::{izip, zip_eq};
+use itertools::{izip, zip_eq, Itertools};
 use num_traits::Zero;
+use std::collections::HashMap;
 use std::ops::Neg;
+use stwo_prover::constraint_framework::PREPROCESSED_TRACE_IDX;
 use stwo_prover::core::circle::CirclePoint;
 use stwo_prover::core::fields::m31::M31;
 use stwo_prover::core::fields::qm31::QM31;
 use stwo_prover::core::fields::ComplexConjugate;
+use stwo_prover::core::pcs::{TreeSubspan, TreeVec};
+use stwo_prover::core::ColumnVec;
 
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub enum ShiftIndex {
@@ -26,6 +31,58 @@ impl ShiftIndex {
     }
 }
 
+/// Places the points at which the mask of a component is sampled at the component's columns in
+/// `sampled_points`, which is laid out as the commitment trees.
+///
+/// The mask offsets count rows of the component's trace, whose steps are those of `trace_coset`,
+/// from `oods_point`. A preprocessed column must not be shared with a component placed before, as
+/// it could only be sampled at the points of one of them.
+pub fn place_mask_points(
+    sampled_points: &mut TreeVec<ColumnVec<Vec<(ShiftIndex, CirclePointQM31Var)>>>,
+    oods_point: &CirclePointQM31Var,
+    trace_coset: &CosetVar,
+    mask_offsets: &TreeVec<Vec<Vec<isize>>>,
+    tree_subspan: &[TreeSubspan],
+    preprocessed_column_indices: &[usize],
+) {
+    let log_size = trace_coset.log_size();
+    let mut shifted_points = HashMap::<isize, CirclePointQM31Var>::new();
+    let mut mask_points = |offsets: &Vec<isize>| {
+        offsets
+            .iter()
+            .map(|&shift| {
+                let point = shifted_points
+                    .entry(shift)
+                    .or_insert_with(|| trace_coset.shift(oods_point, shift));
+                (ShiftIndex::from_shift(shift, log_size), point.clone())
+            })
+            .collect_vec()
+    };
+
+    for (&idx, offsets) in preprocessed_column_indices
+        .iter()
+        .zip_eq(mask_offsets[PREPROCESSED_TRACE_IDX].iter())
+    {
+        assert!(
+            sampled_points[PREPROCESSED_TRACE_IDX][idx].is_empty(),
+            "the preprocessed column {} is shared by several components",
+            idx
+        );
+        sampled_points[PREPROCESSED_TRACE_IDX][idx] = mask_points(offsets);
+    }
+
+    for subspan in tree_subspan
+        .iter()
+        .filter(|subspan| subspan.tree_index != PREPROCESSED_TRACE_IDX)
+    {
+        let offsets = &mask_offsets[subspan.tree_index];
+        assert_eq!(subspan.col_end - subspan.col_start, offsets.len());
+        for (i, column_offsets) in offsets.iter().enumerate() {
+            sampled_points[subspan.tree_index][subspan.col_start + i] = mask_points(column_offsets);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PointSampleVar {
     pub shift: ShiftIndex,
@@ -195,3 +252,88 @@ pub fn batch_random_coeffs_var(
         .map(|sb| random_coeff.pow(sb.columns_and_values.len() as u128))
         .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use crate::data_structures::{place_mask_points, ShiftIndex};
+    use circle_plonk_dsl_circle::CirclePointQM31Var;
+    use circle_plonk_dsl_composition::data_structures::{
+        EvalAtRowVar, PointEvaluationAccumulatorVar,
+    };
+    use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_domain::CosetVar;
+    use circle_plonk_dsl_fields::QM31Var;
+    use stwo_prover::constraint_framework::ORIGINAL_TRACE_IDX;
+    use stwo_prover::core::backend::CpuBackend;
+    use stwo_prover::core::circle::CirclePoint;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::SecureField;
+    use stwo_prover::core::pcs::{TreeSubspan, TreeVec};
+    use stwo_prover::core::poly::circle::{CanonicCoset, CircleEvaluation};
+    use stwo_prover::core::poly::BitReversedOrder;
+
+    #[test]
+    fn test_mask_offset_round_trip() {
+        const LOG_SIZE: u32 = 4;
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        // a trace column that the component reads at the previous and the current row
+        let values = (0..1u32 << LOG_SIZE)
+            .map(|i| M31::from(i * i + 7))
+            .collect();
+        let poly = CircleEvaluation::<CpuBackend, M31, BitReversedOrder>::new(
+            CanonicCoset::new(LOG_SIZE).circle_domain(),
+            values,
+        )
+        .interpolate();
+        let mask_offsets = TreeVec::new(vec![vec![], vec![vec![-1, 0]]]);
+
+        let oods_point = CirclePoint::<SecureField>::get_point(98765);
+        let prev_point = oods_point + CanonicCoset::new(LOG_SIZE).step().mul_signed(-1).into_ef();
+
+        // the answer stage opens the column at the oods point shifted back by one row
+        let mut sampled_points = TreeVec::new(vec![vec![], vec![vec![]]]);
+        place_mask_points(
+            &mut sampled_points,
+            &CirclePointQM31Var::new_witness(&cs, &oods_point),
+            &CosetVar::canonic(&cs, LOG_SIZE),
+            &mask_offsets,
+            &[TreeSubspan {
+                tree_index: ORIGINAL_TRACE_IDX,
+                col_start: 0,
+                col_end: 1,
+            }],
+            &[],
+        );
+        let points = &sampled_points[ORIGINAL_TRACE_IDX][0];
+        assert_eq!(points[0].0, ShiftIndex::Shift(-1, LOG_SIZE));
+        assert_eq!(points[0].1.value(), prev_point);
+        assert_eq!(points[1].0, ShiftIndex::Zero);
+        assert_eq!(points[1].1.value(), oods_point);
+
+        // the composition reads the values opened at these points back by their offsets
+        let trace_values = vec![points
+            .iter()
+            .map(|(_, point)| QM31Var::new_witness(&cs, &poly.eval_at_point(point.value())))
+            .collect::<Vec<_>>()];
+        let sampled_values = TreeVec::new(vec![vec![], trace_values.iter().collect()]);
+
+        let mut evaluation_accumulator = PointEvaluationAccumulatorVar::new(QM31Var::one(&cs));
+        let mut eval = EvalAtRowVar::new(
+            sampled_values,
+            &mask_offsets,
+            &[],
+            QM31Var::zero(&cs),
+            QM31Var::one(&cs),
+            LOG_SIZE,
+            &mut evaluation_accumulator,
+        );
+        let [prev, cur] = eval.next_interaction_mask(ORIGINAL_TRACE_IDX, [-1, 0]);
+        assert_eq!(prev.value(), poly.eval_at_point(prev_point));
+        assert_eq!(cur.value(), poly.eval_at_point(oods_point));
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -1,23 +1,23 @@
 use crate::data_structures::{
-    accumulate_row_quotients_var, quotient_constants_var, ColumnSampleBatchVar, PointSampleVar,
-    ShiftIndex,
+    accumulate_row_quotients_var, place_mask_points, quotient_constants_var, ColumnSampleBatchVar,
+    PointSampleVar, ShiftIndex,
 };
 use circle_plonk_dsl_circle::{CirclePointM31Var, CirclePointQM31Var};
 use circle_plonk_dsl_constraint_system::var::Var;
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
-use circle_plonk_dsl_data_structures::{DecommitmentVar, PlonkWithPoseidonProofVar};
+use circle_plonk_dsl_data_structures::{
+    DecommitmentVar, PlonkWithPoseidonProofVar, SinglePathMerkleProofVar,
+};
+use circle_plonk_dsl_domain::CosetVar;
 use circle_plonk_dsl_fiat_shamir::FiatShamirResults;
 use circle_plonk_dsl_fields::{M31Var, QM31Var};
 use circle_plonk_dsl_hints::{AnswerHints, DecommitHints, FiatShamirHints};
-use circle_plonk_dsl_query::{PointCarryingQueryVar, QueryPositionsPerLogSizeVar};
+use circle_plonk_dsl_query::QueryPositionsPerLogSizeVar;
 use itertools::{izip, multiunzip, Itertools};
 use std::cmp::Reverse;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::BTreeMap;
 use std::iter::zip;
-use std::ops::Add;
-use stwo_prover::constraint_framework::PREPROCESSED_TRACE_IDX;
 use stwo_prover::core::pcs::{PcsConfig, TreeVec};
-use stwo_prover::core::poly::circle::CanonicCoset;
 use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
 use stwo_prover::core::ColumnVec;
 
@@ -42,124 +42,47 @@ impl AnswerResults {
     ) -> AnswerResults {
         let cs = oods_point.cs();
 
-        let mut all_shifts_plonk = HashSet::new();
-        let mut all_shifts_poseidon = HashSet::new();
-        for round in fiat_shamir_hints.mask_plonk.iter() {
-            for column in round.iter() {
-                for &shift in column.iter() {
-                    all_shifts_plonk.insert(shift);
-                }
-            }
-        }
-        for round in fiat_shamir_hints.mask_poseidon.iter() {
-            for column in round.iter() {
-                for &shift in column.iter() {
-                    all_shifts_poseidon.insert(shift);
-                }
-            }
-        }
-
-        let trace_step_plonk = CanonicCoset::new(fiat_shamir_hints.log_size_plonk).step();
-        let trace_step_poseidon = CanonicCoset::new(fiat_shamir_hints.log_size_poseidon).step();
-
-        let mut shifted_points_plonk = HashMap::<isize, CirclePointQM31Var>::new();
-        let mut shifted_points_poseidon = HashMap::<isize, CirclePointQM31Var>::new();
-        for &i in all_shifts_plonk.iter() {
-            shifted_points_plonk.insert(i, oods_point.add(&trace_step_plonk.mul_signed(i)));
-        }
-        for &i in all_shifts_poseidon.iter() {
-            shifted_points_poseidon.insert(i, oods_point.add(&trace_step_poseidon.mul_signed(i)));
-        }
+        let trace_coset_plonk = CosetVar::canonic(&cs, fiat_shamir_hints.log_size_plonk);
+        let trace_coset_poseidon = CosetVar::canonic(&cs, fiat_shamir_hints.log_size_poseidon);
 
-        let mut mask_points_plonk: TreeVec<ColumnVec<Vec<(ShiftIndex, CirclePointQM31Var)>>> =
-            fiat_shamir_hints.mask_plonk.as_ref().map_cols(|column| {
-                column
+        let mut sampled_points: TreeVec<ColumnVec<Vec<(ShiftIndex, CirclePointQM31Var)>>> =
+            TreeVec::new(
+                fiat_shamir_hints
+                    .sample_points
                     .iter()
-                    .map(|shift| {
-                        (
-                            ShiftIndex::from_shift(*shift, fiat_shamir_hints.log_size_plonk),
-                            shifted_points_plonk.get(shift).unwrap().clone(),
-                        )
-                    })
-                    .collect_vec()
-            });
-        mask_points_plonk[PREPROCESSED_TRACE_IDX] =
-            vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 10];
-        let mut mask_points_poseidon: TreeVec<ColumnVec<Vec<(ShiftIndex, CirclePointQM31Var)>>> =
-            fiat_shamir_hints.mask_poseidon.as_ref().map_cols(|column| {
-                column
-                    .iter()
-                    .map(|shift| {
-                        (
-                            ShiftIndex::from_shift(*shift, fiat_shamir_hints.log_size_poseidon),
-                            shifted_points_poseidon.get(shift).unwrap().clone(),
-                        )
-                    })
-                    .collect_vec()
-            });
-        mask_points_poseidon[PREPROCESSED_TRACE_IDX] =
-            vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 40];
-
-        assert_eq!(
-            mask_points_plonk.len(),
-            fiat_shamir_hints.sample_points.len() - 1
+                    .take(fiat_shamir_hints.sample_points.len() - 1)
+                    .map(|tree| vec![vec![]; tree.len()])
+                    .collect_vec(),
+            );
+        place_mask_points(
+            &mut sampled_points,
+            oods_point,
+            &trace_coset_plonk,
+            &fiat_shamir_hints.mask_plonk,
+            &fiat_shamir_hints.plonk_tree_subspan,
+            &fiat_shamir_hints.plonk_prepared_column_indices,
+        );
+        place_mask_points(
+            &mut sampled_points,
+            oods_point,
+            &trace_coset_poseidon,
+            &fiat_shamir_hints.mask_poseidon,
+            &fiat_shamir_hints.poseidon_tree_subspan,
+            &fiat_shamir_hints.poseidon_prepared_column_indices,
         );
-        for (round_idx, (round_plonk, round_poseidon)) in mask_points_plonk
+        sampled_points.push(vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 4]);
+
+        for (column, expected_column) in sampled_points
             .iter()
-            .zip(mask_points_poseidon.iter())
-            .enumerate()
-            .take(3)
+            .flatten()
+            .zip_eq(fiat_shamir_hints.sample_points.iter().flatten())
         {
-            assert_eq!(
-                round_plonk.len() + round_poseidon.len(),
-                fiat_shamir_hints.sample_points[round_idx].len(),
-                "round_idx = {}",
-                round_idx
-            );
-            for (column_idx, column) in round_plonk.iter().enumerate() {
-                assert_eq!(
-                    column.len(),
-                    fiat_shamir_hints.sample_points[round_idx][column_idx].len()
-                );
-                for (shift_idx, (_, shifted_point)) in column.iter().enumerate() {
-                    assert_eq!(
-                        shifted_point.x.value(),
-                        fiat_shamir_hints.sample_points[round_idx][column_idx][shift_idx].x
-                    );
-                    assert_eq!(
-                        shifted_point.y.value(),
-                        fiat_shamir_hints.sample_points[round_idx][column_idx][shift_idx].y
-                    );
-                }
-            }
-
-            for (column_idx, column) in round_poseidon.iter().enumerate() {
-                assert_eq!(
-                    column.len(),
-                    fiat_shamir_hints.sample_points[round_idx][round_plonk.len() + column_idx]
-                        .len()
-                );
-                for (shift_idx, (_, shifted_point)) in column.iter().enumerate() {
-                    assert_eq!(
-                        shifted_point.x.value(),
-                        fiat_shamir_hints.sample_points[round_idx][round_plonk.len() + column_idx]
-                            [shift_idx]
-                            .x
-                    );
-                    assert_eq!(
-                        shifted_point.y.value(),
-                        fiat_shamir_hints.sample_points[round_idx][round_plonk.len() + column_idx]
-                            [shift_idx]
-                            .y
-                    );
-                }
+            assert_eq!(column.len(), expected_column.len());
+            for ((_, point), expected_point) in column.iter().zip(expected_column.iter()) {
+                assert_eq!(point.value(), *expected_point);
             }
         }
 
-        let mut sampled_points =
-            TreeVec::concat_cols([mask_points_plonk, mask_points_poseidon].into_iter());
-        sampled_points.push(vec![vec![(ShiftIndex::Zero, oods_point.clone())]; 4]);
-
         let samples = sampled_points
             .zip_cols(proof.stark_proof.sampled_values.clone())
             .map_cols(|(sampled_points, sampled_values)| {
@@ -172,27 +95,22 @@ impl AnswerResults {
                     .collect_vec()
             });
 
-        let query_positions_per_log_size = QueryPositionsPerLogSizeVar::new(
-            pcs_config.fri_config.log_last_layer_degree_bound
-                + pcs_config.fri_config.log_blowup_factor
-                + 1..=fiat_shamir_hints.max_first_layer_column_log_size,
+        let query_positions_per_log_size = QueryPositionsPerLogSizeVar::from_pcs_config(
+            pcs_config,
+            &fiat_shamir_hints.all_log_sizes,
             &fiat_shamir_results.raw_queries,
         );
 
+        // the distinct positions are those of the slots that start a new position
         for &column_log_size in fiat_shamir_hints.all_log_sizes.iter() {
-            let mut sorted_queries = vec![];
-            for query in query_positions_per_log_size[column_log_size].iter() {
-                sorted_queries.push(query.bits.get_value().0 as usize);
-            }
-            sorted_queries.sort_unstable();
-            sorted_queries.dedup();
-
-            if column_log_size == fiat_shamir_hints.max_first_layer_column_log_size {
-                assert_eq!(sorted_queries.len(), pcs_config.fri_config.n_queries,
-                    "The implementation does not support the situation when the first {} attempts in sampling queries end up duplicated queries",
-                           pcs_config.fri_config.n_queries
-                );
-            }
+            let sorted = query_positions_per_log_size.sorted_positions(column_log_size);
+            let sorted_queries = sorted
+                .positions
+                .iter()
+                .zip(sorted.is_first.iter())
+                .filter(|(_, is_first)| is_first.value.0 == 1)
+                .map(|(position, _)| position.value.0 as usize)
+                .collect_vec();
 
             assert_eq!(
                 sorted_queries,
@@ -200,61 +118,48 @@ impl AnswerResults {
             );
         }
         for &column_log_size in fiat_shamir_hints.all_log_sizes.iter() {
-            let mut unsorted_queries = vec![];
+            let mut queries = vec![];
             for query in query_positions_per_log_size[column_log_size].iter() {
-                unsorted_queries.push(query.bits.get_value().0 as usize);
+                queries.push(query.bits.get_value().0 as usize);
             }
 
             assert_eq!(
-                unsorted_queries,
-                fiat_shamir_hints.unsorted_query_positions_per_log_size[&column_log_size]
+                queries,
+                fiat_shamir_hints.query_positions_per_log_size[&column_log_size]
             );
         }
 
-        let mut decommitment_var = DecommitmentVar::new(&cs, &decommit_hints);
-        for (i, query) in query_positions_per_log_size[*fiat_shamir_hints.trees_log_sizes[0]
-            .iter()
-            .max()
-            .unwrap()
-            + fiat_shamir_hints.log_blowup_factor]
-            .iter()
-            .enumerate()
-        {
-            decommitment_var.precomputed_proofs[i]
-                .verify(&fiat_shamir_results.preprocessed_commitment, &query.bits);
-        }
-
-        for (i, query) in query_positions_per_log_size[*fiat_shamir_hints.trees_log_sizes[1]
-            .iter()
-            .max()
-            .unwrap()
-            + fiat_shamir_hints.log_blowup_factor]
-            .iter()
-            .enumerate()
-        {
-            decommitment_var.trace_proofs[i]
-                .verify(&fiat_shamir_results.trace_commitment, &query.bits);
-        }
-        for (i, query) in query_positions_per_log_size[*fiat_shamir_hints.trees_log_sizes[2]
-            .iter()
-            .max()
-            .unwrap()
-            + fiat_shamir_hints.log_blowup_factor]
-            .iter()
-            .enumerate()
-        {
-            decommitment_var.interaction_proofs[i].verify(
+        let decommitment_var = DecommitmentVar::new(&cs, &decommit_hints);
+        for (tree_idx, (proofs, commitment)) in [
+            (
+                &decommitment_var.precomputed_proofs,
+                &fiat_shamir_results.preprocessed_commitment,
+            ),
+            (
+                &decommitment_var.trace_proofs,
+                &fiat_shamir_results.trace_commitment,
+            ),
+            (
+                &decommitment_var.interaction_proofs,
                 &fiat_shamir_results.interaction_trace_commitment,
-                &query.bits,
-            );
-        }
-        for (i, query) in query_positions_per_log_size
-            [fiat_shamir_hints.max_first_layer_column_log_size]
-            .iter()
-            .enumerate()
+            ),
+            (
+                &decommitment_var.composition_proofs,
+                &fiat_shamir_results.composition_commitment,
+            ),
+        ]
+        .into_iter()
+        .enumerate()
         {
-            decommitment_var.composition_proofs[i]
-                .verify(&fiat_shamir_results.composition_commitment, &query.bits);
+            let max_log_size = *fiat_shamir_hints.n_columns_per_log_size[tree_idx]
+                .keys()
+                .max()
+                .unwrap();
+            let queries = query_positions_per_log_size[max_log_size]
+                .iter()
+                .map(|query| query.bits.clone())
+                .collect_vec();
+            SinglePathMerkleProofVar::verify_all(proofs, commitment, &queries);
         }
 
         let mut queried_values = BTreeMap::new();
@@ -307,7 +212,8 @@ impl AnswerResults {
                 Self::fri_answers_for_log_size(
                     &samples,
                     &fiat_shamir_results.after_sampled_values_random_coeff,
-                    &query_positions_per_log_size[log_size],
+                    &query_positions_per_log_size,
+                    log_size,
                     &queried_values[&log_size],
                 );
             domain_points.push(domain_points_per_log_size);
@@ -353,32 +259,33 @@ impl AnswerResults {
         }
     }
 
+    /// Computes the quotients at the queries of `log_size`, once per query, so that the queries
+    /// at the same position are each checked against their own queried values.
     pub fn fri_answers_for_log_size(
         samples: &[&Vec<PointSampleVar>],
         random_coeff: &QM31Var,
-        query_positions: &[PointCarryingQueryVar],
+        query_positions: &QueryPositionsPerLogSizeVar,
+        log_size: u32,
         queried_values: &[Vec<M31Var>],
     ) -> (Vec<CirclePointM31Var>, Vec<QM31Var>) {
         let sample_batches = ColumnSampleBatchVar::new_vec(samples);
         // TODO(ilya): Is it ok to use the same `random_coeff` for all log sizes.
         let quotient_constants = quotient_constants_var(&sample_batches, random_coeff);
 
-        let mut domain_points_at_queries = Vec::new();
-        let mut quotient_evals_at_queries = Vec::new();
-        for (query_position, queried_values_at_row) in
-            query_positions.iter().zip(queried_values.iter())
-        {
-            let domain_point = query_position.get_next_point();
-            quotient_evals_at_queries.push(accumulate_row_quotients_var(
-                &sample_batches,
-                &queried_values_at_row,
-                &quotient_constants,
-                &domain_point,
-            ));
-            domain_points_at_queries.push(domain_point);
-        }
-
-        (domain_points_at_queries, quotient_evals_at_queries)
+        query_positions[log_size]
+            .iter()
+            .enumerate()
+            .map(|(i, query_position)| {
+                let domain_point = query_position.get_next_point();
+                let quotient_eval = accumulate_row_quotients_var(
+                    &sample_batches,
+                    &queried_values[i],
+                    &quotient_constants,
+                    &domain_point,
+                );
+                (domain_point, quotient_eval)
+            })
+            .unzip()
     }
 }
 
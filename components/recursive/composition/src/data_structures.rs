@@ -82,22 +82,42 @@ impl Drop for LogupAtRowVar {
 pub struct EvalAtRowVar<'a> {
     pub col_index: [usize; 4],
     pub mask: TreeVec<ColumnVec<&'a Vec<QM31Var>>>,
+    pub mask_offsets: &'a TreeVec<Vec<Vec<isize>>>,
+    pub preprocessed_columns: &'a [PreProcessedColumnId],
     pub logup: LogupAtRowVar,
     pub denom_inverse: QM31Var,
     pub evaluation_accumulator: &'a mut PointEvaluationAccumulatorVar,
 }
 
 impl<'a> EvalAtRowVar<'a> {
+    /// Creates the evaluator of a component from its sampled values, which are sampled at the
+    /// `mask_offsets` of each column, with the preprocessed columns in the order of
+    /// `preprocessed_columns`.
     pub fn new(
         sampled_values: TreeVec<ColumnVec<&'a Vec<QM31Var>>>,
+        mask_offsets: &'a TreeVec<Vec<Vec<isize>>>,
+        preprocessed_columns: &'a [PreProcessedColumnId],
         total_sum: QM31Var,
         denom_inverse: QM31Var,
         log_size: u32,
         evaluation_accumulator: &'a mut PointEvaluationAccumulatorVar,
     ) -> Self {
+        for (values, offsets) in sampled_values.iter().zip(mask_offsets.iter()) {
+            assert_eq!(values.len(), offsets.len());
+            for (column_values, column_offsets) in values.iter().zip(offsets.iter()) {
+                assert_eq!(column_values.len(), column_offsets.len());
+            }
+        }
+        assert_eq!(
+            preprocessed_columns.len(),
+            sampled_values[PREPROCESSED_TRACE_IDX].len()
+        );
+
         Self {
             col_index: [0usize; 4],
             mask: sampled_values,
+            mask_offsets,
+            preprocessed_columns,
             logup: LogupAtRowVar::new(INTERACTION_TRACE_IDX, total_sum, log_size),
             denom_inverse,
             evaluation_accumulator,
@@ -109,22 +129,54 @@ impl<'a> EvalAtRowVar<'a> {
         mask_item
     }
 
-    pub fn get_preprocessed_column(&mut self, _column: PreProcessedColumnId) -> QM31Var {
-        let [mask_item] = self.next_interaction_mask(PREPROCESSED_TRACE_IDX, [0]);
+    pub fn get_preprocessed_column(&mut self, column: PreProcessedColumnId) -> QM31Var {
+        let col_index = self
+            .preprocessed_columns
+            .iter()
+            .position(|id| *id == column);
+        assert!(
+            col_index.is_some(),
+            "the preprocessed column {:?} is not used by the component",
+            column
+        );
+
+        let [mask_item] = self.mask_at(PREPROCESSED_TRACE_IDX, col_index.unwrap(), [0]);
         mask_item
     }
 
     pub fn next_interaction_mask<const N: usize>(
         &mut self,
         interaction: usize,
-        _offsets: [isize; N],
+        offsets: [isize; N],
     ) -> [QM31Var; N] {
         let col_index = self.col_index[interaction];
         self.col_index[interaction] += 1;
 
-        let mask = self.mask[interaction][col_index].clone();
-        assert_eq!(mask.len(), N);
-        mask.try_into().unwrap()
+        self.mask_at(interaction, col_index, offsets)
+    }
+
+    /// Returns the values of a column at the rows `offsets` away from the current row, which
+    /// must be among the offsets that the column is sampled at.
+    fn mask_at<const N: usize>(
+        &self,
+        interaction: usize,
+        col_index: usize,
+        offsets: [isize; N],
+    ) -> [QM31Var; N] {
+        let column_values = self.mask[interaction][col_index];
+        let column_offsets = &self.mask_offsets[interaction][col_index];
+
+        offsets.map(|offset| {
+            let position = column_offsets.iter().position(|&v| v == offset);
+            assert!(
+                position.is_some(),
+                "the offset {} is not sampled for column {} of interaction {}",
+                offset,
+                col_index,
+                interaction
+            );
+            column_values[position.unwrap()].clone()
+        })
     }
 
     pub fn next_extension_interaction_mask<const N: usize>(
@@ -213,3 +265,101 @@ impl<'a> EvalAtRowVar<'a> {
         self.finalize_logup(2)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::data_structures::{EvalAtRowVar, PointEvaluationAccumulatorVar};
+    use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::QM31Var;
+    use num_traits::One;
+    use stwo_prover::constraint_framework::preprocessed_columns::PreProcessedColumnId;
+    use stwo_prover::constraint_framework::{
+        INTERACTION_TRACE_IDX, ORIGINAL_TRACE_IDX, PREPROCESSED_TRACE_IDX,
+    };
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::pcs::TreeVec;
+
+    #[test]
+    fn test_mask_offsets() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let new_value = |v: u32| QM31Var::new_witness(&cs, &QM31::from_u32_unchecked(v, 0, 0, 0));
+        let preprocessed_values = vec![vec![new_value(1)], vec![new_value(2)]];
+        let trace_values = vec![
+            vec![new_value(3), new_value(4), new_value(5)],
+            vec![new_value(6)],
+        ];
+        let interaction_values = vec![vec![new_value(7), new_value(8)]];
+
+        let sampled_values = TreeVec::new(vec![
+            preprocessed_values.iter().collect(),
+            trace_values.iter().collect(),
+            interaction_values.iter().collect(),
+        ]);
+        let mask_offsets = TreeVec::new(vec![
+            vec![vec![0], vec![0]],
+            vec![vec![-1, 0, 1], vec![0]],
+            vec![vec![-1, 0]],
+        ]);
+        let preprocessed_columns = [
+            PreProcessedColumnId {
+                id: "first".to_string(),
+            },
+            PreProcessedColumnId {
+                id: "second".to_string(),
+            },
+        ];
+
+        let mut evaluation_accumulator = PointEvaluationAccumulatorVar::new(QM31Var::one(&cs));
+        let mut eval = EvalAtRowVar::new(
+            sampled_values,
+            &mask_offsets,
+            &preprocessed_columns,
+            QM31Var::zero(&cs),
+            QM31Var::one(&cs),
+            4,
+            &mut evaluation_accumulator,
+        );
+
+        let second = eval.get_preprocessed_column(preprocessed_columns[1].clone());
+        let first = eval.get_preprocessed_column(preprocessed_columns[0].clone());
+        assert_eq!(first.value(), QM31::from_u32_unchecked(1, 0, 0, 0));
+        assert_eq!(second.value(), QM31::from_u32_unchecked(2, 0, 0, 0));
+
+        let [next, prev] = eval.next_interaction_mask(ORIGINAL_TRACE_IDX, [1, -1]);
+        assert_eq!(next.value(), QM31::from_u32_unchecked(5, 0, 0, 0));
+        assert_eq!(prev.value(), QM31::from_u32_unchecked(3, 0, 0, 0));
+        assert_eq!(
+            eval.next_trace_mask().value(),
+            QM31::from_u32_unchecked(6, 0, 0, 0)
+        );
+
+        let [cur, prev] = eval.next_interaction_mask(INTERACTION_TRACE_IDX, [0, -1]);
+        assert_eq!(cur.value(), QM31::from_u32_unchecked(8, 0, 0, 0));
+        assert_eq!(prev.value(), QM31::from_u32_unchecked(7, 0, 0, 0));
+        assert_eq!(eval.col_index[PREPROCESSED_TRACE_IDX], 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not sampled")]
+    fn test_mask_offset_not_sampled() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let trace_values = vec![vec![QM31Var::one(&cs)]];
+        let sampled_values = TreeVec::new(vec![vec![], trace_values.iter().collect()]);
+        let mask_offsets = TreeVec::new(vec![vec![], vec![vec![0]]]);
+
+        let mut evaluation_accumulator = PointEvaluationAccumulatorVar::new(QM31Var::one(&cs));
+        let mut eval = EvalAtRowVar::new(
+            sampled_values,
+            &mask_offsets,
+            &[],
+            QM31Var::zero(&cs),
+            QM31Var::one(&cs),
+            4,
+            &mut evaluation_accumulator,
+        );
+        let _ = eval.next_interaction_mask(ORIGINAL_TRACE_IDX, [-1]);
+    }
+}
@@ -4,11 +4,11 @@ use crate::poseidon::evaluate_poseidon;
 use circle_plonk_dsl_circle::CirclePointQM31Var;
 use circle_plonk_dsl_constraint_system::var::Var;
 use circle_plonk_dsl_data_structures::{LookupElementsVar, PlonkWithPoseidonProofVar};
-use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use circle_plonk_dsl_domain::CosetVar;
+use circle_plonk_dsl_fields::QM31Var;
 use circle_plonk_dsl_hints::FiatShamirHints;
 use itertools::Itertools;
 use stwo_prover::constraint_framework::PREPROCESSED_TRACE_IDX;
-use stwo_prover::core::poly::circle::CanonicCoset;
 use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
 
 pub mod data_structures;
@@ -16,16 +16,7 @@ pub mod plonk;
 pub mod poseidon;
 
 pub fn coset_vanishing(p: &CirclePointQM31Var, coset_log_size: u32) -> QM31Var {
-    let cs = p.cs();
-    let coset = CanonicCoset::new(coset_log_size).coset;
-    let mut x = (p + &(-coset.initial + coset.step_size.half().to_point())).x;
-
-    // The formula for the x coordinate of the double of a point.
-    for _ in 1..coset.log_size {
-        let sq = &x * &x;
-        x = &(&sq + &sq) - &M31Var::one(&cs);
-    }
-    x
+    CosetVar::canonic(&p.cs(), coset_log_size).vanishing_at(p)
 }
 
 pub struct CompositionCheck;
@@ -43,18 +34,14 @@ impl CompositionCheck {
         let poseidon_tree_subspan = &fiat_shamir_hints.poseidon_tree_subspan;
         let poseidon_prepared_column_indices = &fiat_shamir_hints.poseidon_prepared_column_indices;
 
-        // enforce that the columns are separate, which would be the case if the Poseidon circuit is
-        // much smaller than the Plonk circuit (expected), so there is_first column does not overlap.
-        assert_eq!(
-            *plonk_prepared_column_indices,
-            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
-        );
-        assert_eq!(
-            *poseidon_prepared_column_indices,
-            vec![
-                10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30,
-                31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49,
-            ]
+        // enforce that the components do not share preprocessed columns, which is the case if
+        // the Poseidon circuit is smaller than the Plonk circuit, so that their is_first columns
+        // are separate
+        assert!(
+            plonk_prepared_column_indices
+                .iter()
+                .all(|idx| !poseidon_prepared_column_indices.contains(idx)),
+            "the Plonk and Poseidon components share preprocessed columns"
         );
 
         let mut evaluation_accumulator = PointEvaluationAccumulatorVar::new(random_coeff);
@@ -73,6 +60,8 @@ impl CompositionCheck {
 
             EvalAtRowVar::new(
                 mask_points,
+                &fiat_shamir_hints.mask_plonk,
+                &fiat_shamir_hints.plonk_preprocessed_column_ids,
                 proof.stmt1.plonk_total_sum.clone(),
                 coset_vanishing(&oods_point, proof.stmt0.log_size_plonk.value.0).inv(),
                 proof.stmt0.log_size_plonk.value.0,
@@ -95,6 +84,8 @@ impl CompositionCheck {
 
             EvalAtRowVar::new(
                 mask_points,
+                &fiat_shamir_hints.mask_poseidon,
+                &fiat_shamir_hints.poseidon_preprocessed_column_ids,
                 proof.stmt1.poseidon_total_sum.clone(),
                 coset_vanishing(&oods_point, proof.stmt0.log_size_poseidon.value.0).inv(),
                 proof.stmt0.log_size_poseidon.value.0,
@@ -1,11 +1,14 @@
 use circle_plonk_dsl_bits::BitsVar;
-use circle_plonk_dsl_channel::{ChannelVar, HashVar};
+use circle_plonk_dsl_channel::{split_u64, ChannelVar, HashVar};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_fields::{M31Var, QM31Var};
 use circle_plonk_dsl_hints::{DecommitHints, SinglePairMerkleProof, SinglePathMerkleProof};
 use circle_plonk_dsl_line::LinePolyVar;
-use circle_plonk_dsl_merkle::Poseidon31MerkleHasherVar;
+use circle_plonk_dsl_merkle::{
+    verify_merkle_multi_proof, verify_single_pair_merkle_proof, verify_single_path_merkle_proof,
+    Poseidon31MerkleHasherVar,
+};
 use std::collections::BTreeMap;
 use stwo_prover::core::fields::m31::M31;
 use stwo_prover::core::fri::FriProof;
@@ -194,23 +197,8 @@ impl AllocVar for StarkProofVar {
 
         let fri_proof = FriProofVar::new_variables(cs, &value.fri_proof, mode);
 
-        let proof_of_work = [
-            M31Var::new_variables(
-                cs,
-                &M31::from((value.proof_of_work % ((1 << 22) - 1)) as u32),
-                mode,
-            ),
-            M31Var::new_variables(
-                cs,
-                &M31::from(((value.proof_of_work >> 22) & ((1 << 21) - 1)) as u32),
-                mode,
-            ),
-            M31Var::new_variables(
-                cs,
-                &M31::from(((value.proof_of_work >> 43) & ((1 << 21) - 1)) as u32),
-                mode,
-            ),
-        ];
+        let proof_of_work = split_u64(value.proof_of_work)
+            .map(|limb| M31Var::new_variables(cs, &M31::from(limb), mode));
 
         Self {
             cs: cs.clone(),
@@ -288,7 +276,7 @@ impl SinglePathMerkleProofVar {
     pub fn new(cs: &ConstraintSystemRef, value: &SinglePathMerkleProof) -> Self {
         let mut sibling_hashes = vec![];
         for sibling_hash in value.sibling_hashes.iter() {
-            sibling_hashes.push(HashVar::new_single_use_witness_only(&cs, &sibling_hash.0));
+            sibling_hashes.push(HashVar::new_witness(&cs, &sibling_hash.0));
         }
 
         let mut columns = BTreeMap::new();
@@ -318,39 +306,50 @@ impl SinglePathMerkleProofVar {
         assert_eq!(root.value(), self.value.root.0);
         assert_eq!(query.get_value().0, self.value.query as u32);
 
-        let mut cur_hash = Poseidon31MerkleHasherVar::hash_m31_columns_get_rate(
-            &self.columns.get(&self.value.depth).unwrap_or(&vec![]),
+        verify_single_path_merkle_proof::<Poseidon31MerkleHasherVar>(
+            root,
+            query,
+            self.value.depth,
+            &self.sibling_hashes,
+            &self.columns,
         );
+    }
 
-        for i in 0..self.value.depth {
-            let h = self.value.depth - i - 1;
-
-            if self.columns.contains_key(&h) {
-                let mut column_hash = Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(
-                    &self.columns.get(&h).unwrap_or(&vec![]),
-                );
-                cur_hash = Poseidon31MerkleHasherVar::hash_tree_with_column_hash_with_swap(
-                    &mut cur_hash,
-                    &mut self.sibling_hashes[i],
-                    query.value[i],
-                    query.variables[i],
-                    &mut column_hash,
-                );
-            } else {
-                cur_hash = Poseidon31MerkleHasherVar::hash_tree_with_swap(
-                    &mut cur_hash,
-                    &mut self.sibling_hashes[i],
-                    query.value[i],
-                    query.variables[i],
-                );
-            }
+    /// Verifies the proofs of all the queries together with `verify_merkle_multi_proof`, where
+    /// the proofs are in the order of the queries, which are in increasing order.
+    pub fn verify_all(proofs: &[Self], root: &HashVar, queries: &[BitsVar]) {
+        assert_eq!(proofs.len(), queries.len());
+        for (proof, query) in proofs.iter().zip(queries.iter()) {
+            proof.value.verify();
+            assert_eq!(root.value(), proof.value.root.0);
+            assert_eq!(query.get_value().0, proof.value.query as u32);
         }
 
-        assert_eq!(cur_hash.value(), root.value());
-
-        // check that the left_variable and right_variable are the same
-        // as though in self.root
-        cur_hash.equalverify(&root);
+        let depth = proofs[0].value.depth;
+        let sibling_hashes = proofs
+            .iter()
+            .map(|proof| {
+                proof
+                    .sibling_hashes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, hash)| (depth - i, hash.clone()))
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+        let columns = proofs
+            .iter()
+            .map(|proof| proof.columns.clone())
+            .collect::<Vec<_>>();
+
+        verify_merkle_multi_proof::<Poseidon31MerkleHasherVar, M31Var>(
+            root,
+            queries,
+            depth,
+            &sibling_hashes,
+            &columns,
+            &vec![BTreeMap::new(); proofs.len()],
+        );
     }
 }
 
@@ -375,7 +374,7 @@ impl SinglePairMerkleProofVar {
     pub fn new(cs: &ConstraintSystemRef, value: &SinglePairMerkleProof) -> Self {
         let mut sibling_hashes = vec![];
         for sibling_hash in value.sibling_hashes.iter() {
-            sibling_hashes.push(HashVar::new_single_use_witness_only(&cs, &sibling_hash.0));
+            sibling_hashes.push(HashVar::new_witness(&cs, &sibling_hash.0));
         }
 
         let mut self_columns = BTreeMap::new();
@@ -403,64 +402,63 @@ impl SinglePairMerkleProofVar {
         assert_eq!(root.value(), self.value.root.0);
         assert_eq!(query.get_value().0, self.value.query as u32);
 
-        let cs = self.cs().and(&root.cs()).and(&query.cs());
-
-        let mut self_hash = Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[
-            self.self_columns.get(&self.value.depth).unwrap().clone(),
-            QM31Var::zero(&cs),
-        ]);
-        let mut sibling_hash = Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[
-            self.siblings_columns
-                .get(&self.value.depth)
-                .unwrap()
-                .clone(),
-            QM31Var::zero(&cs),
-        ]);
-
-        for i in 0..self.value.depth {
-            let h = self.value.depth - i - 1;
-
-            if !self.self_columns.contains_key(&h) {
-                self_hash = Poseidon31MerkleHasherVar::hash_tree_with_swap(
-                    &mut self_hash,
-                    &mut sibling_hash,
-                    query.value[i],
-                    query.variables[i],
-                );
-                if i != self.value.depth - 1 {
-                    sibling_hash = self.sibling_hashes[i].clone();
-                }
-            } else {
-                let mut self_column_hash =
-                    Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[
-                        self.self_columns.get(&h).unwrap().clone(),
-                        QM31Var::zero(&cs),
-                    ]);
-                let mut sibling_column_hash =
-                    Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[
-                        self.siblings_columns.get(&h).unwrap().clone(),
-                        QM31Var::zero(&cs),
-                    ]);
-
-                self_hash = Poseidon31MerkleHasherVar::hash_tree_with_column_hash_with_swap(
-                    &mut self_hash,
-                    &mut sibling_hash,
-                    query.value[i],
-                    query.variables[i],
-                    &mut self_column_hash,
-                );
-                sibling_hash = Poseidon31MerkleHasherVar::combine_hash_tree_with_column(
-                    &mut self.sibling_hashes[i],
-                    &mut sibling_column_hash,
-                );
-            }
-        }
+        verify_single_pair_merkle_proof::<Poseidon31MerkleHasherVar>(
+            root,
+            query,
+            self.value.depth,
+            &self.sibling_hashes,
+            &self.self_columns,
+            &self.siblings_columns,
+        );
+    }
 
-        assert_eq!(self_hash.value(), root.value());
+    /// Verifies the proofs of all the queries together with `verify_merkle_multi_proof`, where
+    /// the proofs are in the order of the queries, which are in increasing order.
+    pub fn verify_all(proofs: &[Self], root: &HashVar, queries: &[BitsVar]) {
+        assert_eq!(proofs.len(), queries.len());
+        for (proof, query) in proofs.iter().zip(queries.iter()) {
+            proof.value.verify();
+            assert_eq!(root.value(), proof.value.root.0);
+            assert_eq!(query.get_value().0, proof.value.query as u32);
+        }
 
-        // check that the left_variable and right_variable are the same
-        // as though in self.root
-        self_hash.equalverify(&root);
+        // the sibling hashes start from the layer above the leaves, whose siblings are hashed
+        // from their columns
+        let depth = proofs[0].value.depth;
+        let sibling_hashes = proofs
+            .iter()
+            .map(|proof| {
+                proof
+                    .sibling_hashes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, hash)| (depth - 1 - i, hash.clone()))
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+        let to_columns = |columns: &BTreeMap<usize, QM31Var>| {
+            columns
+                .iter()
+                .map(|(&h, v)| (h, vec![v.clone()]))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let columns = proofs
+            .iter()
+            .map(|proof| to_columns(&proof.self_columns))
+            .collect::<Vec<_>>();
+        let siblings_columns = proofs
+            .iter()
+            .map(|proof| to_columns(&proof.siblings_columns))
+            .collect::<Vec<_>>();
+
+        verify_merkle_multi_proof::<Poseidon31MerkleHasherVar, QM31Var>(
+            root,
+            queries,
+            depth,
+            &sibling_hashes,
+            &columns,
+            &siblings_columns,
+        );
     }
 }
 
@@ -573,6 +571,9 @@ mod test {
             let query_bits = BitsVar::from_m31(&query, proof.depth);
             proof_var.verify(&root, &query_bits);
         }
+
+        cs.pad();
+        cs.check_arithmetics();
     }
 
     #[test]
@@ -599,5 +600,8 @@ mod test {
             let query_bits = BitsVar::from_m31(&query, proof.depth);
             proof_var.verify(&root, &query_bits);
         }
+
+        cs.pad();
+        cs.check_arithmetics();
     }
 }
@@ -1,5 +1,4 @@
-use circle_plonk_dsl_bits::BitsVar;
-use circle_plonk_dsl_channel::{ChannelVar, HashVar};
+use circle_plonk_dsl_channel::{ChannelVar, HashVar, TranscriptEntry};
 use circle_plonk_dsl_circle::CirclePointQM31Var;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
 use circle_plonk_dsl_data_structures::{LookupElementsVar, PlonkWithPoseidonProofVar};
@@ -25,6 +24,9 @@ pub struct FiatShamirResults {
     pub raw_queries: Vec<M31Var>,
 
     pub fri_alphas: Vec<QM31Var>,
+
+    /// The operations on the channel, to be compared with `FiatShamirHints::transcript`.
+    pub transcript: Vec<TranscriptEntry>,
 }
 
 impl FiatShamirResults {
@@ -42,6 +44,7 @@ impl FiatShamirResults {
         let mut composition_commitment = proof.stark_proof.commitments[3].clone();
 
         let mut channel = ChannelVar::default(&cs);
+        channel.enable_trace();
 
         // Preprocessed trace.
         channel.mix_root(&mut preprocessed_commitment);
@@ -99,35 +102,10 @@ impl FiatShamirResults {
             }
         }
 
-        let nonce_felt = QM31Var::from_m31(
-            &proof.stark_proof.proof_of_work[0],
-            &proof.stark_proof.proof_of_work[1],
-            &proof.stark_proof.proof_of_work[2],
-            &M31Var::zero(&cs),
-        );
-
-        let _ = BitsVar::from_m31(&proof.stark_proof.proof_of_work[0], 22);
-        let _ = BitsVar::from_m31(&proof.stark_proof.proof_of_work[1], 21);
-        let _ = BitsVar::from_m31(&proof.stark_proof.proof_of_work[2], 21);
-
-        channel.mix_one_felt(&nonce_felt);
+        channel.mix_nonce(&proof.stark_proof.proof_of_work);
+        channel.verify_pow_nonce(pcs_config.pow_bits);
 
-        let lower_bits = BitsVar::from_m31(&channel.digest.to_qm31()[0].decompose_m31()[0], 31)
-            .compose_range(0..pcs_config.pow_bits as usize);
-        lower_bits.equalverify(&M31Var::zero(&cs));
-
-        let mut raw_queries = Vec::with_capacity(pcs_config.fri_config.n_queries);
-        let mut draw_queries_felts =
-            Vec::with_capacity(pcs_config.fri_config.n_queries.div_ceil(4));
-        for _ in 0..pcs_config.fri_config.n_queries.div_ceil(4) {
-            let [a, b] = channel.draw_felts();
-            draw_queries_felts.push(a);
-            draw_queries_felts.push(b);
-        }
-        for felt in draw_queries_felts.iter() {
-            raw_queries.extend_from_slice(&felt.decompose_m31());
-        }
-        raw_queries.truncate(pcs_config.fri_config.n_queries);
+        let raw_queries = channel.draw_raw_queries(pcs_config.fri_config.n_queries);
 
         // enforce the total sum
         let mut input_sum = QM31Var::zero(&cs);
@@ -172,6 +150,7 @@ impl FiatShamirResults {
             oods_point,
             raw_queries,
             fri_alphas,
+            transcript: channel.trace.unwrap(),
         }
     }
 }
@@ -179,6 +158,7 @@ impl FiatShamirResults {
 #[cfg(test)]
 mod test {
     use crate::FiatShamirResults;
+    use circle_plonk_dsl_channel::assert_transcripts_match;
     use circle_plonk_dsl_constraint_system::var::AllocVar;
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
     use circle_plonk_dsl_data_structures::PlonkWithPoseidonProofVar;
@@ -209,12 +189,13 @@ mod test {
         let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
         let mut proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, &proof);
 
-        let _results = FiatShamirResults::compute(
+        let results = FiatShamirResults::compute(
             &fiat_shamir_hints,
             &mut proof_var,
             config,
             &[(1, QM31Var::one(&cs))],
         );
+        assert_transcripts_match(&results.transcript, &fiat_shamir_hints.transcript(&proof));
 
         cs.pad();
         cs.check_arithmetics();
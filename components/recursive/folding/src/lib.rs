@@ -19,18 +19,21 @@ impl FoldingResults {
     ) {
         let cs = answer_results.cs.clone();
 
-        // allocate all the first layer merkle proofs
-        let mut proofs = vec![];
-        for (i, proof) in first_layer_hints.merkle_proofs.iter().enumerate() {
-            let mut proof = SinglePairMerkleProofVar::new(&cs, proof);
-            proof.verify(
-                &proof_var.stark_proof.fri_proof.first_layer_commitment,
-                &answer_results.query_positions_per_log_size
-                    [fiat_shamir_hints.max_first_layer_column_log_size][i]
-                    .bits,
-            );
-            proofs.push(proof);
-        }
+        // allocate all the first layer merkle proofs and verify them together
+        let proofs = first_layer_hints
+            .merkle_proofs
+            .iter()
+            .map(|proof| SinglePairMerkleProofVar::new(&cs, proof))
+            .collect::<Vec<_>>();
+        SinglePairMerkleProofVar::verify_all(
+            &proofs,
+            &proof_var.stark_proof.fri_proof.first_layer_commitment,
+            &answer_results.query_positions_per_log_size
+                [fiat_shamir_hints.max_first_layer_column_log_size]
+                .iter()
+                .map(|query| query.bits.clone())
+                .collect::<Vec<_>>(),
+        );
 
         // check the fri answers match the self_columns
         for (&log_size, fri_answer_per_log_size) in fiat_shamir_hints
@@ -40,7 +43,7 @@ impl FoldingResults {
             .zip(answer_results.fri_answers.iter())
         {
             for (i, (_, fri_answer)) in fiat_shamir_hints
-                .unsorted_query_positions_per_log_size
+                .query_positions_per_log_size
                 .get(&log_size)
                 .unwrap()
                 .iter()
@@ -56,42 +59,28 @@ impl FoldingResults {
         // compute the first layer folding results
         let mut folded_results = BTreeMap::new();
         for &log_size in fiat_shamir_hints.all_log_sizes.iter() {
-            let mut folded_results_per_log_size = Vec::new();
-            for (proof, query) in proofs
+            let folded_results_per_log_size: Vec<QM31Var> = answer_results
+                .query_positions_per_log_size[log_size]
                 .iter()
-                .zip(answer_results.query_positions_per_log_size[log_size].iter())
-            {
-                let self_val = proof.self_columns.get(&(log_size as usize)).unwrap();
-                let sibling_val = proof.siblings_columns.get(&(log_size as usize)).unwrap();
-
-                let point = query.get_absolute_point().double();
-                let y_inv = point.y.inv();
-
-                let (left_val, right_val) = QM31Var::swap(
-                    &self_val,
-                    &sibling_val,
-                    query.bits.value[0],
-                    query.bits.variables[0],
-                );
-
-                let new_left_val = &left_val + &right_val;
-                let new_right_val = &(&left_val - &right_val) * &y_inv;
-
-                let folded_result = &new_left_val
-                    + &(&new_right_val
-                        * &fiat_shamir_results.fri_alphas[(fiat_shamir_hints
+                .enumerate()
+                .map(|(i, query)| {
+                    let proof = &proofs[i];
+                    query.fold_circle(
+                        proof.self_columns.get(&(log_size as usize)).unwrap(),
+                        proof.siblings_columns.get(&(log_size as usize)).unwrap(),
+                        &fiat_shamir_results.fri_alphas[(fiat_shamir_hints
                             .max_first_layer_column_log_size
                             - log_size)
-                            as usize]);
-
-                folded_results_per_log_size.push(folded_result);
-            }
+                            as usize],
+                    )
+                })
+                .collect();
             folded_results.insert(log_size, folded_results_per_log_size);
         }
 
         for (log_size, folded_evals) in first_layer_hints.folded_evals_by_column.iter() {
             let folded_queries = fiat_shamir_hints
-                .unsorted_query_positions_per_log_size
+                .query_positions_per_log_size
                 .get(&log_size)
                 .unwrap()
                 .iter()
@@ -124,7 +113,7 @@ impl FoldingResults {
 
         let mut folded = Vec::new();
         for _ in 0..fiat_shamir_hints
-            .unsorted_query_positions_per_log_size
+            .query_positions_per_log_size
             .get(&log_size)
             .unwrap()
             .len()
@@ -147,48 +136,40 @@ impl FoldingResults {
 
             let queries = answer_results.query_positions_per_log_size[log_size].clone();
 
-            let merkle_proofs = inner_layers_hints.merkle_proofs.get(&log_size).unwrap();
+            let merkle_proof_hints = inner_layers_hints.merkle_proofs.get(&log_size).unwrap();
 
-            let mut new_folded = vec![];
-            for ((folded_result, query), proof) in
-                folded.iter().zip(queries.iter()).zip(merkle_proofs.iter())
-            {
-                let mut merkle_proof = SinglePairMerkleProofVar::new(&cs, proof);
+            let merkle_proofs = merkle_proof_hints
+                .iter()
+                .map(|proof| SinglePairMerkleProofVar::new(&cs, proof))
+                .collect::<Vec<_>>();
+            SinglePairMerkleProofVar::verify_all(
+                &merkle_proofs,
+                &proof_var.stark_proof.fri_proof.inner_layer_commitments[i],
+                &queries
+                    .iter()
+                    .map(|query| query.bits.clone())
+                    .collect::<Vec<_>>(),
+            );
 
-                let self_val = merkle_proof.self_columns.get(&(log_size as usize)).unwrap();
-                let sibling_val = merkle_proof
-                    .siblings_columns
-                    .get(&(log_size as usize))
-                    .unwrap();
+            // every query checks its own folded value and folds its own Merkle proof, so the
+            // queries at the same position do not depend on each other
+            for (folded_result, proof) in folded.iter().zip(merkle_proofs.iter()) {
+                let self_val = proof.self_columns.get(&(log_size as usize)).unwrap();
                 folded_result.equalverify(&self_val);
-
-                let mut left_query = query.bits.clone();
-                left_query.value[0] = false;
-                left_query.variables[0] = 0;
-
-                let point = query.get_absolute_point();
-                let x_inv = point.x.inv();
-
-                let (left_val, right_val) = QM31Var::swap(
-                    &self_val,
-                    &sibling_val,
-                    query.bits.value[0],
-                    query.bits.variables[0],
-                );
-
-                let new_left_val = &left_val + &right_val;
-                let new_right_val = &(&left_val - &right_val) * &x_inv;
-
-                let folded_result =
-                    &new_left_val + &(&new_right_val * &fiat_shamir_results.fri_alphas[i + 1]);
-                new_folded.push(folded_result);
-
-                merkle_proof.verify(
-                    &proof_var.stark_proof.fri_proof.inner_layer_commitments[i],
-                    &query.bits,
-                );
             }
-            folded = new_folded;
+
+            folded = answer_results.query_positions_per_log_size[log_size]
+                .iter()
+                .enumerate()
+                .map(|(j, query)| {
+                    let proof = &merkle_proofs[j];
+                    query.fold_line(
+                        proof.self_columns.get(&(log_size as usize)).unwrap(),
+                        proof.siblings_columns.get(&(log_size as usize)).unwrap(),
+                        &fiat_shamir_results.fri_alphas[i + 1],
+                    )
+                })
+                .collect();
         }
 
         let queries = answer_results.query_positions_per_log_size[log_size].clone();
@@ -229,42 +210,32 @@ mod test {
         prove_plonk_with_poseidon, verify_plonk_with_poseidon, PlonkWithPoseidonProof,
     };
 
-    #[test]
-    pub fn test_folding() {
-        let proof: PlonkWithPoseidonProof<Poseidon31MerkleHasher> =
-            bincode::deserialize(include_bytes!("../../../test_data/small_proof.bin")).unwrap();
-        let config = PcsConfig {
-            pow_bits: 20,
-            fri_config: FriConfig::new(2, 5, 16),
-        };
-
-        verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
-            proof.clone(),
-            config,
-            &[(1, QM31::one())],
-        )
-        .unwrap();
-
-        let fiat_shamir_hints = FiatShamirHints::new(&proof, config, &[(1, QM31::one())]);
-        let answer_hints = AnswerHints::compute(&fiat_shamir_hints, &proof);
-        let fri_answer_hints = AnswerHints::compute(&fiat_shamir_hints, &proof);
-        let decommitment_hints = DecommitHints::compute(&fiat_shamir_hints, &proof);
-        let first_layer_hints = FirstLayerHints::compute(&fiat_shamir_hints, &answer_hints, &proof);
+    /// Runs the Fiat-Shamir, answer and folding stages of the recursive verifier on `proof`.
+    fn fold_proof(
+        proof: &PlonkWithPoseidonProof<Poseidon31MerkleHasher>,
+        config: PcsConfig,
+        inputs: &[(usize, QM31)],
+    ) -> ConstraintSystemRef {
+        let fiat_shamir_hints = FiatShamirHints::new(proof, config, inputs);
+        let answer_hints = AnswerHints::compute(&fiat_shamir_hints, proof);
+        let fri_answer_hints = AnswerHints::compute(&fiat_shamir_hints, proof);
+        let decommitment_hints = DecommitHints::compute(&fiat_shamir_hints, proof);
+        let first_layer_hints = FirstLayerHints::compute(&fiat_shamir_hints, &answer_hints, proof);
         let inner_layer_hints = InnerLayersHints::compute(
             &first_layer_hints.folded_evals_by_column,
             &fiat_shamir_hints,
-            &proof,
+            proof,
         );
 
         let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
-        let mut proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, &proof);
+        let mut proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, proof);
+        let inputs_var = inputs
+            .iter()
+            .map(|(i, input)| (*i, QM31Var::new_constant(&cs, input)))
+            .collect::<Vec<_>>();
 
-        let fiat_shamir_results = FiatShamirResults::compute(
-            &fiat_shamir_hints,
-            &mut proof_var,
-            config,
-            &[(1, QM31Var::one(&cs))],
-        );
+        let fiat_shamir_results =
+            FiatShamirResults::compute(&fiat_shamir_hints, &mut proof_var, config, &inputs_var);
 
         let answer_results = AnswerResults::compute(
             &CirclePointQM31Var::new_witness(&cs, &fiat_shamir_hints.oods_point),
@@ -289,6 +260,26 @@ mod test {
         cs.check_arithmetics();
         cs.populate_logup_arguments();
         cs.check_poseidon_invocations();
+        cs
+    }
+
+    #[test]
+    pub fn test_folding() {
+        let proof: PlonkWithPoseidonProof<Poseidon31MerkleHasher> =
+            bincode::deserialize(include_bytes!("../../../test_data/small_proof.bin")).unwrap();
+        let config = PcsConfig {
+            pow_bits: 20,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+
+        verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+            proof.clone(),
+            config,
+            &[(1, QM31::one())],
+        )
+        .unwrap();
+
+        let cs = fold_proof(&proof, config, &[(1, QM31::one())]);
 
         let (plonk, mut poseidon) = cs.generate_plonk_with_poseidon_circuit();
         let proof =
@@ -304,4 +295,53 @@ mod test {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_folding_duplicate_queries() {
+        // more queries than positions in the last FRI layer, so that some queries repeat
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(0, 2, 16),
+        };
+        let inputs = [
+            (1, QM31::one()),
+            (2, QM31::from_u32_unchecked(0, 1, 0, 0)),
+            (3, QM31::from_u32_unchecked(0, 0, 1, 0)),
+        ];
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+        let a = QM31::from_u32_unchecked(1, 2, 3, 4);
+        let a_var = QM31Var::new_witness(&cs, &a);
+        let b_var = QM31Var::new_witness(&cs, &(a * a * a));
+        (&(&a_var * &a_var) * &a_var).equalverify(&b_var);
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+
+        let (plonk, mut poseidon) = cs.generate_plonk_with_poseidon_circuit();
+        let proof =
+            prove_plonk_with_poseidon::<Poseidon31MerkleChannel>(config, &plonk, &mut poseidon);
+        verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(proof.clone(), config, &inputs)
+            .unwrap();
+
+        let fiat_shamir_hints =
+            FiatShamirHints::<Poseidon31MerkleChannel>::new(&proof, config, &inputs);
+        // the queries are sorted, so the ones at the same position in the last FRI layer are
+        // next to each other
+        let (&max_log_size, positions) = fiat_shamir_hints
+            .query_positions_per_log_size
+            .last_key_value()
+            .unwrap();
+        let last_layer_log_size =
+            config.fri_config.log_last_layer_degree_bound + config.fri_config.log_blowup_factor + 1;
+        assert_eq!(positions.len(), 16);
+        assert!(positions.windows(2).any(|pair| {
+            pair[0] >> (max_log_size - last_layer_log_size)
+                == pair[1] >> (max_log_size - last_layer_log_size)
+        }));
+
+        let _ = fold_proof(&proof, config, &inputs);
+    }
 }
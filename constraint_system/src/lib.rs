@@ -1,3 +1,4 @@
+use crate::range_table::{LogupElements, RangeTableCircuitTrace};
 use crate::var::AllocationMode;
 use plonk_with_poseidon::PlonkWithPoseidonConstraintSystem;
 use plonk_without_poseidon::PlonkWithoutPoseidonConstraintSystem;
@@ -17,6 +18,8 @@ pub mod var;
 pub mod plonk_with_poseidon;
 pub mod plonk_without_poseidon;
 
+pub mod range_table;
+
 #[derive(Debug)]
 pub enum ConstraintSystemEnum {
     PlonkWithPoseidon(PlonkWithPoseidonConstraintSystem),
@@ -54,6 +57,15 @@ impl ConstraintSystemRef {
         }
     }
 
+    /// Overwrites the value of a variable, so that tests can check that a circuit rejects a
+    /// tampered witness.
+    pub fn set_value(&self, idx: usize, value: QM31) {
+        match self.0.borrow_mut().deref_mut() {
+            ConstraintSystemEnum::PlonkWithPoseidon(cs) => cs.variables[idx] = value,
+            ConstraintSystemEnum::PlonkWithoutPoseidon(cs) => cs.variables[idx] = value,
+        }
+    }
+
     pub fn get_type(&self) -> ConstraintSystemType {
         match self.0.borrow().deref() {
             ConstraintSystemEnum::PlonkWithPoseidon(_) => ConstraintSystemType::PlonkWithPoseidon,
@@ -169,6 +181,14 @@ impl ConstraintSystemRef {
         }
     }
 
+    /// Computes `op * (a + b) + (1 - op) * a * b` in a single row.
+    pub fn do_arith_gate(&self, a_wire: usize, b_wire: usize, op: M31) -> usize {
+        match self.0.borrow_mut().deref_mut() {
+            ConstraintSystemEnum::PlonkWithPoseidon(cs) => cs.do_arith_gate(a_wire, b_wire, op),
+            ConstraintSystemEnum::PlonkWithoutPoseidon(cs) => cs.do_arith_gate(a_wire, b_wire, op),
+        }
+    }
+
     pub fn enforce_zero(&self, var: usize) {
         match self.0.borrow_mut().deref_mut() {
             ConstraintSystemEnum::PlonkWithPoseidon(cs) => {
@@ -180,6 +200,18 @@ impl ConstraintSystemRef {
         }
     }
 
+    /// Looks up the variable in the range table `0..2^16`.
+    ///
+    /// The lookups are counted in the multiplicities of the Plonk circuit, so a proof of a circuit
+    /// that uses them must also include the range table component from
+    /// `generate_range_table_circuit`.
+    pub fn range_check_16(&self, var: usize) {
+        match self.0.borrow_mut().deref_mut() {
+            ConstraintSystemEnum::PlonkWithPoseidon(cs) => cs.range_check_16(var),
+            ConstraintSystemEnum::PlonkWithoutPoseidon(cs) => cs.range_check_16(var),
+        }
+    }
+
     pub fn check_arithmetics(&self) {
         match self.0.borrow().deref() {
             ConstraintSystemEnum::PlonkWithPoseidon(cs) => cs.check_arithmetics(),
@@ -256,6 +288,30 @@ impl ConstraintSystemRef {
         }
     }
 
+    pub fn generate_range_table_circuit(&self) -> RangeTableCircuitTrace {
+        match self.0.borrow().deref() {
+            ConstraintSystemEnum::PlonkWithPoseidon(cs) => cs.generate_range_table_circuit(),
+            ConstraintSystemEnum::PlonkWithoutPoseidon(cs) => cs.generate_range_table_circuit(),
+        }
+    }
+
+    /// Returns the total logup sum of the circuit and its range table, which must be zero for a
+    /// proof with the range table component to verify.
+    pub fn logup_total_sum(
+        &self,
+        plonk_elements: &LogupElements,
+        range_elements: &LogupElements,
+    ) -> QM31 {
+        match self.0.borrow().deref() {
+            ConstraintSystemEnum::PlonkWithPoseidon(_) => {
+                unimplemented!()
+            }
+            ConstraintSystemEnum::PlonkWithoutPoseidon(cs) => {
+                cs.logup_total_sum(plonk_elements, range_elements)
+            }
+        }
+    }
+
     pub fn num_plonk_rows(&self) -> usize {
         match self.0.borrow_mut().deref_mut() {
             ConstraintSystemEnum::PlonkWithPoseidon(cs) => cs.a_wire.len(),
@@ -1,3 +1,4 @@
+use crate::range_table::{RangeTable, RangeTableCircuitTrace};
 use crate::var::AllocationMode;
 use crate::LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE;
 use num_traits::{One, Zero};
@@ -36,6 +37,8 @@ pub struct PlonkWithPoseidonConstraintSystem {
 
     pub flow: PoseidonFlow,
 
+    pub range_table: RangeTable,
+
     pub num_input: usize,
     pub is_program_started: bool,
 }
@@ -55,6 +58,7 @@ impl PlonkWithPoseidonConstraintSystem {
             mult_poseidon: vec![],
             enforce_c_m31: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
             op: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
+            range_table: RangeTable::default(),
             num_input: 0,
             is_program_started: false,
             flow: PoseidonFlow::default(),
@@ -127,6 +131,10 @@ impl PlonkWithPoseidonConstraintSystem {
             .push((entry_1, entry_2, entry_3, entry_4, swap_option));
     }
 
+    pub fn range_check_16(&mut self, var: usize) {
+        self.range_table.lookup(var);
+    }
+
     pub fn enforce_zero(&mut self, var: usize) {
         self.is_program_started = true;
 
@@ -191,6 +199,18 @@ impl PlonkWithPoseidonConstraintSystem {
         c_wire
     }
 
+    pub fn do_arith_gate(&mut self, a_wire: usize, b_wire: usize, op: M31) -> usize {
+        let a_val = self.variables[a_wire];
+        let b_val = self.variables[b_wire];
+
+        let c_wire = self.variables.len();
+        self.variables
+            .push(op * (a_val + b_val) + (M31::one() - op) * a_val * b_val);
+
+        self.insert_gate(a_wire, b_wire, c_wire, op);
+        c_wire
+    }
+
     pub fn new_m31(&mut self, variable: M31, mode: AllocationMode) -> usize {
         let c_wire = self.variables.len();
         self.variables.push(QM31::from(variable));
@@ -332,6 +352,8 @@ impl PlonkWithPoseidonConstraintSystem {
             self.enforce_c_m31.push(0);
             self.op.push(M31::one());
         }
+
+        self.range_table.pad();
     }
 
     pub fn check_arithmetics(&self) {
@@ -378,6 +400,8 @@ impl PlonkWithPoseidonConstraintSystem {
                 );
             }
         }
+
+        self.range_table.check_arithmetics(&self.variables);
     }
 
     pub fn populate_logup_arguments(&mut self) {
@@ -403,6 +427,9 @@ impl PlonkWithPoseidonConstraintSystem {
             counts[i + 1] += 1;
         }
 
+        self.range_table
+            .populate_logup_arguments(&self.variables, &mut counts);
+
         for (_, _, _, _, swap) in self.flow.0.iter() {
             counts[swap.addr] += 1;
         }
@@ -625,4 +652,9 @@ impl PlonkWithPoseidonConstraintSystem {
 
         (circuit, self.flow.clone())
     }
+
+    pub fn generate_range_table_circuit(&self) -> RangeTableCircuitTrace {
+        self.range_table
+            .generate_range_table_circuit(&self.variables)
+    }
 }
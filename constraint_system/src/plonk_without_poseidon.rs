@@ -1,3 +1,4 @@
+use crate::range_table::{LogupElements, RangeTable, RangeTableCircuitTrace};
 use crate::var::AllocationMode;
 use crate::LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE;
 use num_traits::{One, Zero};
@@ -7,6 +8,7 @@ use stwo_prover::core::backend::simd::m31::N_LANES;
 use stwo_prover::core::backend::Column;
 use stwo_prover::core::fields::m31::{BaseField, M31};
 use stwo_prover::core::fields::qm31::QM31;
+use stwo_prover::core::fields::FieldExpOps;
 use stwo_prover::examples::plonk_without_poseidon::plonk::PlonkWithoutAcceleratorCircuitTrace;
 
 #[derive(Debug)]
@@ -25,6 +27,8 @@ pub struct PlonkWithoutPoseidonConstraintSystem {
     pub op3: Vec<M31>,
     pub op4: Vec<M31>,
 
+    pub range_table: RangeTable,
+
     pub num_input: usize,
     pub is_program_started: bool,
 }
@@ -42,6 +46,7 @@ impl PlonkWithoutPoseidonConstraintSystem {
             op2: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
             op3: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
             op4: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
+            range_table: RangeTable::default(),
             num_input: 0,
             is_program_started: false,
         };
@@ -243,6 +248,10 @@ impl PlonkWithoutPoseidonConstraintSystem {
         id
     }
 
+    pub fn range_check_16(&mut self, var: usize) {
+        self.range_table.lookup(var);
+    }
+
     pub fn enforce_zero(&mut self, var: usize) {
         self.is_program_started = true;
 
@@ -287,6 +296,18 @@ impl PlonkWithoutPoseidonConstraintSystem {
         c_wire
     }
 
+    pub fn do_arith_gate(&mut self, a_wire: usize, b_wire: usize, op: M31) -> usize {
+        let a_val = self.variables[a_wire];
+        let b_val = self.variables[b_wire];
+
+        let c_wire = self.variables.len();
+        self.variables
+            .push(op * (a_val + b_val) + (M31::one() - op) * a_val * b_val);
+
+        self.insert_gate(a_wire, b_wire, c_wire, op);
+        c_wire
+    }
+
     pub fn new_m31(&mut self, variable: M31, mode: AllocationMode) -> usize {
         let c_wire = self.variables.len();
         self.variables.push(QM31::from(variable));
@@ -405,6 +426,8 @@ impl PlonkWithoutPoseidonConstraintSystem {
             self.op3.push(M31::zero());
             self.op4.push(M31::zero());
         }
+
+        self.range_table.pad();
     }
 
     pub fn check_arithmetics(&self) {
@@ -595,6 +618,8 @@ impl PlonkWithoutPoseidonConstraintSystem {
                     + is_grand_sum * QM31::from_m31(grand_sum, grand_sum, grand_sum, grand_sum)
             );
         }
+
+        self.range_table.check_arithmetics(&self.variables);
     }
 
     pub fn populate_logup_arguments(&mut self) {
@@ -617,6 +642,9 @@ impl PlonkWithoutPoseidonConstraintSystem {
             counts[i + 1] += 1;
         }
 
+        self.range_table
+            .populate_logup_arguments(&self.variables, &mut counts);
+
         let mut first_occurred = vec![false; n_vars];
         let mut mult_c = Vec::with_capacity(n_rows);
         for i in 0..n_rows {
@@ -711,4 +739,46 @@ impl PlonkWithoutPoseidonConstraintSystem {
 
         circuit
     }
+
+    pub fn generate_range_table_circuit(&self) -> RangeTableCircuitTrace {
+        self.range_table
+            .generate_range_table_circuit(&self.variables)
+    }
+
+    /// Returns the logup sum of the Plonk component, the public inputs and the range table
+    /// component, which is zero if and only if the multiplicities balance.
+    pub fn logup_total_sum(
+        &self,
+        plonk_elements: &LogupElements,
+        range_elements: &LogupElements,
+    ) -> QM31 {
+        assert!(!self.mult_c.is_empty());
+
+        let entry = |wire: usize| {
+            plonk_elements
+                .combine(&[self.variables[wire], QM31::from(M31::from(wire))])
+                .inverse()
+        };
+
+        let mut sum = QM31::zero();
+        for i in 0..self.a_wire.len() {
+            let mult_c = M31::from(self.mult_c[i].unsigned_abs());
+            let mult_c = if self.mult_c[i].is_negative() {
+                mult_c.neg()
+            } else {
+                mult_c
+            };
+
+            sum += entry(self.a_wire[i]);
+            sum += entry(self.b_wire[i]);
+            sum += entry(self.c_wire[i]) * mult_c;
+        }
+        for i in 0..self.num_input {
+            sum += entry(i + 1);
+        }
+
+        sum + self
+            .range_table
+            .logup_sum(&self.variables, plonk_elements, range_elements)
+    }
 }
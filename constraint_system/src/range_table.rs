@@ -0,0 +1,147 @@
+use num_traits::{One, Zero};
+use std::cmp::max;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
+use stwo_prover::core::fields::FieldExpOps;
+
+pub const LOG_RANGE_TABLE_SIZE: usize = 16;
+
+/// The wires that are looked up against the preprocessed range table `0..2^16`.
+///
+/// The range table component has one lookup and one table entry per row. Each lookup consumes
+/// the (value, wire) pair from the Plonk logup relation and the value from the range table
+/// relation, in which the preprocessed table column provides each entry with multiplicity
+/// `mult[k]`.
+#[derive(Debug, Default)]
+pub struct RangeTable {
+    pub wires: Vec<usize>,
+    pub mult: Vec<usize>,
+}
+
+/// The trace of the range table component.
+#[derive(Debug, Clone)]
+pub struct RangeTableCircuitTrace {
+    /// The preprocessed table column, which counts from 0 to `2^16 - 1` and is zero beyond.
+    pub table: Vec<M31>,
+    /// The multiplicity of each entry of the table column.
+    pub mult: Vec<M31>,
+    /// The preprocessed column of the looked-up wires.
+    pub wire: Vec<M31>,
+    pub val: Vec<M31>,
+}
+
+/// The random elements of a logup relation, which combine a tuple `(v_0, v_1, ...)` into
+/// `v_0 + alpha * v_1 + ... - z`, as in stwo.
+#[derive(Debug, Clone, Copy)]
+pub struct LogupElements {
+    pub z: QM31,
+    pub alpha: QM31,
+}
+
+impl LogupElements {
+    pub fn combine(&self, values: &[QM31]) -> QM31 {
+        let mut sum = QM31::zero();
+        let mut power = QM31::one();
+        for &value in values.iter() {
+            sum += power * value;
+            power *= self.alpha;
+        }
+        sum - self.z
+    }
+}
+
+impl RangeTable {
+    pub fn lookup(&mut self, wire: usize) {
+        self.wires.push(wire);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wires.is_empty()
+    }
+
+    pub fn pad(&mut self) {
+        assert!(self.mult.is_empty());
+
+        if self.wires.is_empty() {
+            return;
+        }
+
+        // the component has at least as many rows as the table, and the padding looks up the
+        // zero wire
+        let len = self.wires.len();
+        let padded_len = max(1 << LOG_RANGE_TABLE_SIZE, len.next_power_of_two());
+        self.wires.resize(padded_len, 0);
+    }
+
+    pub fn check_arithmetics(&self, variables: &[QM31]) {
+        for &wire in self.wires.iter() {
+            let v = variables[wire];
+            assert!(
+                QM31::from(v.0 .0) == v && v.0 .0 .0 < (1 << LOG_RANGE_TABLE_SIZE),
+                "Wire {} is looked up in the range table, but its value {} is out of range",
+                wire,
+                v
+            );
+        }
+    }
+
+    pub fn populate_logup_arguments(&mut self, variables: &[QM31], counts: &mut [isize]) {
+        assert!(self.mult.is_empty());
+
+        if self.wires.is_empty() {
+            return;
+        }
+
+        let mut mult = vec![0; self.wires.len()];
+        for &wire in self.wires.iter() {
+            mult[variables[wire].0 .0 .0 as usize] += 1;
+            counts[wire] += 1;
+        }
+        self.mult = mult;
+    }
+
+    /// Returns the sum of the range table component over both relations, which balances the
+    /// lookups counted in the multiplicities of the Plonk circuit.
+    pub fn logup_sum(
+        &self,
+        variables: &[QM31],
+        plonk_elements: &LogupElements,
+        range_elements: &LogupElements,
+    ) -> QM31 {
+        let mut sum = QM31::zero();
+        for &wire in self.wires.iter() {
+            let val = variables[wire];
+            let wire = QM31::from(M31::from(wire));
+            sum += plonk_elements.combine(&[val, wire]).inverse();
+            sum += range_elements.combine(&[val]).inverse();
+        }
+        for (k, &mult) in self.mult.iter().enumerate() {
+            if mult != 0 {
+                let entry = QM31::from(M31::from(k));
+                sum -= range_elements.combine(&[entry]).inverse() * M31::from(mult);
+            }
+        }
+        sum
+    }
+
+    pub fn generate_range_table_circuit(&self, variables: &[QM31]) -> RangeTableCircuitTrace {
+        assert!(self.wires.len() >= 1 << LOG_RANGE_TABLE_SIZE);
+        assert!(self.wires.len().is_power_of_two());
+        assert_eq!(self.mult.len(), self.wires.len());
+
+        RangeTableCircuitTrace {
+            table: (0..self.wires.len())
+                .map(|k| {
+                    if k < 1 << LOG_RANGE_TABLE_SIZE {
+                        M31::from(k)
+                    } else {
+                        M31::zero()
+                    }
+                })
+                .collect(),
+            mult: self.mult.iter().map(|&m| M31::from(m)).collect(),
+            wire: self.wires.iter().map(|&w| M31::from(w)).collect(),
+            val: self.wires.iter().map(|&w| variables[w].0 .0).collect(),
+        }
+    }
+}
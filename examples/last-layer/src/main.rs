@@ -1,9 +1,7 @@
 use circle_plonk_dsl_constraint_system::var::AllocVar;
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_hints::{AnswerHints, FiatShamirHints};
-use circle_plonk_dsl_last_answer::data_structures::{
-    LastDecommitHints, LastDecommitInput, LastDecommitInputVar,
-};
+use circle_plonk_dsl_last_answer::data_structures::LastDecommitHints;
 use circle_plonk_dsl_last_answer::LastAnswerResults;
 use circle_plonk_dsl_last_data_structures::LastPlonkWithPoseidonProofVar;
 use circle_plonk_dsl_last_fiat_shamir::{
@@ -59,22 +57,20 @@ fn main() {
     );
 
     let fiat_shamir_input = LastFiatShamirInput::from_proof(&proof, &fiat_shamir_hints);
-    let decommit_input = LastDecommitInput::from_hints(&decommit_hints);
     let fiat_shamir_input_var = LastFiatShamirInputVar::new_public_input(&cs, &fiat_shamir_input);
-    let decommit_input_var = LastDecommitInputVar::new_public_input(&cs, &decommit_input);
     let first_layer_input_var = LastFirstLayerInputVar::new_public_input(&cs, &first_layer_hints);
     let inner_layers_input_var =
         LastInnerLayersInputVar::new_public_input(&cs, &inner_layers_hints);
 
     let proof_var = LastPlonkWithPoseidonProofVar::new_witness(&cs, &proof);
-    let fiat_shamir_results = LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var);
+    let fiat_shamir_results =
+        LastFiatShamirResults::compute(&proof_var, &fiat_shamir_input_var, config);
 
     let last_answer_results = LastAnswerResults::compute(
         &fiat_shamir_hints,
         &decommit_hints,
         &fri_answer_hints,
         &fiat_shamir_results,
-        &decommit_input_var,
         &proof_var,
         config,
     );
@@ -152,19 +148,6 @@ fn main() {
     for fri_alpha in fiat_shamir_input.fri_alphas.iter() {
         add_input(&mut inputs, *fri_alpha);
     }
-    for proof in decommit_input
-        .precomputed_proofs
-        .iter()
-        .chain(decommit_input.trace_proofs.iter())
-        .chain(decommit_input.interaction_proofs.iter())
-        .chain(decommit_input.composition_proofs.iter())
-    {
-        for (_, column) in proof.packed_columns.iter() {
-            for elem in column.iter() {
-                add_input(&mut inputs, *elem);
-            }
-        }
-    }
     for proof in first_layer_hints.merkle_proofs.iter() {
         for (_, elem) in proof.self_columns.iter() {
             add_input(&mut inputs, *elem);
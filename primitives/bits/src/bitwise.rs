@@ -0,0 +1,243 @@
+use crate::BitsVar;
+use circle_plonk_dsl_constraint_system::var::Var;
+use num_traits::{One, Zero};
+use std::ops::Neg;
+use stwo_prover::core::fields::m31::M31;
+
+impl BitsVar {
+    pub fn and(&self, rhs: &BitsVar) -> BitsVar {
+        assert_eq!(self.variables.len(), rhs.variables.len());
+        let cs = self.cs().and(&rhs.cs());
+
+        let mut value = Vec::with_capacity(self.value.len());
+        let mut variables = Vec::with_capacity(self.variables.len());
+        for i in 0..self.variables.len() {
+            value.push(self.value[i] & rhs.value[i]);
+            variables.push(cs.mul(self.variables[i], rhs.variables[i]));
+        }
+
+        BitsVar {
+            cs,
+            value,
+            variables,
+        }
+    }
+
+    pub fn xor(&self, rhs: &BitsVar) -> BitsVar {
+        assert_eq!(self.variables.len(), rhs.variables.len());
+        let cs = self.cs().and(&rhs.cs());
+
+        let mut value = Vec::with_capacity(self.value.len());
+        let mut variables = Vec::with_capacity(self.variables.len());
+        for i in 0..self.variables.len() {
+            value.push(self.value[i] ^ rhs.value[i]);
+
+            // with op = -1, the gate computes 2ab - (a + b), which is the negated xor
+            let neg_xor = cs.do_arith_gate(self.variables[i], rhs.variables[i], M31::one().neg());
+            variables.push(cs.mul_constant(neg_xor, M31::one().neg()));
+        }
+
+        BitsVar {
+            cs,
+            value,
+            variables,
+        }
+    }
+
+    pub fn or(&self, rhs: &BitsVar) -> BitsVar {
+        assert_eq!(self.variables.len(), rhs.variables.len());
+        let cs = self.cs().and(&rhs.cs());
+
+        // omega is a primitive sixth root of unity, a root of x^2 - x + 1, so that
+        // omega * (1 - omega) = 1 and (1 - omega)^3 = -1
+        let omega = M31::from(634005912);
+        debug_assert_eq!(omega * omega - omega + M31::one(), M31::zero());
+
+        let mut value = Vec::with_capacity(self.value.len());
+        let mut variables = Vec::with_capacity(self.variables.len());
+        for i in 0..self.variables.len() {
+            value.push(self.value[i] | rhs.value[i]);
+
+            // a single gate cannot compute a + b - ab, but after scaling both inputs by 1 - omega,
+            // the gate with op = omega computes it exactly
+            let a = cs.mul_constant(self.variables[i], M31::one() - omega);
+            let b = cs.mul_constant(rhs.variables[i], M31::one() - omega);
+            variables.push(cs.do_arith_gate(a, b, omega));
+        }
+
+        BitsVar {
+            cs,
+            value,
+            variables,
+        }
+    }
+
+    pub fn not(&self) -> BitsVar {
+        let cs = self.cs();
+
+        let mut value = Vec::with_capacity(self.value.len());
+        let mut variables = Vec::with_capacity(self.variables.len());
+        for i in 0..self.variables.len() {
+            value.push(!self.value[i]);
+
+            // with op = -1 and b = 1, the gate computes a - 1, which is the negated not
+            let neg_not = cs.do_arith_gate(self.variables[i], 1, M31::one().neg());
+            variables.push(cs.mul_constant(neg_not, M31::one().neg()));
+        }
+
+        BitsVar {
+            cs,
+            value,
+            variables,
+        }
+    }
+
+    pub fn rotate_right(&self, n: usize) -> BitsVar {
+        let l = self.variables.len();
+        let n = n % l;
+
+        let mut value = self.value[n..].to_vec();
+        value.extend_from_slice(&self.value[..n]);
+        let mut variables = self.variables[n..].to_vec();
+        variables.extend_from_slice(&self.variables[..n]);
+
+        BitsVar {
+            cs: self.cs(),
+            value,
+            variables,
+        }
+    }
+
+    pub fn shift_right(&self, n: usize) -> BitsVar {
+        let l = self.variables.len();
+        let n = n.min(l);
+
+        let mut value = self.value[n..].to_vec();
+        value.resize(l, false);
+        let mut variables = self.variables[n..].to_vec();
+        variables.resize(l, 0);
+
+        BitsVar {
+            cs: self.cs(),
+            value,
+            variables,
+        }
+    }
+
+    pub fn add_u32(&self, rhs: &BitsVar) -> BitsVar {
+        BitsVar::sum_u32(&[self, rhs])
+    }
+
+    /// Adds 32-bit words modulo 2^32.
+    ///
+    /// Each word is split into two 16-bit limbs, so that the sum of each limb together with the
+    /// carry fits in a field element and can be decomposed again.
+    pub fn sum_u32(values: &[&BitsVar]) -> BitsVar {
+        assert!(!values.is_empty());
+        for v in values.iter() {
+            assert_eq!(v.variables.len(), 32);
+        }
+        if values.len() == 1 {
+            return values[0].clone();
+        }
+
+        let carry_bits = (usize::BITS - (values.len() - 1).leading_zeros()) as usize;
+
+        let mut lo = values[0].compose_range(0..16);
+        for v in values.iter().skip(1) {
+            lo = &lo + &v.compose_range(0..16);
+        }
+        let lo_bits = BitsVar::from_m31(&lo, 16 + carry_bits);
+
+        let mut hi = lo_bits.compose_range(16..16 + carry_bits);
+        for v in values.iter() {
+            hi = &hi + &v.compose_range(16..32);
+        }
+        let hi_bits = BitsVar::from_m31(&hi, 16 + carry_bits);
+
+        let mut value = lo_bits.value[0..16].to_vec();
+        value.extend_from_slice(&hi_bits.value[0..16]);
+        let mut variables = lo_bits.variables[0..16].to_vec();
+        variables.extend_from_slice(&hi_bits.variables[0..16]);
+
+        BitsVar {
+            cs: lo_bits.cs(),
+            value,
+            variables,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::BitsVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+
+    fn to_bits(v: u32) -> Vec<bool> {
+        (0..32).map(|i| (v >> i) & 1 != 0).collect()
+    }
+
+    fn from_bits(bits: &BitsVar) -> u32 {
+        assert_eq!(bits.value.len(), 32);
+        for (&b, &variable) in bits.value.iter().zip(bits.variables.iter()) {
+            assert_eq!(bits.cs.get_value(variable), QM31::from(M31::from(b as u32)));
+        }
+        bits.value
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | ((b as u32) << i))
+    }
+
+    #[test]
+    fn test_bitwise_operations() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let a: u32 = prng.gen();
+        let b: u32 = prng.gen();
+        let c: u32 = prng.gen();
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+        let a_var = BitsVar::new_witness(&cs, &to_bits(a));
+        let b_var = BitsVar::new_witness(&cs, &to_bits(b));
+        let c_var = BitsVar::new_witness(&cs, &to_bits(c));
+
+        let num_rows = cs.num_plonk_rows();
+        assert_eq!(from_bits(&a_var.and(&b_var)), a & b);
+        assert_eq!(cs.num_plonk_rows() - num_rows, 32);
+
+        let num_rows = cs.num_plonk_rows();
+        assert_eq!(from_bits(&a_var.xor(&b_var)), a ^ b);
+        assert_eq!(cs.num_plonk_rows() - num_rows, 64);
+
+        let num_rows = cs.num_plonk_rows();
+        assert_eq!(from_bits(&a_var.or(&b_var)), a | b);
+        assert_eq!(cs.num_plonk_rows() - num_rows, 96);
+
+        let num_rows = cs.num_plonk_rows();
+        assert_eq!(from_bits(&a_var.not()), !a);
+        assert_eq!(cs.num_plonk_rows() - num_rows, 64);
+
+        let num_rows = cs.num_plonk_rows();
+        assert_eq!(from_bits(&a_var.rotate_right(7)), a.rotate_right(7));
+        assert_eq!(from_bits(&a_var.shift_right(10)), a >> 10);
+        assert_eq!(cs.num_plonk_rows() - num_rows, 0);
+
+        // composing the four 16-bit limbs takes 4 * 30 rows, the three additions take 3 rows,
+        // and each of the two 17-bit decompositions takes 17 + 2 * 16 + 1 rows.
+        let num_rows = cs.num_plonk_rows();
+        assert_eq!(from_bits(&a_var.add_u32(&b_var)), a.wrapping_add(b));
+        assert_eq!(cs.num_plonk_rows() - num_rows, 223);
+
+        assert_eq!(
+            from_bits(&BitsVar::sum_u32(&[&a_var, &b_var, &c_var])),
+            a.wrapping_add(b).wrapping_add(c)
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
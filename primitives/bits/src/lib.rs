@@ -2,11 +2,19 @@ use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_fields::M31Var;
 use num_traits::{One, Zero};
-use std::ops::{Neg, Range, RangeFrom};
+use std::ops::{Range, RangeFrom};
 use stwo_prover::core::fields::m31::M31;
 use stwo_prover::core::fields::qm31::QM31;
 
-#[derive(Clone)]
+mod bitwise;
+
+pub mod pow;
+pub use pow::*;
+
+pub mod range;
+pub use range::*;
+
+#[derive(Debug, Clone)]
 pub struct BitsVar {
     pub cs: ConstraintSystemRef,
     pub value: Vec<bool>,
@@ -30,9 +38,8 @@ impl AllocVar for BitsVar {
             variables.push(bit);
 
             if mode == AllocationMode::Witness {
-                let minus_one = M31Var::new_constant(cs, &M31::one().neg());
-                let bit_minus_one = cs.add(bit, minus_one.variable);
-                cs.insert_gate(bit, bit_minus_one, 0, M31::zero());
+                // bit * bit = bit
+                cs.insert_gate(bit, bit, bit, M31::zero());
             }
         }
 
@@ -81,6 +88,18 @@ impl BitsVar {
         res
     }
 
+    pub fn get_bit(&self, i: usize) -> M31Var {
+        M31Var {
+            cs: self.cs(),
+            value: if self.value[i] {
+                M31::one()
+            } else {
+                M31::zero()
+            },
+            variable: self.variables[i],
+        }
+    }
+
     pub fn get_value(&self) -> M31 {
         let mut sum_value = M31::zero();
 
@@ -0,0 +1,138 @@
+use crate::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use num_traits::One;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
+
+/// Exponentiation where the exponent is a little-endian `BitsVar`.
+pub trait PowVar: Var + Sized {
+    /// Computes `self^exp` by square-and-multiply, starting from the most significant bit.
+    fn pow_var(&self, exp: &BitsVar) -> Self;
+
+    /// Computes `base^exp` for a constant base, by multiplying the precomputed constants
+    /// `base^(2^i)` selected by each bit of the exponent.
+    fn pow_fixed_base(cs: &ConstraintSystemRef, base: Self::Value, exp: &BitsVar) -> Self;
+}
+
+impl PowVar for M31Var {
+    fn pow_var(&self, exp: &BitsVar) -> Self {
+        let cs = self.cs().and(&exp.cs());
+        let one = M31Var::one(&cs);
+
+        // the factor for each bit is 1 + (self - 1) * bit
+        let self_minus_one = self - &one;
+
+        let mut cur: Option<M31Var> = None;
+        for i in (0..exp.variables.len()).rev() {
+            let factor = &(&self_minus_one * &exp.get_bit(i)) + &one;
+            cur = Some(match cur {
+                None => factor,
+                Some(cur) => &(&cur * &cur) * &factor,
+            });
+        }
+        cur.unwrap_or(one)
+    }
+
+    fn pow_fixed_base(cs: &ConstraintSystemRef, base: M31, exp: &BitsVar) -> Self {
+        let cs = cs.and(&exp.cs());
+        let one = M31Var::one(&cs);
+
+        let mut power = base;
+        let mut cur: Option<M31Var> = None;
+        for i in 0..exp.variables.len() {
+            // the factor for each bit is 1 + (base^(2^i) - 1) * bit
+            let factor = &exp.get_bit(i).mul_constant(power - M31::one()) + &one;
+            cur = Some(match cur {
+                None => factor,
+                Some(cur) => &cur * &factor,
+            });
+            power = power * power;
+        }
+        cur.unwrap_or(one)
+    }
+}
+
+impl PowVar for QM31Var {
+    fn pow_var(&self, exp: &BitsVar) -> Self {
+        let cs = self.cs().and(&exp.cs());
+        let one = QM31Var::one(&cs);
+
+        // the factor for each bit is 1 + (self - 1) * bit
+        let self_minus_one = self - &one;
+
+        let mut cur: Option<QM31Var> = None;
+        for i in (0..exp.variables.len()).rev() {
+            let factor = &(&self_minus_one * &exp.get_bit(i)) + &one;
+            cur = Some(match cur {
+                None => factor,
+                Some(cur) => &(&cur * &cur) * &factor,
+            });
+        }
+        cur.unwrap_or(one)
+    }
+
+    fn pow_fixed_base(cs: &ConstraintSystemRef, base: QM31, exp: &BitsVar) -> Self {
+        let cs = cs.and(&exp.cs());
+        let one = QM31Var::one(&cs);
+
+        let mut power = base;
+        let mut cur: Option<QM31Var> = None;
+        for i in 0..exp.variables.len() {
+            // the factor for each bit is 1 + (base^(2^i) - 1) * bit
+            let power_minus_one = QM31Var::new_constant(&cs, &(power - QM31::one()));
+            let factor = &(&power_minus_one * &exp.get_bit(i)) + &one;
+            cur = Some(match cur {
+                None => factor,
+                Some(cur) => &cur * &factor,
+            });
+            power = power * power;
+        }
+        cur.unwrap_or(one)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BitsVar, PowVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::{M31Var, QM31Var};
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::fields::FieldExpOps;
+
+    #[test]
+    fn test_pow_var() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        for _ in 0..10 {
+            let exp = prng.gen_range(0..(1u32 << 20));
+            let exp_var = BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(exp)), 20);
+
+            let a: M31 = prng.gen();
+            let a_var = M31Var::new_witness(&cs, &a);
+            a_var
+                .pow_var(&exp_var)
+                .equalverify(&M31Var::new_witness(&cs, &a.pow(exp as u128)));
+            M31Var::pow_fixed_base(&cs, a, &exp_var)
+                .equalverify(&M31Var::new_witness(&cs, &a.pow(exp as u128)));
+
+            let b: QM31 = prng.gen();
+            let b_var = QM31Var::new_witness(&cs, &b);
+            b_var
+                .pow_var(&exp_var)
+                .equalverify(&QM31Var::new_witness(&cs, &b.pow(exp as u128)));
+            QM31Var::pow_fixed_base(&cs, b, &exp_var)
+                .equalverify(&QM31Var::new_witness(&cs, &b.pow(exp as u128)));
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
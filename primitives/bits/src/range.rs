@@ -0,0 +1,187 @@
+use crate::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_fields::M31Var;
+use stwo_prover::core::fields::m31::M31;
+
+/// Range checks and comparisons for `M31Var`, built on top of bit decomposition.
+///
+/// Each check decomposes the value only once, and bounds against constants are enforced on the
+/// bits directly.
+pub trait RangeCheckVar: Sized {
+    /// Enforces `self < 2^bits` and returns the little-endian bits.
+    fn assert_in_range(&self, bits: usize) -> BitsVar;
+
+    /// Enforces `self < bound`, for a constant `0 < bound <= 2^30`.
+    fn assert_less_than_const(&self, bound: u32);
+
+    /// Returns a bit indicating whether `self < rhs`.
+    ///
+    /// Both `self` and `rhs` must be known (range-checked elsewhere) to be smaller than `2^bits`.
+    fn is_less_than(&self, rhs: &Self, bits: usize) -> Self;
+
+    /// Returns the smaller one between `self` and `rhs`, under the same assumption as `is_less_than`.
+    fn min(&self, rhs: &Self, bits: usize) -> Self;
+
+    /// Returns the larger one between `self` and `rhs`, under the same assumption as `is_less_than`.
+    fn max(&self, rhs: &Self, bits: usize) -> Self;
+}
+
+impl RangeCheckVar for M31Var {
+    fn assert_in_range(&self, bits: usize) -> BitsVar {
+        assert!(bits <= 31);
+        BitsVar::from_m31(self, bits)
+    }
+
+    fn assert_less_than_const(&self, bound: u32) {
+        assert!(bound > 0 && bound <= 1 << 30);
+        assert!(self.value.0 < bound);
+
+        let cs = self.cs();
+        if bound == 1 {
+            cs.enforce_zero(self.variable);
+            return;
+        }
+
+        // decompose once into as many bits as bound - 1 has, and compare the bits to it
+        let l = (32 - (bound - 1).leading_zeros()) as usize;
+        BitsVar::from_m31(self, l).assert_at_most_const(bound - 1);
+    }
+
+    fn is_less_than(&self, rhs: &M31Var, bits: usize) -> M31Var {
+        assert!(bits <= 29);
+        let cs = self.cs().and(&rhs.cs());
+
+        // self - rhs + 2^bits lies in (0, 2^(bits + 1)), and its top bit is set iff self >= rhs
+        let shift = M31Var::new_constant(&cs, &M31::from(1 << bits));
+        let diff = &(self - rhs) + &shift;
+        let diff_bits = BitsVar::from_m31(&diff, bits + 1);
+
+        &M31Var::one(&cs) - &diff_bits.get_bit(bits)
+    }
+
+    fn min(&self, rhs: &M31Var, bits: usize) -> M31Var {
+        let is_less_than = self.is_less_than(rhs, bits);
+        rhs + &(&is_less_than * &(self - rhs))
+    }
+
+    fn max(&self, rhs: &M31Var, bits: usize) -> M31Var {
+        let min = self.min(rhs, bits);
+        &(self + rhs) - &min
+    }
+}
+
+impl BitsVar {
+    /// Enforces that the number represented by the bits is at most the constant `c`.
+    ///
+    /// Going down from the top bit, `prefix_equal` tracks whether the bits so far equal those of
+    /// `c`, and a bit can only be set where `c` has a zero bit once the prefix is already smaller.
+    /// This takes at most one row per bit, down to the lowest zero bit of `c`.
+    pub fn assert_at_most_const(&self, c: u32) {
+        let l = self.value.len();
+        assert!(l <= 31);
+        assert!(self.get_value().0 <= c);
+
+        if c as u64 >= (1u64 << l) - 1 {
+            return;
+        }
+
+        let cs = self.cs();
+        let mut prefix_equal = 1;
+        for i in ((!c).trailing_zeros() as usize..l).rev() {
+            if (c >> i) & 1 == 1 {
+                prefix_equal = if prefix_equal == 1 {
+                    self.variables[i]
+                } else {
+                    cs.mul(prefix_equal, self.variables[i])
+                };
+            } else {
+                cs.insert_gate(prefix_equal, self.variables[i], 0, M31::zero());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BitsVar, RangeCheckVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+
+    #[test]
+    fn test_range_check() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        for _ in 0..10 {
+            let bound = prng.gen_range(1..(1u32 << 20));
+            let a = prng.gen_range(0..bound);
+            let a_var = M31Var::new_witness(&cs, &M31::from(a));
+            a_var.assert_less_than_const(bound);
+            a_var.assert_in_range(20);
+
+            let b = prng.gen_range(0..(1u32 << 20));
+            let b_var = M31Var::new_witness(&cs, &M31::from(b));
+
+            let is_less_than = a_var.is_less_than(&b_var, 20);
+            assert_eq!(is_less_than.value, M31::from((a < b) as u32));
+            a_var
+                .min(&b_var, 20)
+                .equalverify(&M31Var::new_witness(&cs, &M31::from(a.min(b))));
+            a_var
+                .max(&b_var, 20)
+                .equalverify(&M31Var::new_witness(&cs, &M31::from(a.max(b))));
+        }
+
+        let a_var = M31Var::new_witness(&cs, &M31::from(1023));
+        a_var.assert_less_than_const(1024);
+        assert_eq!(a_var.is_less_than(&a_var, 10).value, M31::from(0));
+
+        // 999 = 0b1111100111 needs one row for each bit from the second highest down to its
+        // lowest zero bit, on top of a single 10-bit decomposition
+        let a_var = M31Var::new_witness(&cs, &M31::from(998));
+        let num_rows = cs.num_plonk_rows();
+        let _ = BitsVar::from_m31(&a_var, 10);
+        let decomposition_rows = cs.num_plonk_rows() - num_rows;
+        a_var.assert_less_than_const(1000);
+        assert_eq!(cs.num_plonk_rows() - num_rows, 2 * decomposition_rows + 6);
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_assert_at_most_const() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        for c in [0u32, 5, 6, 10, 12, 15] {
+            for v in 0..=c {
+                let v_var = M31Var::new_witness(&cs, &M31::from(v));
+                BitsVar::from_m31(&v_var, 4).assert_at_most_const(c);
+            }
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    #[should_panic(expected = "is incorrect")]
+    fn test_range_check_out_of_range() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let a_var = M31Var::new_witness(&cs, &M31::from(999));
+        a_var.assert_less_than_const(1000);
+
+        // a witness at the bound no longer matches its decomposition
+        cs.set_value(a_var.variable, QM31::from(M31::from(1000)));
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
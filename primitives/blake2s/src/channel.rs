@@ -0,0 +1,116 @@
+use crate::{Blake2sDigestVar, Blake2sHasherVar};
+use circle_plonk_dsl_channel::{ByteChannelVar, ByteHasherVar};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_uint::U8Var;
+
+impl ByteHasherVar for Blake2sHasherVar {
+    type DigestVar = Blake2sDigestVar;
+
+    fn hash_bytes(cs: &ConstraintSystemRef, bytes: &[U8Var]) -> Blake2sDigestVar {
+        Self::hash(cs, bytes)
+    }
+
+    fn digest_to_bytes(digest: &Blake2sDigestVar) -> Vec<U8Var> {
+        digest.to_bytes()
+    }
+}
+
+/// The in-circuit counterpart of stwo's Blake2s channel.
+pub type Blake2sChannelVar = ByteChannelVar<Blake2sHasherVar>;
+
+#[cfg(test)]
+mod test {
+    use crate::{Blake2sChannelVar, Blake2sDigestVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::QM31Var;
+    use circle_plonk_dsl_uint::U32Var;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::channel::{Blake2sChannel, Channel, MerkleChannel};
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::vcs::blake2_hash::Blake2sHash;
+    use stwo_prover::core::vcs::blake2_merkle::Blake2sMerkleChannel;
+
+    #[test]
+    fn test_blake2s_channel() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+        let mut channel = Blake2sChannel::default();
+        let mut channel_var = Blake2sChannelVar::default(&cs);
+
+        let root: [u8; 32] = prng.gen();
+        Blake2sMerkleChannel::mix_root(&mut channel, Blake2sHash(root));
+        channel_var.mix_root(&Blake2sDigestVar::new_witness(&cs, &root));
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        let felts: [QM31; 3] = prng.gen();
+        channel.mix_felts(&felts);
+        channel_var.mix_felts(
+            &felts
+                .iter()
+                .map(|felt| QM31Var::new_witness(&cs, felt))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        assert_eq!(channel_var.draw_felt().value, channel.draw_felt());
+        for (a, b) in channel_var.draw_felts(3).iter().zip(channel.draw_felts(3)) {
+            assert_eq!(a.value, b);
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_blake2s_channel_nonce_and_queries() {
+        const POW_BITS: u32 = 8;
+
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+        let mut channel = Blake2sChannel::default();
+        let mut channel_var = Blake2sChannelVar::default(&cs);
+
+        channel.mix_u64(12345);
+        channel_var.mix_u64(12345);
+        channel.mix_u32s(&[1, 2, 3]);
+        channel_var.mix_u32s(&[1u32, 2, 3].map(|w| U32Var::new_witness(&cs, &w)));
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        let nonce = (0u64..)
+            .find(|&nonce| {
+                let mut channel = channel.clone();
+                channel.mix_u64(nonce);
+                channel.trailing_zeros() >= POW_BITS
+            })
+            .unwrap();
+        channel.mix_u64(nonce);
+
+        let nonce_var = [nonce as u32, (nonce >> 32) as u32].map(|w| U32Var::new_witness(&cs, &w));
+        channel_var.mix_nonce(&nonce_var);
+        channel_var.verify_pow_nonce(POW_BITS);
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        // the queries are the lower bits of the little-endian words of the random bytes
+        let mut expected = vec![];
+        while expected.len() < 10 {
+            for chunk in channel.draw_random_bytes().chunks_exact(4) {
+                expected.push(u32::from_le_bytes(chunk.try_into().unwrap()) & ((1 << 20) - 1));
+            }
+        }
+        expected.truncate(10);
+
+        let queries = channel_var.draw_queries(10, 20);
+        assert_eq!(
+            queries
+                .iter()
+                .map(|query| query.get_value().0)
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -0,0 +1,233 @@
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_uint::{U32Var, U8Var};
+
+pub mod merkle;
+pub use merkle::*;
+
+pub mod channel;
+pub use channel::*;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// A Blake2s-256 digest, stored as the eight little-endian words of the final state.
+#[derive(Clone)]
+pub struct Blake2sDigestVar {
+    pub value: [u8; 32],
+    pub words: [U32Var; 8],
+}
+
+impl Var for Blake2sDigestVar {
+    type Value = [u8; 32];
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.words[0].cs()
+    }
+}
+
+impl AllocVar for Blake2sDigestVar {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let words = std::array::from_fn(|i| {
+            let word = u32::from_le_bytes(value[i * 4..i * 4 + 4].try_into().unwrap());
+            U32Var::new_variables(cs, &word, mode)
+        });
+
+        Self {
+            value: *value,
+            words,
+        }
+    }
+}
+
+impl Blake2sDigestVar {
+    pub fn from_words(words: [U32Var; 8]) -> Self {
+        let mut value = [0u8; 32];
+        for (chunk, word) in value.chunks_mut(4).zip(words.iter()) {
+            chunk.copy_from_slice(&word.value.to_le_bytes());
+        }
+
+        Self { value, words }
+    }
+
+    pub fn to_bytes(&self) -> Vec<U8Var> {
+        self.words
+            .iter()
+            .flat_map(|word| word.to_bytes_le())
+            .collect()
+    }
+
+    pub fn equalverify(&self, rhs: &Blake2sDigestVar) {
+        for (a, b) in self.words.iter().zip(rhs.words.iter()) {
+            a.equalverify(b);
+        }
+    }
+
+    /// Returns `(a, b)` if the bit is zero, and `(b, a)` otherwise.
+    pub fn swap(
+        a: &Blake2sDigestVar,
+        b: &Blake2sDigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+    ) -> (Blake2sDigestVar, Blake2sDigestVar) {
+        let (left, right): (Vec<_>, Vec<_>) = a
+            .words
+            .iter()
+            .zip(b.words.iter())
+            .map(|(x, y)| U32Var::swap(x, y, bit_value, bit_variable))
+            .unzip();
+
+        (
+            Self::from_words(left.try_into().ok().unwrap()),
+            Self::from_words(right.try_into().ok().unwrap()),
+        )
+    }
+}
+
+pub struct Blake2sHasherVar;
+
+impl Blake2sHasherVar {
+    /// The initial state of Blake2s-256 without a key.
+    pub fn initial_state(cs: &ConstraintSystemRef) -> [U32Var; 8] {
+        let mut h = IV;
+        h[0] ^= 0x01010020;
+        std::array::from_fn(|i| U32Var::new_constant(cs, &h[i]))
+    }
+
+    /// Applies the Blake2s compression function to a block of sixteen little-endian words, where
+    /// `t` is the number of bytes hashed so far, including this block.
+    pub fn compress(h: &[U32Var; 8], block: &[U32Var; 16], t: u64, last: bool) -> [U32Var; 8] {
+        let cs = h[0].cs().and(&block[0].cs());
+
+        // the counter and the finalization flag only affect the constant half of the state
+        let mut iv = IV;
+        iv[4] ^= t as u32;
+        iv[5] ^= (t >> 32) as u32;
+        if last {
+            iv[6] = !iv[6];
+        }
+
+        let mut v = h.to_vec();
+        v.extend(iv.iter().map(|x| U32Var::new_constant(&cs, x)));
+
+        for s in SIGMA.iter() {
+            g(&mut v, [0, 4, 8, 12], &block[s[0]], &block[s[1]]);
+            g(&mut v, [1, 5, 9, 13], &block[s[2]], &block[s[3]]);
+            g(&mut v, [2, 6, 10, 14], &block[s[4]], &block[s[5]]);
+            g(&mut v, [3, 7, 11, 15], &block[s[6]], &block[s[7]]);
+            g(&mut v, [0, 5, 10, 15], &block[s[8]], &block[s[9]]);
+            g(&mut v, [1, 6, 11, 12], &block[s[10]], &block[s[11]]);
+            g(&mut v, [2, 7, 8, 13], &block[s[12]], &block[s[13]]);
+            g(&mut v, [3, 4, 9, 14], &block[s[14]], &block[s[15]]);
+        }
+
+        std::array::from_fn(|i| U32Var::from_bits(&h[i].bits.xor(&v[i].bits).xor(&v[i + 8].bits)))
+    }
+
+    /// Hashes a message whose length is fixed by the circuit.
+    pub fn hash(cs: &ConstraintSystemRef, message: &[U8Var]) -> Blake2sDigestVar {
+        Self::hash_from_state(&Self::initial_state(cs), 0, message)
+    }
+
+    /// Continues hashing from the state after `absorbed_len` bytes, which must be a multiple of
+    /// the block size and have been compressed as non-final blocks.
+    pub fn hash_from_state(
+        h: &[U32Var; 8],
+        absorbed_len: usize,
+        message: &[U8Var],
+    ) -> Blake2sDigestVar {
+        assert_eq!(absorbed_len % 64, 0);
+        // otherwise, the last absorbed block should have been the final one
+        assert!(absorbed_len == 0 || !message.is_empty());
+        let cs = h[0].cs();
+
+        // the message is padded with zeros to whole blocks, with at least one block
+        let mut bytes = message.to_vec();
+        if bytes.is_empty() || bytes.len() % 64 != 0 {
+            bytes.resize(
+                bytes.len().div_ceil(64).max(1) * 64,
+                U8Var::new_constant(&cs, &0),
+            );
+        }
+
+        let n_blocks = bytes.len() / 64;
+        let mut h = h.clone();
+        for (i, chunk) in bytes.chunks(64).enumerate() {
+            let last = i == n_blocks - 1;
+            let t = absorbed_len + if last { message.len() } else { (i + 1) * 64 };
+
+            let block = std::array::from_fn(|j| U32Var::from_bytes_le(&chunk[j * 4..j * 4 + 4]));
+            h = Self::compress(&h, &block, t as u64, last);
+        }
+
+        Blake2sDigestVar::from_words(h)
+    }
+}
+
+/// The mixing function, applied to the state words at positions `[a, b, c, d]`.
+fn g(v: &mut [U32Var], [a, b, c, d]: [usize; 4], x: &U32Var, y: &U32Var) {
+    v[a] = sum(&[&v[a], &v[b], x]);
+    v[d] = U32Var::from_bits(&v[d].bits.xor(&v[a].bits).rotate_right(16));
+    v[c] = v[c].wrapping_add(&v[d]);
+    v[b] = U32Var::from_bits(&v[b].bits.xor(&v[c].bits).rotate_right(12));
+    v[a] = sum(&[&v[a], &v[b], y]);
+    v[d] = U32Var::from_bits(&v[d].bits.xor(&v[a].bits).rotate_right(8));
+    v[c] = v[c].wrapping_add(&v[d]);
+    v[b] = U32Var::from_bits(&v[b].bits.xor(&v[c].bits).rotate_right(7));
+}
+
+fn sum(values: &[&U32Var]) -> U32Var {
+    let bits = values.iter().map(|v| &v.bits).collect::<Vec<_>>();
+    U32Var::from_bits(&BitsVar::sum_u32(&bits))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Blake2sHasherVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_uint::U8Var;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::vcs::blake2_hash::Blake2sHasher;
+
+    #[test]
+    fn test_blake2s() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            // the empty message, a partial block, exactly one block, and two blocks
+            for len in [0, 3, 64, 100] {
+                let message = (0..len).map(|_| prng.gen()).collect::<Vec<u8>>();
+                let message_var = message
+                    .iter()
+                    .map(|b| U8Var::new_witness(&cs, b))
+                    .collect::<Vec<_>>();
+
+                let digest = Blake2sHasherVar::hash(&cs, &message_var);
+                assert_eq!(digest.value, Blake2sHasher::hash(&message).0);
+            }
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+}
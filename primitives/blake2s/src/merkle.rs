@@ -0,0 +1,551 @@
+use crate::{Blake2sDigestVar, Blake2sHasherVar, IV, SIGMA};
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use circle_plonk_dsl_merkle::{
+    verify_single_pair_merkle_proof, verify_single_path_merkle_proof, MerkleColumnVar,
+    MerkleHasherVar,
+};
+use circle_plonk_dsl_uint::{U32Var, U8Var};
+use std::collections::BTreeMap;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
+use stwo_prover::core::vcs::blake2_hash::Blake2sHash;
+use stwo_prover::core::vcs::blake2_merkle::Blake2sMerkleHasher;
+use stwo_prover::core::vcs::ops::MerkleHasher;
+
+/// Returns the Blake2s state after compressing the children hashes as a non-final block.
+///
+/// The hash of a node with columns is then obtained by continuing from this state, so that a
+/// sibling with columns can be decommitted with this state in place of its hash.
+pub fn hash_children_get_state(left: &Blake2sHash, right: &Blake2sHash) -> Blake2sHash {
+    let mut bytes = left.0.to_vec();
+    bytes.extend_from_slice(&right.0);
+
+    let mut state = IV;
+    state[0] ^= 0x01010020;
+    state_to_hash(&compress(&state, &bytes, 64, false))
+}
+
+/// Finishes the hash of a node with columns from the state after its children.
+pub fn hash_columns_from_state(state: &Blake2sHash, columns: &[M31]) -> Blake2sHash {
+    assert!(!columns.is_empty());
+    let mut state: [u32; 8] =
+        std::array::from_fn(|i| u32::from_le_bytes(state.0[i * 4..i * 4 + 4].try_into().unwrap()));
+
+    let mut bytes = columns
+        .iter()
+        .flat_map(|v| v.0.to_le_bytes())
+        .collect::<Vec<u8>>();
+    let len = bytes.len();
+    bytes.resize(len.div_ceil(64) * 64, 0);
+
+    let n_blocks = bytes.len() / 64;
+    for (i, chunk) in bytes.chunks(64).enumerate() {
+        let last = i == n_blocks - 1;
+        let t = 64 + if last { len } else { (i + 1) * 64 };
+        state = compress(&state, chunk, t as u64, last);
+    }
+
+    state_to_hash(&state)
+}
+
+/// The native counterpart of `Blake2sHasherVar::compress`, on a block of 64 bytes.
+fn compress(h: &[u32; 8], block: &[u8], t: u64, last: bool) -> [u32; 8] {
+    let m: [u32; 16] =
+        std::array::from_fn(|i| u32::from_le_bytes(block[i * 4..i * 4 + 4].try_into().unwrap()));
+
+    let mut v = [0u32; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= t as u32;
+    v[13] ^= (t >> 32) as u32;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for s in SIGMA.iter() {
+        g(&mut v, [0, 4, 8, 12], m[s[0]], m[s[1]]);
+        g(&mut v, [1, 5, 9, 13], m[s[2]], m[s[3]]);
+        g(&mut v, [2, 6, 10, 14], m[s[4]], m[s[5]]);
+        g(&mut v, [3, 7, 11, 15], m[s[6]], m[s[7]]);
+        g(&mut v, [0, 5, 10, 15], m[s[8]], m[s[9]]);
+        g(&mut v, [1, 6, 11, 12], m[s[10]], m[s[11]]);
+        g(&mut v, [2, 7, 8, 13], m[s[12]], m[s[13]]);
+        g(&mut v, [3, 4, 9, 14], m[s[14]], m[s[15]]);
+    }
+
+    std::array::from_fn(|i| h[i] ^ v[i] ^ v[i + 8])
+}
+
+fn g(v: &mut [u32; 16], [a, b, c, d]: [usize; 4], x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+fn state_to_hash(state: &[u32; 8]) -> Blake2sHash {
+    let mut res = [0u8; 32];
+    for (chunk, word) in res.chunks_mut(4).zip(state.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    Blake2sHash(res)
+}
+
+/// The Blake2s Merkle hasher, which hashes the children hashes followed by the column values,
+/// each as a little-endian u32.
+pub struct Blake2sMerkleHasherVar;
+
+impl MerkleHasherVar for Blake2sMerkleHasherVar {
+    type HashVar = Blake2sDigestVar;
+
+    fn hash_value(hash: &Blake2sDigestVar) -> [u8; 32] {
+        hash.value
+    }
+
+    fn hash_node<C: MerkleColumnVar>(
+        cs: &ConstraintSystemRef,
+        children: Option<(&Blake2sDigestVar, &Blake2sDigestVar)>,
+        columns: &[C],
+    ) -> Blake2sDigestVar {
+        let mut bytes = vec![];
+        if let Some((left, right)) = children {
+            bytes.extend(left.to_bytes());
+            bytes.extend(right.to_bytes());
+        }
+        bytes.extend(column_bytes(columns));
+        Blake2sHasherVar::hash(cs, &bytes)
+    }
+
+    fn hash_node_with_swap<C: MerkleColumnVar>(
+        cur: &Blake2sDigestVar,
+        sibling: &Blake2sDigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+        columns: &[C],
+    ) -> Blake2sDigestVar {
+        let (left, right) = Blake2sDigestVar::swap(cur, sibling, bit_value, bit_variable);
+        Self::hash_node(&cur.cs(), Some((&left, &right)), columns)
+    }
+
+    /// The digest of the children is the state after them, as computed by
+    /// `hash_children_get_state`.
+    fn hash_node_from_children_digest<C: MerkleColumnVar>(
+        children_digest: &Blake2sDigestVar,
+        columns: &[C],
+    ) -> Blake2sDigestVar {
+        Blake2sHasherVar::hash_from_state(&children_digest.words, 64, &column_bytes(columns))
+    }
+
+    fn select(
+        a: &Blake2sDigestVar,
+        b: &Blake2sDigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+    ) -> Blake2sDigestVar {
+        Blake2sDigestVar::swap(a, b, bit_value, bit_variable).0
+    }
+
+    fn equalverify(lhs: &Blake2sDigestVar, rhs: &Blake2sDigestVar) {
+        lhs.equalverify(rhs)
+    }
+}
+
+fn column_bytes<C: MerkleColumnVar>(columns: &[C]) -> Vec<U8Var> {
+    C::to_m31(columns)
+        .iter()
+        .flat_map(|v| U32Var::from_m31(v).to_bytes_le())
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2sSinglePathMerkleProof {
+    pub query: usize,
+
+    pub sibling_hashes: Vec<Blake2sHash>,
+    pub columns: BTreeMap<usize, Vec<M31>>,
+
+    pub root: Blake2sHash,
+    pub depth: usize,
+}
+
+impl Blake2sSinglePathMerkleProof {
+    pub fn verify(&self) {
+        let mut cur_hash =
+            Blake2sMerkleHasher::hash_node(None, self.columns.get(&self.depth).unwrap_or(&vec![]));
+
+        for i in 0..self.depth {
+            let h = self.depth - i - 1;
+
+            cur_hash = Blake2sMerkleHasher::hash_node(
+                if (self.query >> i) & 1 == 0 {
+                    Some((cur_hash, self.sibling_hashes[i]))
+                } else {
+                    Some((self.sibling_hashes[i], cur_hash))
+                },
+                self.columns.get(&h).unwrap_or(&vec![]),
+            );
+        }
+
+        assert_eq!(cur_hash, self.root);
+    }
+}
+
+#[derive(Clone)]
+pub struct Blake2sSinglePathMerkleProofVar {
+    pub cs: ConstraintSystemRef,
+    pub value: Blake2sSinglePathMerkleProof,
+    pub sibling_hashes: Vec<Blake2sDigestVar>,
+    pub columns: BTreeMap<usize, Vec<M31Var>>,
+}
+
+impl Var for Blake2sSinglePathMerkleProofVar {
+    type Value = Blake2sSinglePathMerkleProof;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl Blake2sSinglePathMerkleProofVar {
+    pub fn new(cs: &ConstraintSystemRef, value: &Blake2sSinglePathMerkleProof) -> Self {
+        let mut sibling_hashes = vec![];
+        for sibling_hash in value.sibling_hashes.iter() {
+            sibling_hashes.push(Blake2sDigestVar::new_witness(cs, &sibling_hash.0));
+        }
+
+        let mut columns = BTreeMap::new();
+        for (k, v) in value.columns.iter() {
+            let mut v_var = vec![];
+            for vv in v.iter() {
+                v_var.push(M31Var::new_witness(cs, vv));
+            }
+            columns.insert(*k, v_var);
+        }
+
+        Self {
+            cs: cs.clone(),
+            value: value.clone(),
+            sibling_hashes,
+            columns,
+        }
+    }
+
+    pub fn get_values(&self) -> &BTreeMap<usize, Vec<M31Var>> {
+        &self.columns
+    }
+
+    pub fn verify(&self, root: &Blake2sDigestVar, query: &BitsVar) {
+        // verify that the Merkle proof is valid
+        self.value.verify();
+        assert_eq!(root.value, self.value.root.0);
+        assert_eq!(query.get_value().0, self.value.query as u32);
+
+        verify_single_path_merkle_proof::<Blake2sMerkleHasherVar>(
+            root,
+            query,
+            self.value.depth,
+            &self.sibling_hashes,
+            &self.columns,
+        );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2sSinglePairMerkleProof {
+    pub query: usize,
+
+    /// For a layer where the sibling has columns, the entry is the state after the sibling's
+    /// children, as computed by `hash_children_get_state`.
+    pub sibling_hashes: Vec<Blake2sHash>,
+    pub self_columns: BTreeMap<usize, QM31>,
+    pub siblings_columns: BTreeMap<usize, QM31>,
+
+    pub root: Blake2sHash,
+    pub depth: usize,
+}
+
+impl Blake2sSinglePairMerkleProof {
+    pub fn verify(&self) {
+        let mut self_hash = Blake2sMerkleHasher::hash_node(
+            None,
+            &self
+                .self_columns
+                .get(&self.depth)
+                .map_or(vec![], |v| v.to_m31_array().to_vec()),
+        );
+        let mut sibling_hash = Blake2sMerkleHasher::hash_node(
+            None,
+            &self
+                .siblings_columns
+                .get(&self.depth)
+                .map_or(vec![], |v| v.to_m31_array().to_vec()),
+        );
+
+        for i in 0..self.depth {
+            let h = self.depth - i - 1;
+
+            let children = if (self.query >> i) & 1 == 0 {
+                Some((self_hash, sibling_hash))
+            } else {
+                Some((sibling_hash, self_hash))
+            };
+
+            if !self.self_columns.contains_key(&h) {
+                self_hash = Blake2sMerkleHasher::hash_node(children, &[]);
+                if i != self.depth - 1 {
+                    sibling_hash = self.sibling_hashes[i];
+                }
+            } else {
+                self_hash = Blake2sMerkleHasher::hash_node(
+                    children,
+                    &self.self_columns.get(&h).unwrap().to_m31_array(),
+                );
+                sibling_hash = hash_columns_from_state(
+                    &self.sibling_hashes[i],
+                    &self.siblings_columns.get(&h).unwrap().to_m31_array(),
+                );
+            }
+        }
+
+        assert_eq!(self_hash, self.root);
+    }
+}
+
+#[derive(Clone)]
+pub struct Blake2sSinglePairMerkleProofVar {
+    pub cs: ConstraintSystemRef,
+    pub value: Blake2sSinglePairMerkleProof,
+    pub sibling_hashes: Vec<Blake2sDigestVar>,
+    pub self_columns: BTreeMap<usize, QM31Var>,
+    pub siblings_columns: BTreeMap<usize, QM31Var>,
+}
+
+impl Var for Blake2sSinglePairMerkleProofVar {
+    type Value = Blake2sSinglePairMerkleProof;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl Blake2sSinglePairMerkleProofVar {
+    pub fn new(cs: &ConstraintSystemRef, value: &Blake2sSinglePairMerkleProof) -> Self {
+        let mut sibling_hashes = vec![];
+        for sibling_hash in value.sibling_hashes.iter() {
+            sibling_hashes.push(Blake2sDigestVar::new_witness(cs, &sibling_hash.0));
+        }
+
+        let mut self_columns = BTreeMap::new();
+        for (k, v) in value.self_columns.iter() {
+            self_columns.insert(*k, QM31Var::new_witness(cs, v));
+        }
+
+        let mut siblings_columns = BTreeMap::new();
+        for (k, v) in value.siblings_columns.iter() {
+            siblings_columns.insert(*k, QM31Var::new_witness(cs, v));
+        }
+
+        Self {
+            cs: cs.clone(),
+            value: value.clone(),
+            sibling_hashes,
+            self_columns,
+            siblings_columns,
+        }
+    }
+
+    pub fn verify(&self, root: &Blake2sDigestVar, query: &BitsVar) {
+        // verify that the Merkle proof is valid
+        self.value.verify();
+        assert_eq!(root.value, self.value.root.0);
+        assert_eq!(query.get_value().0, self.value.query as u32);
+
+        verify_single_pair_merkle_proof::<Blake2sMerkleHasherVar>(
+            root,
+            query,
+            self.value.depth,
+            &self.sibling_hashes,
+            &self.self_columns,
+            &self.siblings_columns,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        hash_children_get_state, hash_columns_from_state, Blake2sDigestVar, Blake2sMerkleHasherVar,
+        Blake2sSinglePairMerkleProof, Blake2sSinglePairMerkleProofVar,
+        Blake2sSinglePathMerkleProof, Blake2sSinglePathMerkleProofVar,
+    };
+    use circle_plonk_dsl_bits::BitsVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use circle_plonk_dsl_merkle::MerkleHasherVar;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::vcs::blake2_hash::Blake2sHash;
+    use stwo_prover::core::vcs::blake2_merkle::Blake2sMerkleHasher;
+    use stwo_prover::core::vcs::ops::MerkleHasher;
+
+    #[test]
+    fn test_blake2s_hash_from_children_state() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let left = Blake2sHash(prng.gen());
+        let right = Blake2sHash(prng.gen());
+        let state = hash_children_get_state(&left, &right);
+
+        // columns that fit in one block, fill it exactly, and take several blocks
+        for n_columns in [1, 4, 16, 20, 40] {
+            let columns: Vec<M31> = (0..n_columns).map(|_| prng.gen()).collect();
+            assert_eq!(
+                hash_columns_from_state(&state, &columns),
+                Blake2sMerkleHasher::hash_node(Some((left, right)), &columns)
+            );
+        }
+    }
+
+    #[test]
+    fn test_blake2s_hash_node_from_children_digest() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let left = Blake2sHash(prng.gen());
+        let right = Blake2sHash(prng.gen());
+        let state = hash_children_get_state(&left, &right);
+
+        let columns: Vec<M31> = (0..20).map(|_| prng.gen()).collect();
+        let expected = Blake2sMerkleHasher::hash_node(Some((left, right)), &columns);
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            let state_var = Blake2sDigestVar::new_witness(&cs, &state.0);
+            let columns_var = columns
+                .iter()
+                .map(|v| M31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>();
+
+            let hash =
+                Blake2sMerkleHasherVar::hash_node_from_children_digest(&state_var, &columns_var);
+            assert_eq!(hash.value, expected.0);
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+
+    #[test]
+    fn test_blake2s_merkle_proofs() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        // a tree of depth 3 with four columns at the leaves and one column at the layer above
+        let depth = 3;
+        let leaves: Vec<Vec<M31>> = (0..8)
+            .map(|_| (0..4).map(|_| prng.gen()).collect())
+            .collect();
+        let middle: Vec<Vec<M31>> = (0..4).map(|_| vec![prng.gen()]).collect();
+
+        let mut layers: Vec<Vec<Blake2sHash>> = vec![leaves
+            .iter()
+            .map(|v| Blake2sMerkleHasher::hash_node(None, v))
+            .collect()];
+        for h in (0..depth).rev() {
+            let prev = layers.last().unwrap();
+            let layer = (0..1 << h)
+                .map(|j| {
+                    let columns = if h == 2 { middle[j].clone() } else { vec![] };
+                    Blake2sMerkleHasher::hash_node(Some((prev[2 * j], prev[2 * j + 1])), &columns)
+                })
+                .collect();
+            layers.push(layer);
+        }
+        let root = layers[depth][0];
+
+        let query = 6;
+        let path_proof = Blake2sSinglePathMerkleProof {
+            query,
+            sibling_hashes: (0..depth).map(|i| layers[i][(query >> i) ^ 1]).collect(),
+            columns: BTreeMap::from([(3, leaves[query].clone()), (2, middle[query >> 1].clone())]),
+            root,
+            depth,
+        };
+
+        // a pair of QM31 leaves, and a QM31 column in the layer above
+        let qm31_leaves: Vec<QM31> = (0..8).map(|_| prng.gen()).collect();
+        let qm31_middle: Vec<QM31> = (0..4).map(|_| prng.gen()).collect();
+        let mut layers: Vec<Vec<Blake2sHash>> = vec![qm31_leaves
+            .iter()
+            .map(|v| Blake2sMerkleHasher::hash_node(None, &v.to_m31_array()))
+            .collect()];
+        let mut states = vec![];
+        for h in (0..depth).rev() {
+            let prev = layers.last().unwrap();
+            states.push(
+                (0..1 << h)
+                    .map(|j| hash_children_get_state(&prev[2 * j], &prev[2 * j + 1]))
+                    .collect::<Vec<_>>(),
+            );
+            let layer = (0..1 << h)
+                .map(|j| {
+                    let columns = if h == 2 {
+                        qm31_middle[j].to_m31_array().to_vec()
+                    } else {
+                        vec![]
+                    };
+                    Blake2sMerkleHasher::hash_node(Some((prev[2 * j], prev[2 * j + 1])), &columns)
+                })
+                .collect();
+            layers.push(layer);
+        }
+        let pair_root = layers[depth][0];
+
+        let pair_proof = Blake2sSinglePairMerkleProof {
+            query,
+            sibling_hashes: vec![
+                states[0][(query >> 1) ^ 1],
+                layers[2][(query >> 2) ^ 1],
+                layers[3][0],
+            ],
+            self_columns: BTreeMap::from([(3, qm31_leaves[query]), (2, qm31_middle[query >> 1])]),
+            siblings_columns: BTreeMap::from([
+                (3, qm31_leaves[query ^ 1]),
+                (2, qm31_middle[(query >> 1) ^ 1]),
+            ]),
+            root: pair_root,
+            depth,
+        };
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            let query_bits = (0..depth)
+                .map(|i| (query >> i) & 1 != 0)
+                .collect::<Vec<_>>();
+            let query_var = BitsVar::new_witness(&cs, &query_bits);
+
+            let root_var = Blake2sDigestVar::new_witness(&cs, &root.0);
+            Blake2sSinglePathMerkleProofVar::new(&cs, &path_proof).verify(&root_var, &query_var);
+
+            let pair_root_var = Blake2sDigestVar::new_witness(&cs, &pair_root.0);
+            Blake2sSinglePairMerkleProofVar::new(&cs, &pair_proof)
+                .verify(&pair_root_var, &query_var);
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+}
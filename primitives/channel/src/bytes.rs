@@ -0,0 +1,196 @@
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use circle_plonk_dsl_uint::{U32Var, U8Var};
+use stwo_prover::core::fields::m31::P;
+
+/// A hash over bytes with 32-byte digests, from which `ByteChannelVar` is built.
+pub trait ByteHasherVar {
+    type DigestVar: AllocVar<Value = [u8; 32]>;
+
+    fn hash_bytes(cs: &ConstraintSystemRef, bytes: &[U8Var]) -> Self::DigestVar;
+
+    fn digest_to_bytes(digest: &Self::DigestVar) -> Vec<U8Var>;
+}
+
+/// The in-circuit counterpart of stwo's byte-oriented channels, such as `Blake2sChannel`, which
+/// hash the digest together with the mixed data, each element as little-endian bytes.
+pub struct ByteChannelVar<H: ByteHasherVar> {
+    pub n_sent: usize,
+    pub digest: H::DigestVar,
+}
+
+impl<H: ByteHasherVar> Clone for ByteChannelVar<H> {
+    fn clone(&self) -> Self {
+        Self {
+            n_sent: self.n_sent,
+            digest: self.digest.clone(),
+        }
+    }
+}
+
+impl<H: ByteHasherVar> Var for ByteChannelVar<H> {
+    type Value = [u8; 32];
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.digest.cs()
+    }
+}
+
+impl<H: ByteHasherVar> ByteChannelVar<H> {
+    pub fn default(cs: &ConstraintSystemRef) -> Self {
+        let n_sent = 0;
+        let digest = H::DigestVar::new_constant(cs, &[0u8; 32]);
+        Self { n_sent, digest }
+    }
+
+    pub fn mix_root(&mut self, root: &H::DigestVar) {
+        let mut bytes = H::digest_to_bytes(&self.digest);
+        bytes.extend(H::digest_to_bytes(root));
+        self.update_digest(&bytes);
+    }
+
+    pub fn mix_felts(&mut self, felts: &[QM31Var]) {
+        let mut bytes = H::digest_to_bytes(&self.digest);
+        for felt in felts.iter() {
+            for limb in felt.decompose_m31().iter() {
+                bytes.extend(U32Var::from_m31(limb).to_bytes_le());
+            }
+        }
+        self.update_digest(&bytes);
+    }
+
+    pub fn mix_u32s(&mut self, data: &[U32Var]) {
+        let mut bytes = H::digest_to_bytes(&self.digest);
+        for word in data.iter() {
+            bytes.extend(word.to_bytes_le());
+        }
+        self.update_digest(&bytes);
+    }
+
+    /// Mixes a constant `u64` as its lower and upper words.
+    pub fn mix_u64(&mut self, value: u64) {
+        let cs = self.cs();
+        let words = [value as u32, (value >> 32) as u32].map(|w| U32Var::new_constant(&cs, &w));
+        self.mix_u32s(&words);
+    }
+
+    /// Mixes the proof-of-work nonce, given as its lower and upper words, as with `mix_u64`.
+    pub fn mix_nonce(&mut self, nonce: &[U32Var; 2]) {
+        self.mix_u32s(nonce);
+    }
+
+    /// Enforces that the digest, read as a little-endian integer, has at least `pow_bits`
+    /// trailing zeros, which is the proof-of-work check once the nonce is mixed.
+    pub fn verify_pow_nonce(&self, pow_bits: u32) {
+        assert!(pow_bits <= 128);
+        let cs = self.cs();
+        let bytes = H::digest_to_bytes(&self.digest);
+
+        let lower = u128::from_le_bytes(std::array::from_fn(|i| bytes[i].value));
+        assert!(
+            lower.trailing_zeros() >= pow_bits,
+            "the proof of work is invalid"
+        );
+
+        for i in 0..pow_bits as usize {
+            cs.enforce_zero(bytes[i / 8].bits.variables[i % 8]);
+        }
+    }
+
+    /// Hashes the digest together with the counter, padded to 32 bytes.
+    pub fn draw_random_bytes(&mut self) -> Vec<U8Var> {
+        let cs = self.cs();
+
+        let mut padded_counter = [0u8; 32];
+        padded_counter[0..8].copy_from_slice(&(self.n_sent as u64).to_le_bytes());
+        self.n_sent += 1;
+
+        let mut bytes = H::digest_to_bytes(&self.digest);
+        for byte in padded_counter.iter() {
+            bytes.push(U8Var::new_constant(&cs, byte));
+        }
+        H::digest_to_bytes(&H::hash_bytes(&cs, &bytes))
+    }
+
+    /// Draws eight base field elements, requiring each little-endian word to be below `2P`.
+    ///
+    /// The native channel would retry with the next counter otherwise, which happens with a
+    /// negligible probability and is not supported in the circuit.
+    pub fn draw_base_felts(&mut self) -> [M31Var; 8] {
+        let cs = self.cs();
+        let bytes = self.draw_random_bytes();
+
+        std::array::from_fn(|i| {
+            let word = U32Var::from_bytes_le(&bytes[i * 4..i * 4 + 4]);
+            assert!(
+                word.value < 2 * P,
+                "the drawn word is rejected by the channel"
+            );
+
+            // a word is below 2P = 2^32 - 2 iff its upper 31 bits are not all ones
+            let mut product = word.bits.get_bit(1);
+            for j in 2..32 {
+                product = &product * &word.bits.get_bit(j);
+            }
+            cs.enforce_zero(product.variable);
+
+            word.to_m31()
+        })
+    }
+
+    pub fn draw_felt(&mut self) -> QM31Var {
+        let felts = self.draw_base_felts();
+        QM31Var::from_m31(&felts[0], &felts[1], &felts[2], &felts[3])
+    }
+
+    pub fn draw_felts(&mut self, n: usize) -> Vec<QM31Var> {
+        let mut base_felts = vec![];
+        let mut res = Vec::with_capacity(n);
+        for i in 0..n {
+            if i % 2 == 0 {
+                base_felts = self.draw_base_felts().to_vec();
+            }
+            let felts = &base_felts[(i % 2) * 4..(i % 2) * 4 + 4];
+            res.push(QM31Var::from_m31(
+                &felts[0], &felts[1], &felts[2], &felts[3],
+            ));
+        }
+        res
+    }
+
+    /// Draws `n` raw queries, each an element of the base field, from felts drawn two at a time
+    /// as in `ChannelVar::draw_raw_queries`.
+    pub fn draw_raw_queries(&mut self, n: usize) -> Vec<M31Var> {
+        let mut raw_queries = Vec::with_capacity(n);
+        while raw_queries.len() < n {
+            for felt in self.draw_felts(2).iter() {
+                raw_queries.extend_from_slice(&felt.decompose_m31());
+            }
+        }
+        raw_queries.truncate(n);
+        raw_queries
+    }
+
+    /// Draws `n` queries on a domain of size `2^log_domain_size`, as the lower bits of the
+    /// little-endian words of the random bytes, in the order they are drawn.
+    pub fn draw_queries(&mut self, n: usize, log_domain_size: u32) -> Vec<BitsVar> {
+        assert!(log_domain_size <= 32);
+
+        let mut queries = Vec::with_capacity(n);
+        while queries.len() < n {
+            let bytes = self.draw_random_bytes();
+            for chunk in bytes.chunks_exact(4).take(n - queries.len()) {
+                let word = U32Var::from_bytes_le(chunk);
+                queries.push(word.bits.index_range(0..log_domain_size as usize));
+            }
+        }
+        queries
+    }
+
+    fn update_digest(&mut self, bytes: &[U8Var]) {
+        self.digest = H::hash_bytes(&self.cs(), bytes);
+        self.n_sent = 0;
+    }
+}
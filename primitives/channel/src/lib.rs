@@ -1,15 +1,25 @@
+use circle_plonk_dsl_bits::{BitsVar, RangeCheckVar};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_fields::{M31Var, QM31Var};
 use circle_plonk_dsl_poseidon31::Poseidon2HalfVar;
+use circle_plonk_dsl_uint::U32Var;
 use stwo_prover::core::fields::m31::M31;
 
+pub mod trace;
+pub use trace::*;
+
+pub mod bytes;
+pub use bytes::*;
+
 pub type HashVar = Poseidon2HalfVar;
 
 #[derive(Clone)]
 pub struct ChannelVar {
     pub n_sent: usize,
     pub digest: Poseidon2HalfVar,
+    /// The operations so far, which are only recorded once `enable_trace` is called.
+    pub trace: Option<Vec<TranscriptEntry>>,
 }
 
 impl Var for ChannelVar {
@@ -24,12 +34,35 @@ impl ChannelVar {
     pub fn default(cs: &ConstraintSystemRef) -> Self {
         let n_sent = 0;
         let digest = Poseidon2HalfVar::zero(cs);
-        Self { n_sent, digest }
+        Self {
+            n_sent,
+            digest,
+            trace: None,
+        }
+    }
+
+    /// Starts recording every operation with the digests before and after it, to be compared
+    /// with a `Poseidon31ChannelTracer` on the native side.
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    fn record(&mut self, op: ChannelOp, digest_before: [M31; 8]) {
+        let digest_after = self.digest.value();
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(TranscriptEntry {
+                op,
+                digest_before,
+                digest_after,
+            });
+        }
     }
 
     pub fn mix_root(&mut self, root: &HashVar) {
+        let digest_before = self.digest.value();
         self.digest = Poseidon2HalfVar::permute_get_capacity(root, &self.digest);
         self.n_sent = 0;
+        self.record(ChannelOp::MixRoot(root.value()), digest_before);
     }
 
     pub fn draw_felts(&mut self) -> [QM31Var; 2] {
@@ -41,19 +74,206 @@ impl ChannelVar {
         let n_sent = QM31Var::from(&n_sent);
 
         let left = Poseidon2HalfVar::from_qm31(&n_sent, &QM31Var::zero(&cs));
-        Poseidon2HalfVar::permute_get_rate(&left, &self.digest).to_qm31()
+        let felts = Poseidon2HalfVar::permute_get_rate(&left, &self.digest).to_qm31();
+
+        let op = ChannelOp::DrawFelts([felts[0].value, felts[1].value]);
+        self.record(op, self.digest.value());
+        felts
     }
 
     pub fn mix_one_felt(&mut self, felt: &QM31Var) {
         let cs = self.cs();
+        let digest_before = self.digest.value();
         let left = Poseidon2HalfVar::from_qm31(&felt, &QM31Var::zero(&cs));
         self.digest = Poseidon2HalfVar::permute_get_capacity(&left, &self.digest);
         self.n_sent = 0;
+        self.record(ChannelOp::MixOneFelt(felt.value), digest_before);
     }
 
     pub fn mix_two_felts(&mut self, felt1: &QM31Var, felt2: &QM31Var) {
+        let digest_before = self.digest.value();
         let left = Poseidon2HalfVar::from_qm31(&felt1, &felt2);
         self.digest = Poseidon2HalfVar::permute_get_capacity(&left, &self.digest);
         self.n_sent = 0;
+        self.record(
+            ChannelOp::MixTwoFelts(felt1.value, felt2.value),
+            digest_before,
+        );
+    }
+
+    /// Mixes a `u64`, given as its limbs of `NONCE_LIMB_BITS`, as one felt, where the limbs are
+    /// range-checked.
+    pub fn mix_u64(&mut self, limbs: &[M31Var; 3]) {
+        let cs = self.cs();
+        for (limb, bits) in limbs.iter().zip(NONCE_LIMB_BITS) {
+            let _ = limb.assert_in_range(bits);
+        }
+        let felt = QM31Var::from_m31(&limbs[0], &limbs[1], &limbs[2], &M31Var::zero(&cs));
+        self.mix_one_felt(&felt);
+    }
+
+    /// Mixes a constant `u64` as with `mix_u64`.
+    pub fn mix_constant_u64(&mut self, value: u64) {
+        let cs = self.cs();
+        let limbs = split_u64(value).map(|limb| M31Var::new_constant(&cs, &M31::from(limb)));
+        let felt = QM31Var::from_m31(&limbs[0], &limbs[1], &limbs[2], &M31Var::zero(&cs));
+        self.mix_one_felt(&felt);
+    }
+
+    /// Mixes words as the native channel does: each word is split into its lower and upper 16
+    /// bits, two words make up one felt, and the felts are mixed two at a time.
+    pub fn mix_u32s(&mut self, data: &[U32Var]) {
+        if data.is_empty() {
+            return;
+        }
+        let felts = U32Var::pack_qm31(data);
+        for pair in felts.chunks(2) {
+            match pair {
+                [felt1, felt2] => self.mix_two_felts(felt1, felt2),
+                [felt] => self.mix_one_felt(felt),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Mixes constant words as with `mix_u32s`.
+    pub fn mix_constant_u32s(&mut self, data: &[u32]) {
+        let cs = self.cs();
+        let words = data
+            .iter()
+            .map(|word| U32Var::new_constant(&cs, word))
+            .collect::<Vec<_>>();
+        self.mix_u32s(&words);
+    }
+
+    /// Mixes the proof-of-work nonce, given as the limbs of `mix_u64`.
+    pub fn mix_nonce(&mut self, nonce: &[M31Var; 3]) {
+        self.mix_u64(nonce);
+    }
+
+    /// Enforces that the first element of the digest has at least `pow_bits` trailing zeros,
+    /// which is the proof-of-work check once the nonce is mixed.
+    pub fn verify_pow_nonce(&self, pow_bits: u32) {
+        let cs = self.cs();
+        let first = &self.digest.to_qm31()[0].decompose_m31()[0];
+        let lower_bits = BitsVar::from_m31(first, 31).compose_range(0..pow_bits as usize);
+        lower_bits.equalverify(&M31Var::zero(&cs));
+    }
+
+    /// Draws `n` raw queries, each an element of the base field, eight from each draw.
+    pub fn draw_raw_queries(&mut self, n: usize) -> Vec<M31Var> {
+        let mut raw_queries = Vec::with_capacity(n);
+        for _ in 0..n.div_ceil(8) {
+            for felt in self.draw_felts().iter() {
+                raw_queries.extend_from_slice(&felt.decompose_m31());
+            }
+        }
+        raw_queries.truncate(n);
+        raw_queries
+    }
+
+    /// Draws `n` queries on a domain of size `2^log_domain_size`, as the lower bits of the raw
+    /// queries.
+    pub fn draw_queries(&mut self, n: usize, log_domain_size: u32) -> Vec<BitsVar> {
+        self.draw_raw_queries(n)
+            .iter()
+            .map(|raw_query| {
+                BitsVar::from_m31(raw_query, 31).index_range(0..log_domain_size as usize)
+            })
+            .collect()
+    }
+}
+
+/// The number of bits in each limb of a `u64` mixed into the channel.
+pub const NONCE_LIMB_BITS: [usize; 3] = [22, 21, 21];
+
+/// Splits a `u64` into the limbs of `NONCE_LIMB_BITS`, where the first limb is reduced modulo
+/// `2^22 - 1` as in the native channel.
+pub fn split_u64(value: u64) -> [u32; 3] {
+    [
+        (value % ((1 << 22) - 1)) as u32,
+        ((value >> 22) & ((1 << 21) - 1)) as u32,
+        ((value >> 43) & ((1 << 21) - 1)) as u32,
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{split_u64, ChannelVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use circle_plonk_dsl_uint::U32Var;
+    use stwo_prover::core::channel::{Channel, Poseidon31Channel};
+    use stwo_prover::core::fields::m31::M31;
+
+    #[test]
+    fn test_channel_nonce_and_queries() {
+        const POW_BITS: u32 = 8;
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let mut channel = Poseidon31Channel::default();
+        let mut channel_var = ChannelVar::default(&cs);
+
+        channel.mix_u64(12345);
+        channel_var.mix_constant_u64(12345);
+        channel.mix_u64(67890);
+        let value_var = split_u64(67890).map(|limb| M31Var::new_witness(&cs, &M31::from(limb)));
+        channel_var.mix_u64(&value_var);
+
+        let nonce = (0u64..)
+            .find(|&nonce| {
+                let mut channel = channel.clone();
+                channel.mix_u64(nonce);
+                channel.trailing_zeros() >= POW_BITS
+            })
+            .unwrap();
+        channel.mix_u64(nonce);
+
+        let nonce_var = split_u64(nonce).map(|limb| M31Var::new_witness(&cs, &M31::from(limb)));
+        channel_var.mix_nonce(&nonce_var);
+        channel_var.verify_pow_nonce(POW_BITS);
+        assert_eq!(channel_var.digest.value(), channel.digest());
+
+        let mut channel_var_copy = channel_var.clone();
+        let raw_queries = channel_var.draw_raw_queries(10);
+        let queries = channel_var_copy.draw_queries(10, 20);
+        assert_eq!(raw_queries.len(), 10);
+        for (raw_query, query) in raw_queries.iter().zip(queries.iter()) {
+            assert_eq!(query.get_value().0, raw_query.value.0 & ((1 << 20) - 1));
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_channel_mix_u32s() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let mut channel = Poseidon31Channel::default();
+        let mut channel_var = ChannelVar::default(&cs);
+
+        for data in [
+            vec![0xdeadbeef],
+            vec![1, 2, 3, 4],
+            vec![0xffffffff, 0x12345678, 0x9abcdef0, 7, 0x80000000],
+        ] {
+            channel.mix_u32s(&data);
+            channel_var.mix_constant_u32s(&data);
+            assert_eq!(channel_var.digest.value(), channel.digest());
+
+            channel.mix_u32s(&data);
+            let data_var = data
+                .iter()
+                .map(|word| U32Var::new_witness(&cs, word))
+                .collect::<Vec<_>>();
+            channel_var.mix_u32s(&data_var);
+            assert_eq!(channel_var.digest.value(), channel.digest());
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
     }
 }
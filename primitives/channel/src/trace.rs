@@ -0,0 +1,197 @@
+use crate::split_u64;
+use num_traits::Zero;
+use stwo_prover::core::channel::{Channel, MerkleChannel, Poseidon31Channel};
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
+use stwo_prover::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+
+/// An operation on the channel, with the values that it mixed or drew.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChannelOp {
+    MixRoot([M31; 8]),
+    MixOneFelt(QM31),
+    MixTwoFelts(QM31, QM31),
+    DrawFelts([QM31; 2]),
+}
+
+/// An operation on the channel together with the digest before and after it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    pub op: ChannelOp,
+    pub digest_before: [M31; 8],
+    pub digest_after: [M31; 8],
+}
+
+/// A native `Poseidon31Channel` that records its transcript with the same operations as
+/// `ChannelVar`, for comparison with the transcript of the circuit.
+#[derive(Clone, Default)]
+pub struct Poseidon31ChannelTracer {
+    pub channel: Poseidon31Channel,
+    pub trace: Vec<TranscriptEntry>,
+}
+
+impl Poseidon31ChannelTracer {
+    fn record(&mut self, op: ChannelOp, digest_before: [M31; 8]) {
+        self.trace.push(TranscriptEntry {
+            op,
+            digest_before,
+            digest_after: self.channel.digest(),
+        });
+    }
+
+    pub fn mix_root(&mut self, root: Poseidon31Hash) {
+        let digest_before = self.channel.digest();
+        Poseidon31MerkleChannel::mix_root(&mut self.channel, root);
+        self.record(ChannelOp::MixRoot(root.0), digest_before);
+    }
+
+    pub fn mix_one_felt(&mut self, felt: QM31) {
+        let digest_before = self.channel.digest();
+        self.channel.mix_felts(&[felt]);
+        self.record(ChannelOp::MixOneFelt(felt), digest_before);
+    }
+
+    pub fn mix_two_felts(&mut self, felt1: QM31, felt2: QM31) {
+        let digest_before = self.channel.digest();
+        self.channel.mix_felts(&[felt1, felt2]);
+        self.record(ChannelOp::MixTwoFelts(felt1, felt2), digest_before);
+    }
+
+    pub fn draw_felts(&mut self) -> [QM31; 2] {
+        let digest_before = self.channel.digest();
+        let felts: [QM31; 2] = self.channel.draw_felts(2).try_into().unwrap();
+        self.record(ChannelOp::DrawFelts(felts), digest_before);
+        felts
+    }
+
+    /// Mixes a `u64` with the native `mix_u64`, which mixes the limbs of `split_u64` as one felt.
+    pub fn mix_u64(&mut self, value: u64) {
+        let digest_before = self.channel.digest();
+        self.channel.mix_u64(value);
+        let limbs = split_u64(value).map(M31::from);
+        let felt = QM31::from_m31(limbs[0], limbs[1], limbs[2], M31::zero());
+        self.record(ChannelOp::MixOneFelt(felt), digest_before);
+    }
+
+    /// Mixes words as the native `mix_u32s` does, one operation per two felts, so that the
+    /// digests in between can be compared with `ChannelVar::mix_u32s`.
+    pub fn mix_u32s(&mut self, data: &[u32]) {
+        let felts = data
+            .chunks(2)
+            .map(|words| {
+                let mut limbs = [M31::zero(); 4];
+                for (i, &word) in words.iter().enumerate() {
+                    limbs[2 * i] = M31::from(word & 0xffff);
+                    limbs[2 * i + 1] = M31::from(word >> 16);
+                }
+                QM31::from_m31_array(limbs)
+            })
+            .collect::<Vec<_>>();
+        for pair in felts.chunks(2) {
+            match pair {
+                [felt1, felt2] => self.mix_two_felts(*felt1, *felt2),
+                [felt] => self.mix_one_felt(*felt),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Mixes the proof-of-work nonce, which the native channel mixes as a `u64`.
+    pub fn mix_nonce(&mut self, nonce: u64) {
+        self.mix_u64(nonce);
+    }
+
+    /// Draws `n` raw queries, eight from each draw, as `ChannelVar::draw_raw_queries` does.
+    pub fn draw_raw_queries(&mut self, n: usize) -> Vec<M31> {
+        let mut raw_queries = Vec::with_capacity(n);
+        for _ in 0..n.div_ceil(8) {
+            for felt in self.draw_felts().iter() {
+                raw_queries.extend_from_slice(&felt.to_m31_array());
+            }
+        }
+        raw_queries.truncate(n);
+        raw_queries
+    }
+}
+
+/// Returns the index of the first operation at which the two transcripts differ, including one
+/// that is missing in the shorter transcript.
+pub fn first_divergence(a: &[TranscriptEntry], b: &[TranscriptEntry]) -> Option<usize> {
+    match a.iter().zip(b.iter()).position(|(a, b)| a != b) {
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        position => position,
+    }
+}
+
+/// Asserts that the transcript of the circuit matches the native one, and otherwise panics with
+/// the first diverging operation.
+pub fn assert_transcripts_match(circuit: &[TranscriptEntry], native: &[TranscriptEntry]) {
+    if let Some(i) = first_divergence(circuit, native) {
+        panic!(
+            "transcripts diverge at operation {}: circuit {:?}, native {:?}",
+            i,
+            circuit.get(i),
+            native.get(i)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{first_divergence, ChannelVar, HashVar, Poseidon31ChannelTracer};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::QM31Var;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+    #[test]
+    fn test_channel_transcript() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let root = Poseidon31Hash(prng.gen::<[M31; 8]>());
+        let felts: [QM31; 3] = prng.gen();
+
+        let mut native = Poseidon31ChannelTracer::default();
+        native.mix_root(root);
+        native.mix_two_felts(felts[0], felts[1]);
+        native.mix_one_felt(felts[2]);
+        let drawn = native.draw_felts();
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+        let felts_var = felts.map(|felt| QM31Var::new_witness(&cs, &felt));
+
+        let mut channel = ChannelVar::default(&cs);
+        channel.enable_trace();
+        channel.mix_root(&HashVar::new_witness(&cs, &root.0));
+        channel.mix_two_felts(&felts_var[0], &felts_var[1]);
+        channel.mix_one_felt(&felts_var[2]);
+        let drawn_var = channel.draw_felts();
+        assert_eq!(drawn_var[0].value, drawn[0]);
+        assert_eq!(drawn_var[1].value, drawn[1]);
+
+        let trace = channel.trace.clone().unwrap();
+        assert_eq!(trace.len(), 4);
+        assert_eq!(first_divergence(&trace, &native.trace), None);
+        assert_eq!(first_divergence(&trace[..3], &native.trace), Some(3));
+
+        // mixing the felts in another order diverges at the first of them
+        let mut diverging = ChannelVar::default(&cs);
+        diverging.enable_trace();
+        diverging.mix_root(&HashVar::new_witness(&cs, &root.0));
+        diverging.mix_two_felts(&felts_var[1], &felts_var[0]);
+        diverging.mix_one_felt(&felts_var[2]);
+        diverging.draw_felts();
+        assert_eq!(
+            first_divergence(diverging.trace.as_ref().unwrap(), &native.trace),
+            Some(1)
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -5,11 +5,14 @@ use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_fields::{M31Var, QM31Var};
 use itertools::Itertools;
 use num_traits::{One, Zero};
-use std::ops::{Add, Neg};
+use std::ops::{Add, Neg, Sub};
 use stwo_prover::core::circle::{CirclePoint, Coset};
 use stwo_prover::core::fields::m31::{BaseField, M31};
 use stwo_prover::core::fields::qm31::{SecureField, QM31};
 
+pub mod poly;
+pub use poly::*;
+
 #[derive(Clone, Debug)]
 pub struct CirclePointM31Var {
     pub x: M31Var,
@@ -41,6 +44,8 @@ impl AllocVar for CirclePointM31Var {
     }
 }
 
+/// The group law of the circle, `(x1 * x2 - y1 * y2, x1 * y2 + y1 * x2)`, in 7 rows. It is
+/// complete, so the identity and equal points need no special case.
 impl Add<&CirclePointM31Var> for &CirclePointM31Var {
     type Output = CirclePointM31Var;
 
@@ -131,6 +136,182 @@ impl CirclePointM31Var {
     }
 }
 
+impl CirclePointM31Var {
+    /// The identity of the circle group, `(1, 0)`.
+    pub fn zero(cs: &ConstraintSystemRef) -> Self {
+        Self {
+            x: M31Var::one(cs),
+            y: M31Var::zero(cs),
+        }
+    }
+
+    /// Enforces `x^2 + y^2 = 1`.
+    pub fn assert_on_circle(&self) {
+        let cs = self.cs();
+        let xx = &self.x * &self.x;
+        let yy = &self.y * &self.y;
+        (&xx + &yy).equalverify(&M31Var::one(&cs));
+    }
+
+    /// Returns `a` if the bit is zero, or `b` otherwise, for two variable points.
+    pub fn select_var(a: &Self, b: &Self, bit_value: bool, bit_variable: usize) -> Self {
+        let cs = a.cs().and(&b.cs());
+
+        // the result is a + (b - a) * bit
+        let select = |a: &M31Var, b: &M31Var| {
+            let b_minus_a = b - a;
+            let mut variable = cs.mul(b_minus_a.variable, bit_variable);
+            variable = cs.add(a.variable, variable);
+            M31Var {
+                cs: cs.clone(),
+                value: if bit_value { b.value } else { a.value },
+                variable,
+            }
+        };
+
+        Self {
+            x: select(&a.x, &b.x),
+            y: select(&a.y, &b.y),
+        }
+    }
+
+    /// Looks up `table[a + 2 * b]` for the bits `a` and `b` in a table of constant points, by
+    /// evaluating the bilinear interpolation of the table on the bits, in 13 rows.
+    pub fn lookup_constant_window(
+        cs: &ConstraintSystemRef,
+        table: &[CirclePoint<BaseField>; 4],
+        a: (bool, usize),
+        b: (bool, usize),
+    ) -> Self {
+        let value = table[a.0 as usize + 2 * b.0 as usize];
+        let ab = cs.mul(a.1, b.1);
+
+        let interpolate = |t: [M31; 4], value: M31| {
+            let mut variable = cs.mul_constant(a.1, t[1] - t[0]);
+            variable = cs.add(variable, cs.mul_constant(b.1, t[2] - t[0]));
+            variable = cs.add(variable, cs.mul_constant(ab, t[3] - t[2] - t[1] + t[0]));
+            // the arithmetic gate against the unit computes `t0 * (v + 1) + (1 - t0) * v = v + t0`
+            variable = cs.do_arith_gate(variable, 1, t[0]);
+            M31Var {
+                cs: cs.clone(),
+                value,
+                variable,
+            }
+        };
+
+        Self {
+            x: interpolate(table.map(|p| p.x), value.x),
+            y: interpolate(table.map(|p| p.y), value.y),
+        }
+    }
+
+    /// Computes `initial + sum_i bits[i] * points[i]` for constant points, with the points looked
+    /// up two at a time.
+    pub fn sum_of_selected_constants(
+        cs: &ConstraintSystemRef,
+        initial: &CirclePoint<BaseField>,
+        points: &[CirclePoint<BaseField>],
+        bits: &[(bool, usize)],
+    ) -> Self {
+        let mut cur = Self::new_constant(cs, initial);
+        for (points, bits) in points.chunks(2).zip_eq(bits.chunks(2)) {
+            let point = if points.len() == 1 {
+                Self::select(cs, &points[0], bits[0].0, bits[0].1)
+            } else {
+                let table = [
+                    CirclePoint::zero(),
+                    points[0],
+                    points[1],
+                    points[0] + points[1],
+                ];
+                Self::lookup_constant_window(cs, &table, bits[0], bits[1])
+            };
+            cur = &point + &cur;
+        }
+        cur
+    }
+
+    /// Multiplies the point by the scalar whose bits, from the least significant one, are in
+    /// `bits`, with one doubling, one addition and one selection per bit.
+    pub fn mul_by_bits(&self, bits: &BitsVar) -> Self {
+        let cs = self.cs().and(&bits.cs());
+
+        let mut res = Self::select_var(&Self::zero(&cs), self, bits.value[0], bits.variables[0]);
+        let mut cur = self.clone();
+        let bits_iter = bits.value.iter().zip_eq(bits.variables.iter());
+        for (&bit_value, &bit_variable) in bits_iter.skip(1) {
+            cur = cur.double();
+            let sum = &res + &cur;
+            res = Self::select_var(&res, &sum, bit_value, bit_variable);
+        }
+        res
+    }
+
+    /// Computes the same product as `mul_by_bits` with 2-bit windows over the table
+    /// `[0, P, 2P, 3P]`, which takes 32 rows for every two bits instead of 42, after 29 rows for
+    /// the table.
+    pub fn mul_windowed(&self, bits: &BitsVar) -> Self {
+        let cs = self.cs().and(&bits.cs());
+
+        let double = self.double();
+        let triple = &double + self;
+        let table = [Self::zero(&cs), self.clone(), double, triple];
+
+        // the coefficients of the bilinear interpolation of a coordinate of the table
+        let coefficients = |t: [&M31Var; 4]| {
+            let d1 = t[1] - t[0];
+            let d2 = t[2] - t[0];
+            let d3 = &(t[3] - t[2]) - &d1;
+            [t[0].clone(), d1, d2, d3]
+        };
+        let x_coefficients = coefficients([&table[0].x, &table[1].x, &table[2].x, &table[3].x]);
+        let y_coefficients = coefficients([&table[0].y, &table[1].y, &table[2].y, &table[3].y]);
+
+        let mut bits = bits
+            .value
+            .iter()
+            .zip_eq(bits.variables.iter())
+            .map(|(&bit_value, &bit_variable)| (bit_value, bit_variable))
+            .collect_vec();
+
+        let mut res = if bits.len() % 2 == 1 {
+            let (bit_value, bit_variable) = bits.pop().unwrap();
+            Some(Self::select_var(&table[0], self, bit_value, bit_variable))
+        } else {
+            None
+        };
+
+        for pair in bits.chunks_exact(2).rev() {
+            let (a, b) = (pair[0], pair[1]);
+            let value = table[a.0 as usize + 2 * b.0 as usize].value();
+            let ab = cs.mul(a.1, b.1);
+
+            let interpolate = |c: &[M31Var; 4], value: M31| {
+                let mut variable = cs.mul(c[1].variable, a.1);
+                variable = cs.add(variable, cs.mul(c[2].variable, b.1));
+                variable = cs.add(variable, cs.mul(c[3].variable, ab));
+                variable = cs.add(variable, c[0].variable);
+                M31Var {
+                    cs: cs.clone(),
+                    value,
+                    variable,
+                }
+            };
+            let point = Self {
+                x: interpolate(&x_coefficients, value.x),
+                y: interpolate(&y_coefficients, value.y),
+            };
+
+            res = Some(match res {
+                Some(res) => &res.double().double() + &point,
+                None => point,
+            });
+        }
+
+        res.unwrap_or_else(|| Self::zero(&cs))
+    }
+}
+
 impl CirclePointM31Var {
     pub fn bit_reverse_at(coset: &Coset, bits_var: &BitsVar, log_size: u32) -> Self {
         assert_eq!(bits_var.value.len(), log_size as usize);
@@ -240,14 +421,153 @@ impl Add<&CirclePoint<M31>> for &CirclePointQM31Var {
     }
 }
 
+impl Add<&CirclePointQM31Var> for &CirclePointQM31Var {
+    type Output = CirclePointQM31Var;
+
+    fn add(self, rhs: &CirclePointQM31Var) -> Self::Output {
+        let x1x2 = &self.x * &rhs.x;
+        let y1y2 = &self.y * &rhs.y;
+        let x1y2 = &self.x * &rhs.y;
+        let y1x2 = &self.y * &rhs.x;
+
+        let new_x = &x1x2 - &y1y2;
+        let new_y = &x1y2 + &y1x2;
+
+        CirclePointQM31Var { x: new_x, y: new_y }
+    }
+}
+
+impl Neg for &CirclePointQM31Var {
+    type Output = CirclePointQM31Var;
+
+    fn neg(self) -> Self::Output {
+        self.conjugate()
+    }
+}
+
+impl Sub<&CirclePointQM31Var> for &CirclePointQM31Var {
+    type Output = CirclePointQM31Var;
+
+    fn sub(self, rhs: &CirclePointQM31Var) -> Self::Output {
+        self + &(-rhs)
+    }
+}
+
+impl CirclePointQM31Var {
+    /// The identity of the circle group, `(1, 0)`.
+    pub fn zero(cs: &ConstraintSystemRef) -> Self {
+        Self {
+            x: QM31Var::one(cs),
+            y: QM31Var::zero(cs),
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        let xx = &self.x * &self.x;
+        let yy = &self.y * &self.y;
+        let xy = &self.x * &self.y;
+
+        let new_x = &xx - &yy;
+        let new_y = xy.mul_constant_m31(M31::from(2));
+
+        CirclePointQM31Var { x: new_x, y: new_y }
+    }
+
+    /// Returns the x-coordinate of the double of a point whose x-coordinate is `x`.
+    pub fn double_x(x: &QM31Var) -> QM31Var {
+        let cs = x.cs();
+        let sq = x * x;
+        &(&sq + &sq) - &M31Var::one(&cs)
+    }
+
+    pub fn repeated_double(&self, n: u32) -> Self {
+        let mut res = self.clone();
+        for _ in 0..n {
+            res = res.double();
+        }
+        res
+    }
+
+    /// Returns `(x, -y)`, which is the inverse in the circle group.
+    pub fn conjugate(&self) -> Self {
+        Self {
+            x: self.x.clone(),
+            y: -&self.y,
+        }
+    }
+
+    /// Returns `(-x, -y)`.
+    pub fn antipode(&self) -> Self {
+        Self {
+            x: -&self.x,
+            y: -&self.y,
+        }
+    }
+
+    /// Multiplies the point by a constant scalar with double-and-add.
+    pub fn mul(&self, mut scalar: u128) -> Self {
+        let mut res: Option<Self> = None;
+        let mut cur = self.clone();
+        while scalar > 0 {
+            if scalar & 1 == 1 {
+                res = Some(match res {
+                    Some(res) => &res + &cur,
+                    None => cur.clone(),
+                });
+            }
+            scalar >>= 1;
+            if scalar > 0 {
+                cur = cur.double();
+            }
+        }
+        res.unwrap_or_else(|| Self::zero(&self.cs()))
+    }
+
+    /// Multiplies the point by the scalar whose bits, from the least significant one, are in
+    /// `bits`.
+    pub fn mul_by_bits(&self, bits: &BitsVar) -> Self {
+        let cs = self.cs().and(&bits.cs());
+
+        let mut res = Self::select(&Self::zero(&cs), self, bits.value[0], bits.variables[0]);
+        let mut cur = self.clone();
+        let bits_iter = bits.value.iter().zip_eq(bits.variables.iter());
+        for (&bit_value, &bit_variable) in bits_iter.skip(1) {
+            cur = cur.double();
+            let sum = &res + &cur;
+            res = Self::select(&res, &sum, bit_value, bit_variable);
+        }
+        res
+    }
+
+    /// Returns `a` if the bit is zero, or `b` otherwise.
+    pub fn select(a: &Self, b: &Self, bit_value: bool, bit_variable: usize) -> Self {
+        Self {
+            x: QM31Var::select(&a.x, &b.x, bit_value, bit_variable),
+            y: QM31Var::select(&a.y, &b.y, bit_value, bit_variable),
+        }
+    }
+
+    /// Enforces `x^2 + y^2 = 1`.
+    pub fn assert_on_circle(&self) {
+        let cs = self.cs();
+        let xx = &self.x * &self.x;
+        let yy = &self.y * &self.y;
+        (&xx + &yy).equalverify(&QM31Var::one(&cs));
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::CirclePointM31Var;
+    use crate::{CirclePointM31Var, CirclePointQM31Var};
     use circle_plonk_dsl_bits::BitsVar;
     use circle_plonk_dsl_constraint_system::var::AllocVar;
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
-    use circle_plonk_dsl_fields::M31Var;
+    use circle_plonk_dsl_fields::{M31Var, QM31Var};
+    use num_traits::One;
+    use stwo_prover::core::circle::CirclePoint;
     use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::fields::FieldExpOps;
     use stwo_prover::core::poly::circle::CanonicCoset;
     use stwo_prover::core::utils::bit_reverse_index;
 
@@ -274,4 +594,87 @@ mod test {
         assert_eq!(b.x, b_point.x.value);
         assert_eq!(b.y, b_point.y.value);
     }
+
+    #[test]
+    fn test_circle_point_qm31_arithmetic() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let point_from_t = |t: QM31| {
+            let t_squared_plus_1_inverse = (t * t + QM31::one()).inverse();
+            CirclePoint {
+                x: (QM31::one() - t * t) * t_squared_plus_1_inverse,
+                y: (t + t) * t_squared_plus_1_inverse,
+            }
+        };
+
+        let t_a = QM31::from_u32_unchecked(1, 2, 3, 4);
+        let t_b = QM31::from_u32_unchecked(5, 6, 7, 8);
+        let a = point_from_t(t_a);
+        let b = point_from_t(t_b);
+
+        let a_var = CirclePointQM31Var::from_t(&QM31Var::new_witness(&cs, &t_a));
+        let b_var = CirclePointQM31Var::from_t(&QM31Var::new_witness(&cs, &t_b));
+        assert_eq!(a_var.value(), a);
+        assert_eq!(b_var.value(), b);
+
+        a_var.assert_on_circle();
+        b_var.assert_on_circle();
+
+        assert_eq!((&a_var + &b_var).value(), a + b);
+        assert_eq!((&a_var - &b_var).value(), a - b);
+        assert_eq!((-&a_var).value(), -a);
+        assert_eq!(a_var.conjugate().value(), a.conjugate());
+        assert_eq!(a_var.antipode().value(), a.antipode());
+        assert_eq!(a_var.double().value(), a.double());
+        assert_eq!(a_var.repeated_double(5).value(), a.repeated_double(5));
+        assert_eq!(a_var.mul(0).value(), CirclePoint::zero());
+        assert_eq!(a_var.mul(1234567).value(), a.mul(1234567));
+
+        let scalar: u32 = 40001;
+        let bits = BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(scalar)), 16);
+        let product = a_var.mul_by_bits(&bits);
+        assert_eq!(product.value(), a.mul(scalar as u128));
+        product.assert_on_circle();
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_circle_point_m31_mul_by_bits() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let point = CanonicCoset::new(16).circle_domain().at(12345);
+        let point_var = CirclePointM31Var::new_witness(&cs, &point);
+        point_var.assert_on_circle();
+
+        for (scalar, num_bits) in [(0u32, 16), (40001, 16), (20001, 15), (1, 1)] {
+            let bits = BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(scalar)), num_bits);
+            let expected = point.mul(scalar as u128);
+
+            let product = point_var.mul_by_bits(&bits);
+            assert_eq!(product.value(), expected);
+
+            let product = point_var.mul_windowed(&bits);
+            assert_eq!(product.value(), expected);
+            product.assert_on_circle();
+        }
+
+        let steps = [point, point.double(), point.repeated_double(2)];
+        for scalar in 0..8u32 {
+            let bits = BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(scalar)), 3);
+            let selectors = [0, 1, 2].map(|i| (bits.value[i], bits.variables[i]));
+
+            let sum = CirclePointM31Var::sum_of_selected_constants(
+                &cs,
+                &point.antipode(),
+                &steps,
+                &selectors,
+            );
+            assert_eq!(sum.value(), point.antipode() + point.mul(scalar as u128));
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
 }
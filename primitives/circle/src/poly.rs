@@ -0,0 +1,142 @@
+use crate::CirclePointQM31Var;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use itertools::Itertools;
+use stwo_prover::core::fields::qm31::SecureField;
+
+/// A circle polynomial given by its coefficients in the FFT basis, in the order of stwo's
+/// `CirclePoly`. The coefficients are in the secure field, so that a polynomial over the base
+/// field is obtained with `from_m31_coeffs`.
+#[derive(Clone, Debug)]
+pub struct CirclePolyVar {
+    pub cs: ConstraintSystemRef,
+    pub coeffs: Vec<QM31Var>,
+}
+
+impl Var for CirclePolyVar {
+    type Value = Vec<SecureField>;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl AllocVar for CirclePolyVar {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let coeffs = value
+            .iter()
+            .map(|v| QM31Var::new_variables(cs, v, mode))
+            .collect_vec();
+        CirclePolyVar {
+            cs: cs.clone(),
+            coeffs,
+        }
+    }
+}
+
+impl CirclePolyVar {
+    pub fn from_m31_coeffs(coeffs: &[M31Var]) -> Self {
+        let mut cs = coeffs[0].cs();
+        for coeff in coeffs.iter().skip(1) {
+            cs = cs.and(&coeff.cs());
+        }
+
+        CirclePolyVar {
+            cs,
+            coeffs: coeffs.iter().map(QM31Var::from).collect_vec(),
+        }
+    }
+
+    pub fn log_size(&self) -> u32 {
+        assert!(self.coeffs.len().is_power_of_two());
+        self.coeffs.len().ilog2()
+    }
+
+    /// Evaluates the polynomial at a point, folding the coefficients with `y` and then with the
+    /// repeated doublings of `x`, as stwo's `CirclePoly::eval_at_point` does.
+    pub fn eval_at_point(&self, point: &CirclePointQM31Var) -> QM31Var {
+        let log_size = self.log_size();
+        if log_size == 0 {
+            return self.coeffs[0].clone();
+        }
+
+        let mut mappings = vec![point.y.clone()];
+        let mut x = point.x.clone();
+        for _ in 1..log_size {
+            mappings.push(x.clone());
+            x = CirclePointQM31Var::double_x(&x);
+        }
+        mappings.reverse();
+
+        fn fold(values: &[QM31Var], folding_factors: &[QM31Var]) -> QM31Var {
+            let n = values.len();
+            assert_eq!(n, 1 << folding_factors.len());
+            if n == 1 {
+                return values[0].clone();
+            }
+            let (lhs_values, rhs_values) = values.split_at(n / 2);
+            let (folding_factor, folding_factors) = folding_factors.split_first().unwrap();
+            let lhs_val = fold(lhs_values, folding_factors);
+            let rhs_val = fold(rhs_values, folding_factors);
+            &lhs_val + &(&rhs_val * folding_factor)
+        }
+
+        fold(&self.coeffs, &mappings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CirclePointQM31Var, CirclePolyVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::{M31Var, QM31Var};
+    use itertools::Itertools;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::backend::CpuBackend;
+    use stwo_prover::core::circle::SECURE_FIELD_CIRCLE_GEN;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::poly::circle::CirclePoly;
+
+    #[test]
+    fn test_circle_poly_eval_at_point() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+        for log_size in [0, 1, 5] {
+            let point = SECURE_FIELD_CIRCLE_GEN.mul(prng.gen::<u128>());
+            let point_var = CirclePointQM31Var::new_witness(&cs, &point);
+
+            let coeffs: Vec<M31> = (0..1 << log_size).map(|_| prng.gen()).collect_vec();
+            let poly = CirclePoly::<CpuBackend>::new(coeffs.clone());
+
+            let coeffs_var = coeffs
+                .iter()
+                .map(|coeff| M31Var::new_witness(&cs, coeff))
+                .collect_vec();
+            let res = CirclePolyVar::from_m31_coeffs(&coeffs_var).eval_at_point(&point_var);
+            res.equalverify(&QM31Var::new_witness(&cs, &poly.eval_at_point(point)));
+
+            // by linearity, a polynomial with secure coefficients evaluates to the combination
+            // of the evaluations of its coordinate polynomials
+            let secure_coeffs: Vec<QM31> = (0..1 << log_size).map(|_| prng.gen()).collect_vec();
+            let partial_evals = std::array::from_fn(|i| {
+                let coordinate_coeffs = secure_coeffs
+                    .iter()
+                    .map(|coeff| coeff.to_m31_array()[i])
+                    .collect_vec();
+                CirclePoly::<CpuBackend>::new(coordinate_coeffs).eval_at_point(point)
+            });
+            let expected = QM31::from_partial_evals(partial_evals);
+
+            let res = CirclePolyVar::new_witness(&cs, &secure_coeffs).eval_at_point(&point_var);
+            res.equalverify(&QM31Var::new_witness(&cs, &expected));
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -0,0 +1,271 @@
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_circle::{CirclePointM31Var, CirclePointQM31Var};
+use circle_plonk_dsl_constraint_system::var::Var;
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use itertools::Itertools;
+use stwo_prover::core::circle::Coset;
+use stwo_prover::core::poly::circle::{CanonicCoset, CircleDomain};
+
+/// A coset of the circle group that is fixed by the circuit, while the points in it can be
+/// selected by indices that are variables.
+#[derive(Clone, Debug)]
+pub struct CosetVar {
+    pub cs: ConstraintSystemRef,
+    pub coset: Coset,
+}
+
+impl Var for CosetVar {
+    type Value = Coset;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl CosetVar {
+    pub fn new(cs: &ConstraintSystemRef, coset: Coset) -> Self {
+        Self {
+            cs: cs.clone(),
+            coset,
+        }
+    }
+
+    /// The canonic coset of size `2^log_size`, over which the traces are defined.
+    pub fn canonic(cs: &ConstraintSystemRef, log_size: u32) -> Self {
+        Self::new(cs, CanonicCoset::new(log_size).coset)
+    }
+
+    pub fn log_size(&self) -> u32 {
+        self.coset.log_size
+    }
+
+    /// Evaluates at `p` the polynomial that vanishes on the coset, as stwo's `coset_vanishing`.
+    pub fn vanishing_at(&self, p: &CirclePointQM31Var) -> QM31Var {
+        let coset = &self.coset;
+
+        // move the coset onto the canonic coset of the same size, whose vanishing polynomial is
+        // the x-coordinate doubled `log_size - 1` times
+        let mut x = (p + &(-coset.initial + coset.step_size.half().to_point())).x;
+        for _ in 1..coset.log_size {
+            x = CirclePointQM31Var::double_x(&x);
+        }
+        x
+    }
+
+    /// Returns `p` moved by `shift` steps of the coset, which can be negative, as the mask
+    /// offsets of a trace on this coset.
+    pub fn shift(&self, p: &CirclePointQM31Var, shift: isize) -> CirclePointQM31Var {
+        p + &self.coset.step.mul_signed(shift)
+    }
+
+    /// Returns the point at the bit-reversed `index`, where `index` has `log_size` bits.
+    pub fn at_bit_reversed(&self, index: &BitsVar) -> CirclePointM31Var {
+        assert_eq!(index.value.len(), self.log_size() as usize);
+        self.sum_of_steps(index)
+    }
+
+    /// Returns the twiddle that folds the pair of points at the bit-reversed `index` in a line
+    /// FFT layer, which is the inverse of the x-coordinate of the point at the even index.
+    pub fn fold_twiddle(&self, index: &BitsVar) -> M31Var {
+        assert_eq!(index.value.len(), self.log_size() as usize);
+        self.sum_of_steps(&index.index_range_from(1..)).x.inv()
+    }
+
+    /// Returns the initial point plus `2^(len - 1 - i)` steps for each bit `i` set in `bits`.
+    ///
+    /// With all the `log_size` bits of an index, this is the point at the bit-reversed index.
+    fn sum_of_steps(&self, bits: &BitsVar) -> CirclePointM31Var {
+        let mut steps = Vec::with_capacity(bits.value.len());
+        let mut cur = self.coset.step;
+        for _ in 0..bits.value.len() {
+            steps.push(cur);
+            cur = cur.double();
+        }
+
+        let selectors = bits
+            .value
+            .iter()
+            .rev()
+            .zip_eq(bits.variables.iter().rev())
+            .map(|(&bit_value, &bit_variable)| (bit_value, bit_variable))
+            .collect_vec();
+        CirclePointM31Var::sum_of_selected_constants(
+            &self.cs,
+            &self.coset.initial,
+            &steps,
+            &selectors,
+        )
+    }
+}
+
+/// A circle domain, which is a coset together with its conjugate, fixed by the circuit.
+#[derive(Clone, Debug)]
+pub struct CircleDomainVar {
+    pub cs: ConstraintSystemRef,
+    pub domain: CircleDomain,
+}
+
+impl Var for CircleDomainVar {
+    type Value = CircleDomain;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl CircleDomainVar {
+    pub fn new(cs: &ConstraintSystemRef, domain: CircleDomain) -> Self {
+        Self {
+            cs: cs.clone(),
+            domain,
+        }
+    }
+
+    /// The canonic circle domain of size `2^log_size`, over which the columns are committed.
+    pub fn canonic(cs: &ConstraintSystemRef, log_size: u32) -> Self {
+        Self::new(cs, CanonicCoset::new(log_size).circle_domain())
+    }
+
+    pub fn log_size(&self) -> u32 {
+        self.domain.log_size()
+    }
+
+    pub fn half_coset(&self) -> CosetVar {
+        CosetVar::new(&self.cs, self.domain.half_coset)
+    }
+
+    /// Returns the point at the bit-reversed `index`, where `index` has `log_size` bits.
+    ///
+    /// The lowest bit of `index` picks the half coset or its conjugate.
+    pub fn at_bit_reversed(&self, index: &BitsVar) -> CirclePointM31Var {
+        assert_eq!(index.value.len(), self.log_size() as usize);
+        self.half_coset()
+            .sum_of_steps(&index.index_range_from(1..))
+            .conditional_negate(index.value[0], index.variables[0])
+    }
+
+    /// Returns the twiddle that folds the pair of points at the bit-reversed `index` from the
+    /// circle into the line, which is the inverse of the y-coordinate of the point at the even
+    /// index.
+    pub fn fold_twiddle(&self, index: &BitsVar) -> M31Var {
+        assert_eq!(index.value.len(), self.log_size() as usize);
+        self.half_coset()
+            .sum_of_steps(&index.index_range_from(1..))
+            .y
+            .inv()
+    }
+}
+
+/// Evaluates at `p` a function that vanishes only at `vanish_point`, as stwo's
+/// `point_vanishing`.
+pub fn point_vanishing(vanish_point: &CirclePointQM31Var, p: &CirclePointQM31Var) -> QM31Var {
+    let cs = p.cs().and(&vanish_point.cs());
+    let h = p - vanish_point;
+    &h.y * &(&h.x + &QM31Var::one(&cs)).inv()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{point_vanishing, CircleDomainVar, CosetVar};
+    use circle_plonk_dsl_bits::BitsVar;
+    use circle_plonk_dsl_circle::CirclePointQM31Var;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::{M31Var, QM31Var};
+    use num_traits::Zero;
+    use stwo_prover::core::circle::Coset;
+    use stwo_prover::core::constraints;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::fields::FieldExpOps;
+    use stwo_prover::core::poly::circle::CanonicCoset;
+    use stwo_prover::core::poly::line::LineDomain;
+    use stwo_prover::core::utils::bit_reverse_index;
+
+    #[test]
+    fn test_coset_vanishing_and_shifts() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let p = CirclePointQM31Var::from_t(&QM31Var::new_witness(
+            &cs,
+            &QM31::from_u32_unchecked(1, 2, 3, 4),
+        ));
+        let vanish_point = CirclePointQM31Var::from_t(&QM31Var::new_witness(
+            &cs,
+            &QM31::from_u32_unchecked(5, 6, 7, 8),
+        ));
+
+        for coset in [
+            CanonicCoset::new(5).coset,
+            Coset::half_odds(6),
+            Coset::odds(4),
+            Coset::subgroup(3),
+        ] {
+            let coset_var = CosetVar::new(&cs, coset);
+            assert_eq!(
+                coset_var.vanishing_at(&p).value(),
+                constraints::coset_vanishing(coset, p.value())
+            );
+
+            let on_coset = CirclePointQM31Var::new_witness(&cs, &coset.at(3).into_ef());
+            assert_eq!(coset_var.vanishing_at(&on_coset).value(), QM31::zero());
+
+            for shift in [-3, -1, 0, 1, 7] {
+                assert_eq!(
+                    coset_var.shift(&p, shift).value(),
+                    p.value() + coset.step.mul_signed(shift).into_ef()
+                );
+            }
+        }
+
+        assert_eq!(
+            point_vanishing(&vanish_point, &p).value(),
+            constraints::point_vanishing(vanish_point.value(), p.value())
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_domain_points_and_twiddles() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let log_size = 6;
+        let domain = CanonicCoset::new(log_size).circle_domain();
+        let coset = Coset::half_odds(log_size);
+        let line_domain = LineDomain::new(coset);
+
+        let domain_var = CircleDomainVar::canonic(&cs, log_size);
+        let coset_var = CosetVar::new(&cs, coset);
+
+        for position in [0u32, 1, 22, 41, 63] {
+            let index = M31Var::new_witness(&cs, &M31::from(position));
+            let bits = BitsVar::from_m31(&index, log_size as usize);
+
+            let bit_reversed = bit_reverse_index(position as usize, log_size);
+            let even_bit_reversed = bit_reverse_index(position as usize & !1, log_size);
+
+            assert_eq!(
+                domain_var.at_bit_reversed(&bits).value(),
+                domain.at(bit_reversed)
+            );
+            assert_eq!(
+                domain_var.fold_twiddle(&bits).value,
+                domain.at(even_bit_reversed).y.inverse()
+            );
+            assert_eq!(
+                coset_var.at_bit_reversed(&bits).value(),
+                coset.at(bit_reversed)
+            );
+            assert_eq!(
+                coset_var.fold_twiddle(&bits).value,
+                line_domain.at(even_bit_reversed).inverse()
+            );
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -1,10 +1,11 @@
-use crate::M31Var;
+use crate::{M31Var, QM31Var};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
-use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_constraint_system::{ConstraintSystemRef, ConstraintSystemType};
 use num_traits::{One, Zero};
 use std::ops::{Add, Mul, Neg, Sub};
 use stwo_prover::core::fields::cm31::CM31;
 use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
 use stwo_prover::core::fields::FieldExpOps;
 
 #[derive(Debug, Clone)]
@@ -241,6 +242,55 @@ impl CM31Var {
         let cs = self.cs();
         let value = self.value.inverse();
         let res = CM31Var::new_witness(&cs, &value);
+        cs.insert_gate(self.variable, res.variable, 1, M31::zero());
+        res
+    }
+
+    pub fn inv_by_norm(&self) -> CM31Var {
+        // 1 / (a + bi) = (a - bi) / (a^2 + b^2), so only the M31 inverse is hinted
+        &self.conjugate() * &self.norm_to_m31().inv()
+    }
+
+    pub fn decompose_m31(&self) -> [M31Var; 2] {
+        let cs = self.cs();
+
+        let real = M31Var::new_witness(&cs, &self.value.0);
+        let imag = M31Var::new_witness(&cs, &self.value.1);
+        CM31Var::from_m31(&real, &imag).equalverify(self);
+
+        [real, imag]
+    }
+
+    pub fn conjugate(&self) -> CM31Var {
+        match self.cs.get_type() {
+            ConstraintSystemType::PlonkWithoutPoseidon => {
+                let constant = QM31::from_m31(M31::one(), -M31::one(), M31::zero(), M31::zero());
+                let res = QM31Var::from(self).hadamard_constant(constant);
+                CM31Var {
+                    cs: res.cs,
+                    value: res.value.0,
+                    variable: res.variable,
+                }
+            }
+            ConstraintSystemType::PlonkWithPoseidon => {
+                // without a component-wise gate, the conjugate goes through a decomposition
+                let [real, imag] = self.decompose_m31();
+                CM31Var::from_m31(&real, &(-&imag))
+            }
+        }
+    }
+
+    pub fn norm_to_m31(&self) -> M31Var {
+        let [real, imag] = self.decompose_m31();
+        &(&real * &real) + &(&imag * &imag)
+    }
+
+    pub fn assert_in_m31(&self) -> M31Var {
+        let cs = self.cs();
+
+        // the allocation of an M31 witness enforces it to be in the base field
+        let res = M31Var::new_witness(&cs, &self.value.0);
+        CM31Var::from(&res).equalverify(self);
         res
     }
 
@@ -277,3 +327,44 @@ impl CM31Var {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::CM31Var;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use num_traits::{One, Zero};
+    use stwo_prover::core::fields::cm31::CM31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::fields::FieldExpOps;
+
+    #[test]
+    fn test_cm31_inv() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let a = CM31::from_u32_unchecked(3, 5);
+        let a_var = CM31Var::new_witness(&cs, &a);
+        a_var
+            .inv()
+            .equalverify(&CM31Var::new_witness(&cs, &a.inverse()));
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    #[should_panic(expected = "is incorrect")]
+    fn test_cm31_inv_wrong_witness() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        let a = CM31::from_u32_unchecked(3, 5);
+        let a_inv_var = CM31Var::new_witness(&cs, &a).inv();
+        cs.set_value(
+            a_inv_var.variable,
+            QM31(a_inv_var.value + CM31::one(), CM31::zero()),
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
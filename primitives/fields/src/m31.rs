@@ -159,6 +159,36 @@ impl M31Var {
         }
     }
 
+    pub fn range_check_16(&self) {
+        assert!(self.value.0 < 1 << 16);
+        self.cs.range_check_16(self.variable);
+    }
+
+    pub fn range_check(&self, bits: usize) {
+        assert!(bits <= 31);
+        assert!((self.value.0 as u64) < 1 << bits);
+
+        let cs = self.cs();
+        if bits == 0 {
+            cs.enforce_zero(self.variable);
+        } else if bits <= 16 {
+            self.range_check_16();
+            if bits < 16 {
+                // since self < 2^16, self * 2^(16 - bits) does not wrap around, and it is smaller
+                // than 2^16 if and only if self < 2^bits
+                self.mul_constant(M31::from(1 << (16 - bits)))
+                    .range_check_16();
+            }
+        } else {
+            let lo = M31Var::new_witness(&cs, &M31::from(self.value.0 & ((1 << 16) - 1)));
+            let hi = M31Var::new_witness(&cs, &M31::from(self.value.0 >> 16));
+            (&lo + &hi.mul_constant(M31::from(1 << 16))).equalverify(self);
+
+            lo.range_check_16();
+            hi.range_check(bits - 16);
+        }
+    }
+
     pub fn is_eq(&self, rhs: &M31Var) -> M31Var {
         (self - rhs).is_zero()
     }
@@ -178,3 +208,75 @@ impl M31Var {
         out
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::M31Var;
+    use circle_plonk_dsl_constraint_system::range_table::{LogupElements, LOG_RANGE_TABLE_SIZE};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use num_traits::Zero;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+
+    #[test]
+    fn test_m31_range_check() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+
+        for bits in [8usize, 16, 20, 31] {
+            for _ in 0..10 {
+                let a = M31::from(prng.gen_range(0..(1u64 << bits).min((1 << 31) - 1)) as u32);
+                M31Var::new_witness(&cs, &a).range_check(bits);
+            }
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+
+        let range_table = cs.generate_range_table_circuit();
+        assert_eq!(range_table.table.len(), range_table.wire.len());
+        assert_eq!(range_table.mult.len(), range_table.wire.len());
+        assert_eq!(range_table.table[5], M31::from(5));
+        assert_eq!(
+            range_table.mult.iter().map(|m| m.0 as usize).sum::<usize>(),
+            range_table.wire.len()
+        );
+        assert!(range_table.mult[1 << LOG_RANGE_TABLE_SIZE..]
+            .iter()
+            .all(|m| m.0 == 0));
+
+        // the lookups, the table and the Plonk multiplicities balance
+        let plonk_elements = LogupElements {
+            z: QM31::from_u32_unchecked(1, 2, 3, 4),
+            alpha: QM31::from_u32_unchecked(5, 6, 7, 8),
+        };
+        let range_elements = LogupElements {
+            z: QM31::from_u32_unchecked(9, 10, 11, 12),
+            alpha: QM31::from_u32_unchecked(13, 14, 15, 16),
+        };
+        assert_eq!(
+            cs.logup_total_sum(&plonk_elements, &range_elements),
+            QM31::zero()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is out of range")]
+    fn test_m31_range_check_16_out_of_range() {
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+
+        let a_var = M31Var::new_witness(&cs, &M31::from((1 << 16) - 1));
+        a_var.range_check_16();
+
+        // a witness at the bound is no longer in the table
+        cs.set_value(a_var.variable, QM31::from(M31::from(1 << 16)));
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{CM31Var, M31Var};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
-use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_constraint_system::{ConstraintSystemRef, ConstraintSystemType};
 use num_traits::{One, Zero};
 use std::ops::{Add, Mul, Neg, Sub};
 use stwo_prover::core::fields::cm31::CM31;
@@ -84,6 +84,17 @@ impl From<&M31Var> for QM31Var {
     }
 }
 
+impl From<&CM31Var> for QM31Var {
+    fn from(var: &CM31Var) -> Self {
+        let cs = var.cs();
+        Self {
+            cs,
+            value: QM31(var.value, CM31::zero()),
+            variable: var.variable,
+        }
+    }
+}
+
 impl Add<&M31Var> for &QM31Var {
     type Output = QM31Var;
 
@@ -466,16 +477,112 @@ impl QM31Var {
     pub fn shift_by_ij(&self) -> QM31Var {
         self.shift_by_i().shift_by_j()
     }
+
+    /// Multiplies each M31 coordinate by the matching coordinate of `constant`.
+    ///
+    /// This is a single row with the component-wise product gate, which only the Plonk circuit
+    /// without Poseidon has.
+    pub fn hadamard_constant(&self, constant: QM31) -> QM31Var {
+        let cs = self.cs();
+        let constant_var = QM31Var::new_constant(&cs, &constant);
+
+        let value = QM31::from_m31(
+            self.value.0 .0 * constant.0 .0,
+            self.value.0 .1 * constant.0 .1,
+            self.value.1 .0 * constant.1 .0,
+            self.value.1 .1 * constant.1 .1,
+        );
+        QM31Var {
+            cs: cs.clone(),
+            value,
+            variable: cs.do_hadamard(self.variable, constant_var.variable),
+        }
+    }
+
+    pub fn conjugate(&self) -> QM31Var {
+        match self.cs.get_type() {
+            ConstraintSystemType::PlonkWithoutPoseidon => {
+                let minus_one = -M31::one();
+                self.hadamard_constant(QM31::from_m31(M31::one(), M31::one(), minus_one, minus_one))
+            }
+            ConstraintSystemType::PlonkWithPoseidon => {
+                // without a component-wise gate, the conjugate a - bu is computed as 2a - (a + bu)
+                let [a0, a1, _, _] = self.decompose_m31();
+                let a = CM31Var::from_m31(&a0, &a1);
+                &(&a + &a) - self
+            }
+        }
+    }
+
+    pub fn frobenius(&self) -> QM31Var {
+        // (a + bu)^p = conj(a) + conj(b) * (2 + i)^((p - 1) / 2) * u
+        match self.cs.get_type() {
+            ConstraintSystemType::PlonkWithoutPoseidon => {
+                let (zero, one, minus_one) = (M31::zero(), M31::one(), -M31::one());
+                let a = self.hadamard_constant(QM31::from_m31(one, minus_one, zero, zero));
+                let b = self.hadamard_constant(QM31::from_m31(zero, zero, one, minus_one));
+                &a + &b.mul_constant_cm31(Self::frobenius_constant())
+            }
+            ConstraintSystemType::PlonkWithPoseidon => {
+                let [a0, a1, a2, a3] = self.decompose_m31();
+                let a = CM31Var::from_m31(&a0, &(-&a1));
+                let b =
+                    CM31Var::from_m31(&a2, &(-&a3)).mul_constant_cm31(Self::frobenius_constant());
+                QM31Var::from_cm31(&a, &b)
+            }
+        }
+    }
+
+    pub fn norm_to_cm31(&self) -> CM31Var {
+        let res = self * &self.conjugate();
+        CM31Var {
+            cs: res.cs,
+            value: res.value.0,
+            variable: res.variable,
+        }
+    }
+
+    pub fn norm_to_m31(&self) -> M31Var {
+        self.norm_to_cm31().norm_to_m31()
+    }
+
+    pub fn inv_by_norm(&self) -> QM31Var {
+        // 1 / x = conj(x) / (x * conj(x)), where x * conj(x) lies in CM31
+        &self.conjugate() * &self.norm_to_cm31().inv_by_norm()
+    }
+
+    pub fn assert_in_m31(&self) -> M31Var {
+        let cs = self.cs();
+
+        // the allocation of an M31 witness enforces it to be in the base field
+        let res = M31Var::new_witness(&cs, &self.value.0 .0);
+        QM31Var::from(&res).equalverify(self);
+        res
+    }
+
+    pub fn assert_in_cm31(&self) -> CM31Var {
+        let cs = self.cs();
+
+        let res = CM31Var::new_witness(&cs, &self.value.0);
+        QM31Var::from(&res).equalverify(self);
+        res
+    }
+
+    fn frobenius_constant() -> CM31 {
+        CM31::from_u32_unchecked(2, 1).pow(((1u128 << 31) - 2) / 2)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::QM31Var;
+    use crate::{CM31Var, M31Var, QM31Var};
     use circle_plonk_dsl_constraint_system::var::AllocVar;
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
-    use num_traits::One;
+    use num_traits::{One, Zero};
     use rand::prelude::SmallRng;
     use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::cm31::CM31;
+    use stwo_prover::core::fields::m31::M31;
     use stwo_prover::core::fields::qm31::QM31;
     use stwo_prover::core::fields::FieldExpOps;
     use stwo_prover::core::fri::FriConfig;
@@ -522,4 +629,66 @@ mod test {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_qm31_frobenius_and_norm() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let a: QM31 = prng.gen();
+
+        let conjugate = QM31(a.0, -a.1);
+        let frobenius = a.pow((1u128 << 31) - 1);
+        let norm_cm31 = (a * conjugate).0;
+        let norm_m31 = norm_cm31.0 * norm_cm31.0 + norm_cm31.1 * norm_cm31.1;
+
+        let b: M31 = prng.gen();
+        let c = CM31(prng.gen(), prng.gen());
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            let a_var = QM31Var::new_witness(&cs, &a);
+            a_var
+                .conjugate()
+                .equalverify(&QM31Var::new_witness(&cs, &conjugate));
+            a_var
+                .frobenius()
+                .equalverify(&QM31Var::new_witness(&cs, &frobenius));
+            a_var
+                .norm_to_cm31()
+                .equalverify(&CM31Var::new_witness(&cs, &norm_cm31));
+            a_var
+                .norm_to_m31()
+                .equalverify(&M31Var::new_witness(&cs, &norm_m31));
+            a_var
+                .inv_by_norm()
+                .equalverify(&QM31Var::new_witness(&cs, &a.inverse()));
+
+            let b_var = QM31Var::new_witness(&cs, &QM31::from(b));
+            b_var
+                .assert_in_m31()
+                .equalverify(&M31Var::new_witness(&cs, &b));
+
+            let c_var = QM31Var::new_witness(&cs, &QM31(c, CM31::zero()));
+            c_var
+                .assert_in_cm31()
+                .equalverify(&CM31Var::new_witness(&cs, &c));
+            c_var
+                .assert_in_cm31()
+                .inv_by_norm()
+                .equalverify(&CM31Var::new_witness(&cs, &c.inverse()));
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+
+        // with the component-wise product gate, the conjugate is a single row once its constant
+        // has been allocated
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+        let a_var = QM31Var::new_witness(&cs, &a);
+        let _ = a_var.conjugate();
+        let num_rows = cs.num_plonk_rows();
+        let _ = a_var.conjugate();
+        assert_eq!(cs.num_plonk_rows(), num_rows + 1);
+    }
 }
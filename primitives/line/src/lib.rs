@@ -5,7 +5,9 @@ use itertools::Itertools;
 use num_traits::One;
 use std::ops::Neg;
 use stwo_prover::core::fields::m31::M31;
-use stwo_prover::core::poly::line::LinePoly;
+use stwo_prover::core::fields::FieldExpOps;
+use stwo_prover::core::poly::line::{LineDomain, LinePoly};
+use stwo_prover::core::utils::bit_reverse_index;
 
 #[derive(Clone, Debug)]
 pub struct LinePolyVar {
@@ -41,6 +43,25 @@ impl LinePolyVar {
         let mut x = x.clone();
         let line_poly_log_size = self.coeffs.len().ilog2();
 
+        let mut doublings = vec![];
+        doublings.push(QM31Var::from(&x));
+        for _ in 1..line_poly_log_size {
+            let x_sq = &x * &x;
+            x = &x_sq + &x_sq;
+            x = &x + &M31Var::new_constant(&cs, &M31::one().neg());
+            doublings.push(QM31Var::from(&x))
+        }
+
+        fold(&self.coeffs, &doublings)
+    }
+
+    /// Evaluates the polynomial at a secure-field x-coordinate, such as one of an out-of-domain
+    /// point.
+    pub fn eval_at_qm31_point(&self, x: &QM31Var) -> QM31Var {
+        let cs = self.cs().and(&x.cs());
+        let mut x = x.clone();
+        let line_poly_log_size = self.coeffs.len().ilog2();
+
         let mut doublings = vec![];
         doublings.push(x.clone());
         for _ in 1..line_poly_log_size {
@@ -50,21 +71,82 @@ impl LinePolyVar {
             doublings.push(x.clone())
         }
 
-        pub fn fold(values: &[QM31Var], folding_factors: &[M31Var]) -> QM31Var {
-            let n = values.len();
-            assert_eq!(n, 1 << folding_factors.len());
-            if n == 1 {
-                return values[0].clone();
+        fold(&self.coeffs, &doublings)
+    }
+
+    /// Interpolates the polynomial from its evaluations on a constant line domain, given in the
+    /// bit-reversed order of stwo's `LineEvaluation`, with an inverse FFT in the circuit.
+    pub fn interpolate_from_evals(domain: LineDomain, evals: &[QM31Var]) -> Self {
+        assert_eq!(evals.len(), domain.size());
+        let log_size = domain.log_size();
+
+        let mut cs = evals[0].cs();
+        for eval in evals.iter().skip(1) {
+            cs = cs.and(&eval.cs());
+        }
+
+        let mut values = (0..evals.len())
+            .map(|i| evals[bit_reverse_index(i, log_size)].clone())
+            .collect_vec();
+
+        let mut domain = domain;
+        while domain.size() > 1 {
+            for chunk in values.chunks_exact_mut(domain.size()) {
+                let (l, r) = chunk.split_at_mut(domain.size() / 2);
+                for (i, (l, r)) in l.iter_mut().zip(r.iter_mut()).enumerate() {
+                    let itwid = domain.at(i).inverse();
+                    let sum = &*l + &*r;
+                    *r = (&*l - &*r).mul_constant_m31(itwid);
+                    *l = sum;
+                }
             }
-            let (lhs_values, rhs_values) = values.split_at(n / 2);
-            let (folding_factor, folding_factors) = folding_factors.split_first().unwrap();
-            let lhs_val = fold(lhs_values, folding_factors);
-            let rhs_val = fold(rhs_values, folding_factors);
-            &lhs_val + &(&rhs_val * folding_factor)
+            domain = domain.double();
         }
 
-        fold(&self.coeffs, &doublings)
+        let len_inv = M31::from(values.len() as u32).inverse();
+        let coeffs = values
+            .iter()
+            .map(|v| v.mul_constant_m31(len_inv))
+            .collect_vec();
+
+        LinePolyVar { cs, coeffs }
     }
+
+    /// Enforces that the polynomial has a degree below `2^log_degree_bound`, and returns it as a
+    /// polynomial of that size.
+    ///
+    /// The coefficients are stored in bit-reversed order of degree, so only those at multiples
+    /// of `2^(log_size - log_degree_bound)` can be nonzero.
+    pub fn check_degree_bound(&self, log_degree_bound: u32) -> Self {
+        let log_size = self.coeffs.len().ilog2();
+        assert!(log_degree_bound <= log_size);
+
+        let step = 1 << (log_size - log_degree_bound);
+        let zero = QM31Var::zero(&self.cs);
+        for (i, coeff) in self.coeffs.iter().enumerate() {
+            if i % step != 0 {
+                coeff.equalverify(&zero);
+            }
+        }
+
+        LinePolyVar {
+            cs: self.cs.clone(),
+            coeffs: self.coeffs.iter().step_by(step).cloned().collect_vec(),
+        }
+    }
+}
+
+fn fold(values: &[QM31Var], folding_factors: &[QM31Var]) -> QM31Var {
+    let n = values.len();
+    assert_eq!(n, 1 << folding_factors.len());
+    if n == 1 {
+        return values[0].clone();
+    }
+    let (lhs_values, rhs_values) = values.split_at(n / 2);
+    let (folding_factor, folding_factors) = folding_factors.split_first().unwrap();
+    let lhs_val = fold(lhs_values, folding_factors);
+    let rhs_val = fold(rhs_values, folding_factors);
+    &lhs_val + &(&rhs_val * folding_factor)
 }
 
 #[cfg(test)]
@@ -73,10 +155,14 @@ mod test {
     use circle_plonk_dsl_constraint_system::var::AllocVar;
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
     use circle_plonk_dsl_fields::{M31Var, QM31Var};
+    use itertools::Itertools;
+    use num_traits::Zero;
     use rand::prelude::StdRng;
     use rand::{Rng, SeedableRng};
-    use stwo_prover::core::circle::M31_CIRCLE_GEN;
-    use stwo_prover::core::poly::line::LinePoly;
+    use stwo_prover::core::circle::{Coset, M31_CIRCLE_GEN};
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::poly::line::{LineDomain, LinePoly};
+    use stwo_prover::core::utils::bit_reverse_index;
 
     #[test]
     fn test_line_poly_var() {
@@ -101,4 +187,46 @@ mod test {
         cs.pad();
         cs.check_arithmetics();
     }
+
+    #[test]
+    fn test_line_poly_interpolate() {
+        const LOG_SIZE: u32 = 5;
+        const LOG_DEGREE_BOUND: u32 = 3;
+
+        let mut prng = StdRng::seed_from_u64(0);
+
+        let coeffs: Vec<QM31> = (0..1 << LOG_DEGREE_BOUND).map(|_| prng.gen()).collect_vec();
+        let line_poly = LinePoly::new(coeffs.clone());
+
+        let domain = LineDomain::new(Coset::half_odds(LOG_SIZE));
+        let evals = (0..domain.size())
+            .map(|i| line_poly.eval_at_point(domain.at(bit_reverse_index(i, LOG_SIZE)).into()))
+            .collect_vec();
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+        let evals_var = evals
+            .iter()
+            .map(|eval| QM31Var::new_witness(&cs, eval))
+            .collect_vec();
+
+        let interpolated = LinePolyVar::interpolate_from_evals(domain, &evals_var);
+        for (i, coeff) in interpolated.coeffs.iter().enumerate() {
+            let step = 1 << (LOG_SIZE - LOG_DEGREE_BOUND);
+            if i % step == 0 {
+                assert_eq!(coeff.value, coeffs[i / step]);
+            } else {
+                assert_eq!(coeff.value, QM31::zero());
+            }
+        }
+
+        let truncated = interpolated.check_degree_bound(LOG_DEGREE_BOUND);
+        assert_eq!(truncated.coeffs.len(), 1 << LOG_DEGREE_BOUND);
+
+        let x: QM31 = prng.gen();
+        let res = truncated.eval_at_qm31_point(&QM31Var::new_witness(&cs, &x));
+        res.equalverify(&QM31Var::new_witness(&cs, &line_poly.eval_at_point(x)));
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
 }
@@ -1,7 +1,20 @@
 use circle_plonk_dsl_constraint_system::var::Var;
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_fields::{M31Var, QM31Var};
-use circle_plonk_dsl_poseidon31::Poseidon2HalfVar;
-use std::cmp::min;
+use circle_plonk_dsl_poseidon31::{Poseidon2HalfVar, Poseidon2SpongeVar};
+use stwo_prover::core::fields::m31::M31;
+
+pub mod ops;
+pub use ops::*;
+
+pub mod verifier;
+pub use verifier::*;
+
+pub mod tree;
+pub use tree::*;
+
+pub mod sparse;
+pub use sparse::*;
 
 pub struct Poseidon31MerkleHasherVar;
 
@@ -48,174 +61,118 @@ impl Poseidon31MerkleHasherVar {
     }
 
     pub fn hash_m31_columns_get_rate(m31: &[M31Var]) -> Poseidon2HalfVar {
-        let len = m31.len();
-        let num_chunk = len.div_ceil(8);
-        let cs = m31[0].cs();
-
-        // compute the first hash, which consists of 8 elements, and it comes from (no more than)
-        // 16 elements
-
-        let mut input: [M31Var; 8] = std::array::from_fn(|_| M31Var::zero(&cs));
-        input[0..min(len, 8)].clone_from_slice(&m31[0..min(len, 8)]);
-
-        let zero = Poseidon2HalfVar::zero(&cs);
-        let first_chunk = Poseidon2HalfVar::from_m31(&input[0..8]);
-
-        if num_chunk == 1 {
-            return Poseidon2HalfVar::permute_get_rate(&first_chunk, &zero);
-        }
-
-        let mut digest = Poseidon2HalfVar::permute_get_capacity(&first_chunk, &zero);
-        for chunk in m31.chunks_exact(8).skip(1).take(num_chunk - 2) {
-            let left = Poseidon2HalfVar::from_m31(&chunk);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
-        }
-
-        let remain = len % 8;
-        if remain == 0 {
-            let mut input: [M31Var; 8] = std::array::from_fn(|_| M31Var::zero(&cs));
-            input[0..8].clone_from_slice(&m31[len - 8..]);
-
-            let left = Poseidon2HalfVar::from_m31(&input);
-            digest = Poseidon2HalfVar::permute_get_rate(&left, &digest);
-        } else {
-            let mut input: [M31Var; 8] = std::array::from_fn(|_| M31Var::zero(&cs));
-            input[0..remain].clone_from_slice(&m31[len - remain..]);
-
-            let left = Poseidon2HalfVar::from_m31(&input);
-            digest = Poseidon2HalfVar::permute_get_rate(&left, &digest);
-        }
-
-        digest
+        let mut sponge = Poseidon2SpongeVar::new(&m31[0].cs());
+        sponge.absorb_m31(m31);
+        sponge.squeeze_get_rate()
     }
 
     pub fn hash_qm31_columns_get_rate(qm31: &[QM31Var]) -> Poseidon2HalfVar {
-        let len = qm31.len();
-        let num_chunk = len.div_ceil(2);
-        let cs = qm31[0].cs();
-
-        // compute the first hash, which consists of 8 elements, and it comes from (no more than)
-        // 16 elements
-
-        let mut input: [QM31Var; 2] = std::array::from_fn(|_| QM31Var::zero(&cs));
-        input[0..min(len, 2)].clone_from_slice(&qm31[0..min(len, 2)]);
-
-        let zero = Poseidon2HalfVar::zero(&cs);
-        let first_chunk = Poseidon2HalfVar::from_qm31(&input[0], &input[1]);
-
-        if num_chunk == 1 {
-            return Poseidon2HalfVar::permute_get_rate(&first_chunk, &zero);
-        }
-
-        let mut digest = Poseidon2HalfVar::permute_get_capacity(&first_chunk, &zero);
-        for chunk in qm31.chunks_exact(2).skip(1).take(num_chunk - 2) {
-            let left = Poseidon2HalfVar::from_qm31(&chunk[0], &chunk[1]);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
-        }
-
-        let remain = len % 2;
-        if remain == 0 {
-            let mut input: [QM31Var; 2] = std::array::from_fn(|_| QM31Var::zero(&cs));
-            input[0..2].clone_from_slice(&qm31[len - 2..]);
-
-            let left = Poseidon2HalfVar::from_qm31(&input[0], &input[1]);
-            digest = Poseidon2HalfVar::permute_get_rate(&left, &digest);
-        } else {
-            let mut input: [QM31Var; 2] = std::array::from_fn(|_| QM31Var::zero(&cs));
-            input[0..remain].clone_from_slice(&qm31[len - remain..]);
-
-            let left = Poseidon2HalfVar::from_qm31(&input[0], &input[1]);
-            digest = Poseidon2HalfVar::permute_get_rate(&left, &digest);
-        }
-
-        digest
+        let mut sponge = Poseidon2SpongeVar::new(&qm31[0].cs());
+        sponge.absorb_qm31(qm31);
+        sponge.squeeze_get_rate()
     }
 
     pub fn hash_qm31_columns_get_capacity(qm31: &[QM31Var]) -> Poseidon2HalfVar {
-        let len = qm31.len();
-        let num_chunk = len.div_ceil(2);
-        let cs = qm31[0].cs();
-
-        // compute the first hash, which consists of 8 elements, and it comes from (no more than)
-        // 16 elements
-
-        let mut input: [QM31Var; 2] = std::array::from_fn(|_| QM31Var::zero(&cs));
-        input[0..min(len, 2)].clone_from_slice(&qm31[0..min(len, 2)]);
-
-        let zero = Poseidon2HalfVar::zero(&cs);
-        let first_chunk = Poseidon2HalfVar::from_qm31(&input[0], &input[1]);
-
-        if num_chunk == 1 {
-            return Poseidon2HalfVar::permute_get_capacity(&first_chunk, &zero);
-        }
-
-        let mut digest = Poseidon2HalfVar::permute_get_capacity(&first_chunk, &zero);
-        for chunk in qm31.chunks_exact(2).skip(1).take(num_chunk - 2) {
-            let left = Poseidon2HalfVar::from_qm31(&chunk[0], &chunk[1]);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
-        }
-
-        let remain = len % 2;
-        if remain == 0 {
-            let mut input: [QM31Var; 2] = std::array::from_fn(|_| QM31Var::zero(&cs));
-            input[0..2].clone_from_slice(&qm31[len - 2..]);
-
-            let left = Poseidon2HalfVar::from_qm31(&input[0], &input[1]);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
-        } else {
-            let mut input: [QM31Var; 2] = std::array::from_fn(|_| QM31Var::zero(&cs));
-            input[0..remain].clone_from_slice(&qm31[len - remain..]);
-
-            let left = Poseidon2HalfVar::from_qm31(&input[0], &input[1]);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
-        }
-
-        digest
+        let mut sponge = Poseidon2SpongeVar::new(&qm31[0].cs());
+        sponge.absorb_qm31(qm31);
+        sponge.squeeze_get_capacity()
     }
 
     pub fn hash_m31_columns_get_capacity(m31: &[M31Var]) -> Poseidon2HalfVar {
-        let len = m31.len();
-        let num_chunk = len.div_ceil(8);
-        let cs = m31[0].cs();
+        let mut sponge = Poseidon2SpongeVar::new(&m31[0].cs());
+        sponge.absorb_m31(m31);
+        sponge.squeeze_get_capacity()
+    }
 
-        // compute the first hash, which consists of 8 elements, and it comes from (no more than)
-        // 16 elements
+    /// Absorbs the column values, where QM31 values are absorbed without being decomposed.
+    pub fn absorb_columns<C: MerkleColumnVar>(sponge: &mut Poseidon2SpongeVar, columns: &[C]) {
+        let (packed, remainder) = C::to_qm31_packed(columns);
+        sponge.absorb_qm31(&packed);
+        sponge.absorb_m31(&remainder);
+    }
+}
 
-        let mut input: [M31Var; 8] = std::array::from_fn(|_| M31Var::zero(&cs));
-        input[0..min(len, 8)].clone_from_slice(&m31[0..min(len, 8)]);
+impl MerkleHasherVar for Poseidon31MerkleHasherVar {
+    type HashVar = Poseidon2HalfVar;
 
-        let zero = Poseidon2HalfVar::zero(&cs);
-        let first_chunk = Poseidon2HalfVar::from_m31(&input[0..8]);
+    fn hash_value(hash: &Poseidon2HalfVar) -> [M31; 8] {
+        hash.value()
+    }
 
-        if num_chunk == 1 {
-            return Poseidon2HalfVar::permute_get_capacity(&first_chunk, &zero);
+    fn hash_node<C: MerkleColumnVar>(
+        cs: &ConstraintSystemRef,
+        children: Option<(&Poseidon2HalfVar, &Poseidon2HalfVar)>,
+        columns: &[C],
+    ) -> Poseidon2HalfVar {
+        match children {
+            None => {
+                let mut sponge = Poseidon2SpongeVar::new(cs);
+                Self::absorb_columns(&mut sponge, columns);
+                sponge.squeeze_get_rate()
+            }
+            Some((left, right)) if columns.is_empty() => Self::hash_tree(left, right),
+            Some((left, right)) => {
+                Self::hash_tree_with_column(left, right, &column_hash(cs, columns))
+            }
         }
+    }
 
-        let mut digest = Poseidon2HalfVar::permute_get_capacity(&first_chunk, &zero);
-        for chunk in m31.chunks_exact(8).skip(1).take(num_chunk - 2) {
-            let left = Poseidon2HalfVar::from_m31(&chunk);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
+    fn hash_node_with_swap<C: MerkleColumnVar>(
+        cur: &Poseidon2HalfVar,
+        sibling: &Poseidon2HalfVar,
+        bit_value: bool,
+        bit_variable: usize,
+        columns: &[C],
+    ) -> Poseidon2HalfVar {
+        if columns.is_empty() {
+            Self::hash_tree_with_swap(cur, sibling, bit_value, bit_variable)
+        } else {
+            Self::hash_tree_with_column_hash_with_swap(
+                cur,
+                sibling,
+                bit_value,
+                bit_variable,
+                &column_hash(&cur.cs(), columns),
+            )
         }
+    }
 
-        let remain = len % 8;
-        if remain == 0 {
-            let mut input: [M31Var; 8] = std::array::from_fn(|_| M31Var::zero(&cs));
-            input[0..8].clone_from_slice(&m31[len - 8..]);
-
-            let left = Poseidon2HalfVar::from_m31(&input);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
-        } else {
-            let mut input: [M31Var; 8] = std::array::from_fn(|_| M31Var::zero(&cs));
-            input[0..remain].clone_from_slice(&m31[len - remain..]);
+    fn hash_node_from_children_digest<C: MerkleColumnVar>(
+        children_digest: &Poseidon2HalfVar,
+        columns: &[C],
+    ) -> Poseidon2HalfVar {
+        Self::combine_hash_tree_with_column(
+            children_digest,
+            &column_hash(&children_digest.cs(), columns),
+        )
+    }
 
-            let left = Poseidon2HalfVar::from_m31(&input);
-            digest = Poseidon2HalfVar::permute_get_capacity(&left, &digest);
-        }
+    fn select(
+        a: &Poseidon2HalfVar,
+        b: &Poseidon2HalfVar,
+        bit_value: bool,
+        bit_variable: usize,
+    ) -> Poseidon2HalfVar {
+        let [a_left, a_right] = a.to_qm31();
+        let [b_left, b_right] = b.to_qm31();
+        Poseidon2HalfVar::from_qm31(
+            &QM31Var::select(&a_left, &b_left, bit_value, bit_variable),
+            &QM31Var::select(&a_right, &b_right, bit_value, bit_variable),
+        )
+    }
 
-        digest
+    fn equalverify(lhs: &Poseidon2HalfVar, rhs: &Poseidon2HalfVar) {
+        lhs.equalverify(rhs)
     }
 }
 
+/// The column hash of an inner node is the capacity of the sponge.
+fn column_hash<C: MerkleColumnVar>(cs: &ConstraintSystemRef, columns: &[C]) -> Poseidon2HalfVar {
+    let mut sponge = Poseidon2SpongeVar::new(cs);
+    Poseidon31MerkleHasherVar::absorb_columns(&mut sponge, columns);
+    sponge.squeeze_get_capacity()
+}
+
 #[cfg(test)]
 mod test {
     use crate::Poseidon31MerkleHasherVar;
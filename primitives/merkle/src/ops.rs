@@ -0,0 +1,83 @@
+use circle_plonk_dsl_constraint_system::var::Var;
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use std::fmt::Debug;
+
+/// The in-circuit counterpart of stwo's `MerkleHasher`, which lets the Merkle proof gadgets be
+/// shared by all the hash functions.
+pub trait MerkleHasherVar {
+    type HashVar: Var<Value: PartialEq + Debug>;
+
+    fn hash_value(hash: &Self::HashVar) -> <Self::HashVar as Var>::Value;
+
+    /// Hashes a node, which is a leaf if `children` is `None`, together with its column values.
+    fn hash_node<C: MerkleColumnVar>(
+        cs: &ConstraintSystemRef,
+        children: Option<(&Self::HashVar, &Self::HashVar)>,
+        columns: &[C],
+    ) -> Self::HashVar;
+
+    /// Hashes the parent of `cur` and `sibling`, where `cur` is the left child if the bit is
+    /// zero and the right child otherwise.
+    fn hash_node_with_swap<C: MerkleColumnVar>(
+        cur: &Self::HashVar,
+        sibling: &Self::HashVar,
+        bit_value: bool,
+        bit_variable: usize,
+        columns: &[C],
+    ) -> Self::HashVar;
+
+    /// Finishes the hash of a node with columns from the digest of its children alone, which is
+    /// how a sibling with columns is decommitted.
+    fn hash_node_from_children_digest<C: MerkleColumnVar>(
+        children_digest: &Self::HashVar,
+        columns: &[C],
+    ) -> Self::HashVar;
+
+    /// Returns `a` if the bit is zero, and `b` otherwise.
+    fn select(
+        a: &Self::HashVar,
+        b: &Self::HashVar,
+        bit_value: bool,
+        bit_variable: usize,
+    ) -> Self::HashVar;
+
+    fn equalverify(lhs: &Self::HashVar, rhs: &Self::HashVar);
+}
+
+/// A column value that can be hashed into a Merkle tree node.
+///
+/// How the values are absorbed is up to the `MerkleHasherVar`, which can take them either as
+/// M31 elements or packed into QM31 elements, whichever suits the hash function.
+pub trait MerkleColumnVar: Var {
+    fn to_m31(columns: &[Self]) -> Vec<M31Var>;
+
+    /// Packs every four consecutive M31 values into a QM31 element, and returns the remaining
+    /// values, fewer than four, separately.
+    fn to_qm31_packed(columns: &[Self]) -> (Vec<QM31Var>, Vec<M31Var>);
+}
+
+impl MerkleColumnVar for M31Var {
+    fn to_m31(columns: &[Self]) -> Vec<M31Var> {
+        columns.to_vec()
+    }
+
+    fn to_qm31_packed(columns: &[Self]) -> (Vec<QM31Var>, Vec<M31Var>) {
+        let chunks = columns.chunks_exact(4);
+        let remainder = chunks.remainder().to_vec();
+        let packed = chunks
+            .map(|c| QM31Var::from_m31(&c[0], &c[1], &c[2], &c[3]))
+            .collect();
+        (packed, remainder)
+    }
+}
+
+impl MerkleColumnVar for QM31Var {
+    fn to_m31(columns: &[Self]) -> Vec<M31Var> {
+        columns.iter().flat_map(|v| v.decompose_m31()).collect()
+    }
+
+    fn to_qm31_packed(columns: &[Self]) -> (Vec<QM31Var>, Vec<M31Var>) {
+        (columns.to_vec(), vec![])
+    }
+}
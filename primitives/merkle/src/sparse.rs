@@ -0,0 +1,281 @@
+use crate::Poseidon31MerkleHasherVar;
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_poseidon31::Poseidon2HalfVar;
+use num_traits::Zero;
+use std::collections::HashMap;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::vcs::ops::MerkleHasher;
+use stwo_prover::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
+
+/// A sparse Merkle tree with `2^depth` leaves, each of which is a digest indexed by its key.
+///
+/// An empty leaf is the zero digest, so a zero value cannot be told apart from a missing key.
+/// Only the nodes that differ from the roots of empty subtrees are stored.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree {
+    pub depth: usize,
+    /// The roots of the empty subtrees, indexed by their height above the leaves.
+    pub empty_hashes: Vec<Poseidon31Hash>,
+    /// The non-empty nodes of each layer, from the leaves up to the root.
+    pub layers: Vec<HashMap<usize, Poseidon31Hash>>,
+}
+
+/// The path of a key in a sparse Merkle tree, together with the value of the key.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleProof {
+    pub key: usize,
+    pub value: Poseidon31Hash,
+    /// The sibling hashes on the path, from the leaves up.
+    pub siblings: Vec<Poseidon31Hash>,
+}
+
+impl SparseMerkleTree {
+    pub fn new(depth: usize) -> Self {
+        let mut empty_hashes = vec![Poseidon31Hash([M31::zero(); 8])];
+        for i in 0..depth {
+            let child = empty_hashes[i];
+            empty_hashes.push(Poseidon31MerkleHasher::hash_node(Some((child, child)), &[]));
+        }
+
+        Self {
+            depth,
+            empty_hashes,
+            layers: vec![HashMap::new(); depth + 1],
+        }
+    }
+
+    pub fn root(&self) -> Poseidon31Hash {
+        self.get_node(self.depth, 0)
+    }
+
+    pub fn get(&self, key: usize) -> Poseidon31Hash {
+        self.get_node(0, key)
+    }
+
+    fn get_node(&self, height: usize, position: usize) -> Poseidon31Hash {
+        self.layers[height]
+            .get(&position)
+            .copied()
+            .unwrap_or(self.empty_hashes[height])
+    }
+
+    pub fn prove(&self, key: usize) -> SparseMerkleProof {
+        assert!(key < 1 << self.depth);
+
+        SparseMerkleProof {
+            key,
+            value: self.get(key),
+            siblings: (0..self.depth)
+                .map(|i| self.get_node(i, (key >> i) ^ 1))
+                .collect(),
+        }
+    }
+
+    /// Sets the value of the key, and returns the proof before the update, whose siblings also
+    /// prove the new value against the new root.
+    pub fn update(&mut self, key: usize, value: Poseidon31Hash) -> SparseMerkleProof {
+        let proof = self.prove(key);
+
+        let mut cur = value;
+        for i in 0..=self.depth {
+            let position = key >> i;
+            if cur == self.empty_hashes[i] {
+                self.layers[i].remove(&position);
+            } else {
+                self.layers[i].insert(position, cur);
+            }
+
+            if i < self.depth {
+                let (left, right) = if position & 1 == 0 {
+                    (cur, proof.siblings[i])
+                } else {
+                    (proof.siblings[i], cur)
+                };
+                cur = Poseidon31MerkleHasher::hash_node(Some((left, right)), &[]);
+            }
+        }
+
+        proof
+    }
+}
+
+impl SparseMerkleProof {
+    /// Computes the root of a tree in which the key has the given value.
+    pub fn compute_root(&self, value: Poseidon31Hash) -> Poseidon31Hash {
+        let mut cur = value;
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            let (left, right) = if (self.key >> i) & 1 == 0 {
+                (cur, *sibling)
+            } else {
+                (*sibling, cur)
+            };
+            cur = Poseidon31MerkleHasher::hash_node(Some((left, right)), &[]);
+        }
+        cur
+    }
+}
+
+/// The in-circuit counterpart of `SparseMerkleProof`.
+///
+/// The siblings are allocated as reusable witnesses, so that the same path can prove both the
+/// old and the new root of an update.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleProofVar {
+    pub cs: ConstraintSystemRef,
+    pub value: SparseMerkleProof,
+    pub siblings: Vec<Poseidon2HalfVar>,
+}
+
+impl Var for SparseMerkleProofVar {
+    type Value = SparseMerkleProof;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl AllocVar for SparseMerkleProofVar {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let siblings = value
+            .siblings
+            .iter()
+            .map(|sibling| Poseidon2HalfVar::new_variables(cs, &sibling.0, mode))
+            .collect();
+
+        Self {
+            cs: cs.clone(),
+            value: value.clone(),
+            siblings,
+        }
+    }
+}
+
+impl SparseMerkleProofVar {
+    /// Computes the root of a tree in which `key`, whose bits are the path from the leaves up,
+    /// has the given value.
+    pub fn compute_root(&self, key: &BitsVar, value: &Poseidon2HalfVar) -> Poseidon2HalfVar {
+        assert_eq!(key.value.len(), self.siblings.len());
+        assert_eq!(key.get_value().0 as usize, self.value.key);
+
+        let mut cur = value.clone();
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            cur = Poseidon31MerkleHasherVar::hash_tree_with_swap(
+                &cur,
+                sibling,
+                key.value[i],
+                key.variables[i],
+            );
+        }
+        cur
+    }
+
+    /// Verifies that `key` has the given value in the tree with the given root.
+    pub fn verify_membership(
+        &self,
+        root: &Poseidon2HalfVar,
+        key: &BitsVar,
+        value: &Poseidon2HalfVar,
+    ) {
+        let computed_root = self.compute_root(key, value);
+        assert_eq!(computed_root.value(), root.value());
+        computed_root.equalverify(root);
+    }
+
+    /// Verifies that `key` is empty in the tree with the given root.
+    pub fn verify_non_membership(&self, root: &Poseidon2HalfVar, key: &BitsVar) {
+        self.verify_membership(root, key, &Poseidon2HalfVar::zero(&key.cs()));
+    }
+
+    /// Verifies that changing the value of `key` from `old_value` to `new_value` moves the root
+    /// from `old_root` to `new_root`. An insertion or a removal uses the zero digest for the
+    /// missing value.
+    pub fn verify_update(
+        &self,
+        old_root: &Poseidon2HalfVar,
+        new_root: &Poseidon2HalfVar,
+        key: &BitsVar,
+        old_value: &Poseidon2HalfVar,
+        new_value: &Poseidon2HalfVar,
+    ) {
+        self.verify_membership(old_root, key, old_value);
+        self.verify_membership(new_root, key, new_value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{SparseMerkleProofVar, SparseMerkleTree};
+    use circle_plonk_dsl_bits::BitsVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use circle_plonk_dsl_poseidon31::Poseidon2HalfVar;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+    const DEPTH: usize = 20;
+
+    #[test]
+    fn test_sparse_merkle_tree() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let mut tree = SparseMerkleTree::new(DEPTH);
+        for _ in 0..10 {
+            let key = prng.gen_range(0..1 << DEPTH);
+            tree.update(key, Poseidon31Hash(prng.gen()));
+        }
+
+        // the native proofs are consistent with the roots
+        let key = prng.gen_range(0..1 << DEPTH);
+        let value = Poseidon31Hash(prng.gen());
+        let old_root = tree.root();
+        let proof = tree.update(key, value);
+        let new_root = tree.root();
+        assert_eq!(proof.compute_root(proof.value), old_root);
+        assert_eq!(proof.compute_root(value), new_root);
+        assert_eq!(tree.prove(key).value, value);
+
+        // restoring the old value restores the old root
+        let mut restored = tree.clone();
+        restored.update(key, proof.value);
+        assert_eq!(restored.root(), old_root);
+
+        let empty_key = (0..)
+            .find(|&k| tree.get(k) == tree.empty_hashes[0])
+            .unwrap();
+        let empty_proof = tree.prove(empty_key);
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            // the public inputs come before any witness
+            let old_root_var = Poseidon2HalfVar::new_public_input(&cs, &old_root.0);
+            let new_root_var = Poseidon2HalfVar::new_public_input(&cs, &new_root.0);
+
+            let key_var = |key: usize| {
+                BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(key as u32)), DEPTH)
+            };
+
+            let proof_var = SparseMerkleProofVar::new_witness(&cs, &proof);
+            proof_var.verify_update(
+                &old_root_var,
+                &new_root_var,
+                &key_var(key),
+                &Poseidon2HalfVar::new_witness(&cs, &proof.value.0),
+                &Poseidon2HalfVar::new_witness(&cs, &value.0),
+            );
+
+            let empty_proof_var = SparseMerkleProofVar::new_witness(&cs, &empty_proof);
+            empty_proof_var.verify_non_membership(&new_root_var, &key_var(empty_key));
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+}
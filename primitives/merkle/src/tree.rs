@@ -0,0 +1,168 @@
+use crate::MerkleHasherVar;
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::Var;
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::M31Var;
+
+/// A Merkle tree built in the circuit over a vector of digests, in which each inner node is the
+/// hash of its two children without any columns, as `H` would hash it natively.
+pub struct MerkleTreeVar<H: MerkleHasherVar> {
+    pub cs: ConstraintSystemRef,
+    /// The layers from the leaves up to the root.
+    pub layers: Vec<Vec<H::HashVar>>,
+}
+
+impl<H: MerkleHasherVar> Clone for MerkleTreeVar<H> {
+    fn clone(&self) -> Self {
+        Self {
+            cs: self.cs.clone(),
+            layers: self.layers.clone(),
+        }
+    }
+}
+
+impl<H: MerkleHasherVar> Var for MerkleTreeVar<H> {
+    type Value = Vec<<H::HashVar as Var>::Value>;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl<H: MerkleHasherVar> MerkleTreeVar<H> {
+    /// Builds the tree, where the number of leaves must be a power of two.
+    pub fn from_leaves(leaves: &[H::HashVar]) -> Self {
+        assert!(leaves.len().is_power_of_two());
+
+        let mut cs = leaves[0].cs();
+        for leaf in leaves.iter().skip(1) {
+            cs = cs.and(&leaf.cs());
+        }
+
+        let mut layers = vec![leaves.to_vec()];
+        while layers.last().unwrap().len() > 1 {
+            let layer = layers
+                .last()
+                .unwrap()
+                .chunks_exact(2)
+                .map(|pair| H::hash_node::<M31Var>(&cs, Some((&pair[0], &pair[1])), &[]))
+                .collect();
+            layers.push(layer);
+        }
+
+        Self { cs, layers }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn root(&self) -> H::HashVar {
+        self.layers.last().unwrap()[0].clone()
+    }
+
+    /// Returns the sibling hashes on the path of the leaf at `index`, from the leaves up.
+    pub fn path(&self, index: usize) -> Vec<H::HashVar> {
+        assert!(index < self.layers[0].len());
+        self.layers[..self.depth()]
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| layer[(index >> i) ^ 1].clone())
+            .collect()
+    }
+
+    /// Returns the values of the leaves.
+    pub fn value(&self) -> Vec<<H::HashVar as Var>::Value> {
+        self.layers[0].iter().map(H::hash_value).collect()
+    }
+
+    /// Verifies that `leaf` is at the position of `query` in the tree with the given root, where
+    /// `path` is as returned by `path`.
+    pub fn verify_path(root: &H::HashVar, leaf: &H::HashVar, query: &BitsVar, path: &[H::HashVar]) {
+        assert_eq!(query.value.len(), path.len());
+
+        let mut cur_hash = leaf.clone();
+        for (i, sibling) in path.iter().enumerate() {
+            cur_hash = H::hash_node_with_swap::<M31Var>(
+                &cur_hash,
+                sibling,
+                query.value[i],
+                query.variables[i],
+                &[],
+            );
+        }
+
+        assert_eq!(H::hash_value(&cur_hash), H::hash_value(root));
+        H::equalverify(&cur_hash, root);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{MerkleTreeVar, Poseidon31MerkleHasherVar};
+    use circle_plonk_dsl_bits::BitsVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use circle_plonk_dsl_poseidon31::Poseidon2HalfVar;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::vcs::ops::MerkleHasher;
+    use stwo_prover::core::vcs::poseidon31_hash::Poseidon31Hash;
+    use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
+
+    #[test]
+    fn test_merkle_tree_from_leaves() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            let leaves = (0..8)
+                .map(|_| Poseidon31Hash(prng.gen::<[M31; 8]>()))
+                .collect::<Vec<_>>();
+
+            let mut layers = vec![leaves.clone()];
+            while layers.last().unwrap().len() > 1 {
+                let layer = layers
+                    .last()
+                    .unwrap()
+                    .chunks_exact(2)
+                    .map(|pair| Poseidon31MerkleHasher::hash_node(Some((pair[0], pair[1])), &[]))
+                    .collect::<Vec<_>>();
+                layers.push(layer);
+            }
+
+            let leaves_var = leaves
+                .iter()
+                .map(|leaf| Poseidon2HalfVar::new_witness(&cs, &leaf.0))
+                .collect::<Vec<_>>();
+            let tree = MerkleTreeVar::<Poseidon31MerkleHasherVar>::from_leaves(&leaves_var);
+            assert_eq!(tree.depth(), 3);
+            assert_eq!(tree.root().value(), layers[3][0].0);
+
+            let index = 5;
+            let path = tree.path(index);
+            for (i, sibling) in path.iter().enumerate() {
+                assert_eq!(sibling.value(), layers[i][(index >> i) ^ 1].0);
+            }
+
+            let query = BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(index as u32)), 3);
+            let path_var = path
+                .iter()
+                .map(|sibling| Poseidon2HalfVar::new_witness(&cs, &sibling.value()))
+                .collect::<Vec<_>>();
+            MerkleTreeVar::<Poseidon31MerkleHasherVar>::verify_path(
+                &tree.root(),
+                &Poseidon2HalfVar::new_witness(&cs, &leaves[index].0),
+                &query,
+                &path_var,
+            );
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+}
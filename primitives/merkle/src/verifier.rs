@@ -0,0 +1,442 @@
+use crate::{MerkleColumnVar, MerkleHasherVar};
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::Var;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use std::collections::BTreeMap;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::FieldExpOps;
+
+/// Verifies the path from the leaf at `query` to the root, where `columns` maps a layer, with
+/// the leaves at `depth`, to the column values of the node on the path.
+pub fn verify_single_path_merkle_proof<H: MerkleHasherVar>(
+    root: &H::HashVar,
+    query: &BitsVar,
+    depth: usize,
+    sibling_hashes: &[H::HashVar],
+    columns: &BTreeMap<usize, Vec<M31Var>>,
+) {
+    let cs = root.cs().and(&query.cs());
+
+    let mut cur_hash = H::hash_node(
+        &cs,
+        None,
+        columns.get(&depth).map_or(&[][..], |v| v.as_slice()),
+    );
+
+    for i in 0..depth {
+        let h = depth - i - 1;
+
+        cur_hash = H::hash_node_with_swap(
+            &cur_hash,
+            &sibling_hashes[i],
+            query.value[i],
+            query.variables[i],
+            columns.get(&h).map_or(&[][..], |v| v.as_slice()),
+        );
+    }
+
+    assert_eq!(H::hash_value(&cur_hash), H::hash_value(root));
+    H::equalverify(&cur_hash, root);
+}
+
+/// Verifies the paths of the leaf at `query` and its sibling leaf, which share all the nodes
+/// above the leaves.
+///
+/// For a layer where the sibling has columns, the sibling hash is the digest of its children,
+/// which is finished with `hash_node_from_children_digest`.
+pub fn verify_single_pair_merkle_proof<H: MerkleHasherVar>(
+    root: &H::HashVar,
+    query: &BitsVar,
+    depth: usize,
+    sibling_hashes: &[H::HashVar],
+    self_columns: &BTreeMap<usize, QM31Var>,
+    siblings_columns: &BTreeMap<usize, QM31Var>,
+) {
+    let cs = root.cs().and(&query.cs());
+
+    let mut self_hash = H::hash_node(
+        &cs,
+        None,
+        self_columns
+            .get(&depth)
+            .map_or(&[][..], std::slice::from_ref),
+    );
+    let mut sibling_hash = H::hash_node(
+        &cs,
+        None,
+        siblings_columns
+            .get(&depth)
+            .map_or(&[][..], std::slice::from_ref),
+    );
+
+    for i in 0..depth {
+        let h = depth - i - 1;
+
+        match (self_columns.get(&h), siblings_columns.get(&h)) {
+            (None, None) => {
+                self_hash = H::hash_node_with_swap::<QM31Var>(
+                    &self_hash,
+                    &sibling_hash,
+                    query.value[i],
+                    query.variables[i],
+                    &[],
+                );
+                if i != depth - 1 {
+                    sibling_hash = sibling_hashes[i].clone();
+                }
+            }
+            (Some(self_column), Some(sibling_column)) => {
+                self_hash = H::hash_node_with_swap(
+                    &self_hash,
+                    &sibling_hash,
+                    query.value[i],
+                    query.variables[i],
+                    std::slice::from_ref(self_column),
+                );
+                sibling_hash = H::hash_node_from_children_digest(
+                    &sibling_hashes[i],
+                    std::slice::from_ref(sibling_column),
+                );
+            }
+            _ => panic!("the node and its sibling must have columns in the same layers"),
+        }
+    }
+
+    assert_eq!(H::hash_value(&self_hash), H::hash_value(root));
+    H::equalverify(&self_hash, root);
+}
+
+/// Verifies the paths of the leaves at `queries` together, as stwo verifies a Merkle
+/// decommitment, while the queries are witnesses.
+///
+/// The queries must be in increasing order, which the caller enforces, and they may repeat.
+/// Every layer holds one node per query, so that the layout only depends on the number of
+/// queries, and the queries at the same position compute the same node. A node takes its
+/// sibling from the closest node at a different position on the side of the sibling, when that
+/// node is at the sibling's position, and from `sibling_hashes` otherwise. A sibling witness is
+/// allocated for every query, but it only enters the hash where stwo also decommits it.
+///
+/// For each query, the maps are indexed by the layer, with the leaves at `depth`: `columns` has
+/// the column values of the node on the path, and `siblings_columns` those of its sibling where
+/// the sibling is decommitted too. Such a sibling is hashed from its columns, and finished from
+/// the digest of its children, taken from `sibling_hashes`, if it is not a leaf.
+pub fn verify_merkle_multi_proof<H: MerkleHasherVar, C: MerkleColumnVar>(
+    root: &H::HashVar,
+    queries: &[BitsVar],
+    depth: usize,
+    sibling_hashes: &[BTreeMap<usize, H::HashVar>],
+    columns: &[BTreeMap<usize, Vec<C>>],
+    siblings_columns: &[BTreeMap<usize, Vec<C>>],
+) {
+    let n = queries.len();
+    assert!(n > 0);
+    assert_eq!(sibling_hashes.len(), n);
+    assert_eq!(columns.len(), n);
+    assert_eq!(siblings_columns.len(), n);
+
+    let cs = queries
+        .iter()
+        .fold(root.cs(), |cs, query| cs.and(&query.cs()));
+    let get_columns = |columns: &BTreeMap<usize, Vec<C>>, h: usize| {
+        columns.get(&h).map_or(&[][..], |v| v.as_slice())
+    };
+    let half = M31::from(2).inverse();
+
+    let mut nodes = columns
+        .iter()
+        .map(|columns| H::hash_node(&cs, None, get_columns(columns, depth)))
+        .collect::<Vec<_>>();
+    let mut positions = queries
+        .iter()
+        .map(|query| query.compose_range(0..depth))
+        .collect::<Vec<_>>();
+
+    for i in 0..depth {
+        let h = depth - i;
+
+        let is_repeat = (1..n)
+            .map(|j| positions[j].is_eq(&positions[j - 1]))
+            .collect::<Vec<_>>();
+
+        // the closest node at a different position before and after each query, or the node
+        // itself where there is none, which is never at the sibling's position
+        let mut before = vec![(positions[0].clone(), nodes[0].clone())];
+        for j in 1..n {
+            let previous = (positions[j - 1].clone(), nodes[j - 1].clone());
+            before.push(select_node::<H>(
+                &previous,
+                &before[j - 1],
+                &is_repeat[j - 1],
+            ));
+        }
+        let mut after = vec![(positions[n - 1].clone(), nodes[n - 1].clone())];
+        for j in (0..n - 1).rev() {
+            let next = (positions[j + 1].clone(), nodes[j + 1].clone());
+            after.push(select_node::<H>(
+                &next,
+                after.last().unwrap(),
+                &is_repeat[j],
+            ));
+        }
+        after.reverse();
+
+        let mut parents = Vec::with_capacity(n);
+        let mut parent_positions = Vec::with_capacity(n);
+        for j in 0..n {
+            let bit = queries[j].get_bit(i);
+
+            let sibling = if let Some(sibling_columns) = siblings_columns[j].get(&h) {
+                if i == 0 {
+                    H::hash_node(&cs, None, sibling_columns)
+                } else {
+                    H::hash_node_from_children_digest(&sibling_hashes[j][&h], sibling_columns)
+                }
+            } else {
+                // a left child has its sibling after it, and a right child before it
+                let (position, node) = select_node::<H>(&after[j], &before[j], &bit);
+                let sibling_position = &(&positions[j] + &M31Var::one(&cs)) - &(&bit + &bit);
+                let is_on_path = position.is_eq(&sibling_position);
+                H::select(
+                    &sibling_hashes[j][&h],
+                    &node,
+                    is_on_path.value.0 == 1,
+                    is_on_path.variable,
+                )
+            };
+
+            parents.push(H::hash_node_with_swap(
+                &nodes[j],
+                &sibling,
+                queries[j].value[i],
+                queries[j].variables[i],
+                get_columns(&columns[j], h - 1),
+            ));
+            parent_positions.push((&positions[j] - &bit).mul_constant(half));
+        }
+        nodes = parents;
+        positions = parent_positions;
+    }
+
+    for node in nodes.iter() {
+        assert_eq!(H::hash_value(node), H::hash_value(root));
+        H::equalverify(node, root);
+    }
+}
+
+/// Returns the position and the node of `a` if the bit is zero, and those of `b` otherwise.
+fn select_node<H: MerkleHasherVar>(
+    a: &(M31Var, H::HashVar),
+    b: &(M31Var, H::HashVar),
+    bit: &M31Var,
+) -> (M31Var, H::HashVar) {
+    let position = &a.0 + &(&(&b.0 - &a.0) * bit);
+    let node = H::select(&a.1, &b.1, bit.value.0 == 1, bit.variable);
+    (position, node)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        verify_merkle_multi_proof, verify_single_path_merkle_proof, Poseidon31MerkleHasherVar,
+    };
+    use circle_plonk_dsl_bits::BitsVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use circle_plonk_dsl_poseidon31::Poseidon2HalfVar;
+    use num_traits::Zero;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::vcs::ops::MerkleHasher;
+    use stwo_prover::core::vcs::poseidon31_hash::Poseidon31Hash;
+    use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
+
+    const DEPTH: usize = 3;
+
+    /// Returns the columns by layer and position, and the hashes of each layer from the leaves.
+    ///
+    /// The tree has three columns at the leaves and two columns below the root.
+    fn sample_tree() -> (BTreeMap<usize, Vec<Vec<M31>>>, Vec<Vec<Poseidon31Hash>>) {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let columns: BTreeMap<usize, Vec<Vec<M31>>> = BTreeMap::from([
+            (
+                3,
+                (0..8)
+                    .map(|_| (0..3).map(|_| prng.gen()).collect())
+                    .collect(),
+            ),
+            (
+                1,
+                (0..2)
+                    .map(|_| (0..2).map(|_| prng.gen()).collect())
+                    .collect(),
+            ),
+        ]);
+
+        let mut layers: Vec<Vec<Poseidon31Hash>> = vec![columns[&DEPTH]
+            .iter()
+            .map(|v| Poseidon31MerkleHasher::hash_node(None, v))
+            .collect()];
+        for h in (0..DEPTH).rev() {
+            let prev = layers.last().unwrap();
+            let layer = (0..1 << h)
+                .map(|j| {
+                    Poseidon31MerkleHasher::hash_node(
+                        Some((prev[2 * j], prev[2 * j + 1])),
+                        columns.get(&h).map_or(&[][..], |v| v[j].as_slice()),
+                    )
+                })
+                .collect();
+            layers.push(layer);
+        }
+
+        (columns, layers)
+    }
+
+    #[test]
+    fn test_poseidon31_single_path_merkle_proof() {
+        let (tree_columns, layers) = sample_tree();
+        let root = layers[DEPTH][0];
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            for query in [2, 7] {
+                let query_bits = (0..DEPTH)
+                    .map(|i| (query >> i) & 1 != 0)
+                    .collect::<Vec<_>>();
+                let query_var = BitsVar::new_witness(&cs, &query_bits);
+
+                let sibling_hashes = (0..DEPTH)
+                    .map(|i| Poseidon2HalfVar::new_witness(&cs, &layers[i][(query >> i) ^ 1].0))
+                    .collect::<Vec<_>>();
+                let mut columns = BTreeMap::new();
+                for (&h, values) in tree_columns.iter() {
+                    let values = values[query >> (DEPTH - h)]
+                        .iter()
+                        .map(|v| M31Var::new_witness(&cs, v))
+                        .collect::<Vec<_>>();
+                    columns.insert(h, values);
+                }
+
+                let root_var = Poseidon2HalfVar::new_witness(&cs, &root.0);
+                verify_single_path_merkle_proof::<Poseidon31MerkleHasherVar>(
+                    &root_var,
+                    &query_var,
+                    DEPTH,
+                    &sibling_hashes,
+                    &columns,
+                );
+            }
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+
+    #[test]
+    fn test_poseidon31_merkle_multi_proof() {
+        let (tree_columns, layers) = sample_tree();
+        let root = layers[DEPTH][0];
+
+        // repeated queries and paths that meet, with the siblings on other paths given wrong
+        // witnesses, which must not be used; then pairs of leaves, whose siblings are
+        // decommitted with their columns, as in FRI
+        for (queries, is_pair) in [(vec![0, 1, 1, 5], false), (vec![1, 4, 4, 6], true)] {
+            let mut sibling_hashes_value = vec![];
+            let mut columns_value = vec![];
+            let mut siblings_columns_value = vec![];
+            for &query in queries.iter() {
+                let mut sibling_hashes = BTreeMap::new();
+                let mut siblings_columns = BTreeMap::new();
+                for h in 1..=DEPTH {
+                    let position = query >> (DEPTH - h);
+                    let sibling = position ^ 1;
+                    let is_on_path = queries.iter().any(|q| q >> (DEPTH - h) == sibling);
+
+                    if is_pair && tree_columns.contains_key(&h) {
+                        siblings_columns.insert(h, tree_columns[&h][sibling].clone());
+                        if h < DEPTH {
+                            let children = &layers[DEPTH - h - 1][sibling << 1..][..2];
+                            let digest = Poseidon31MerkleHasher::hash_node(
+                                Some((children[0], children[1])),
+                                &[],
+                            );
+                            sibling_hashes.insert(h, digest);
+                        }
+                    } else if is_on_path {
+                        sibling_hashes.insert(h, Poseidon31Hash([M31::zero(); 8]));
+                    } else {
+                        sibling_hashes.insert(h, layers[DEPTH - h][sibling]);
+                    }
+                }
+
+                let columns = tree_columns
+                    .iter()
+                    .map(|(&h, values)| (h, values[query >> (DEPTH - h)].clone()))
+                    .collect::<BTreeMap<_, _>>();
+
+                sibling_hashes_value.push(sibling_hashes);
+                columns_value.push(columns);
+                siblings_columns_value.push(siblings_columns);
+            }
+
+            for cs in [
+                ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+                ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+            ] {
+                let alloc_columns = |columns: &Vec<BTreeMap<usize, Vec<M31>>>| {
+                    columns
+                        .iter()
+                        .map(|columns| {
+                            columns
+                                .iter()
+                                .map(|(&h, v)| {
+                                    (h, v.iter().map(|x| M31Var::new_witness(&cs, x)).collect())
+                                })
+                                .collect::<BTreeMap<usize, Vec<M31Var>>>()
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                let query_vars = queries
+                    .iter()
+                    .map(|&query| {
+                        let bits = (0..DEPTH)
+                            .map(|i| (query >> i) & 1 != 0)
+                            .collect::<Vec<_>>();
+                        BitsVar::new_witness(&cs, &bits)
+                    })
+                    .collect::<Vec<_>>();
+                let sibling_hashes = sibling_hashes_value
+                    .iter()
+                    .map(|sibling_hashes| {
+                        sibling_hashes
+                            .iter()
+                            .map(|(&h, v)| (h, Poseidon2HalfVar::new_witness(&cs, &v.0)))
+                            .collect::<BTreeMap<_, _>>()
+                    })
+                    .collect::<Vec<_>>();
+                let columns = alloc_columns(&columns_value);
+                let siblings_columns = alloc_columns(&siblings_columns_value);
+
+                let root_var = Poseidon2HalfVar::new_witness(&cs, &root.0);
+                verify_merkle_multi_proof::<Poseidon31MerkleHasherVar, M31Var>(
+                    &root_var,
+                    &query_vars,
+                    DEPTH,
+                    &sibling_hashes,
+                    &columns,
+                    &siblings_columns,
+                );
+
+                cs.pad();
+                cs.check_arithmetics();
+            }
+        }
+    }
+}
@@ -22,16 +22,7 @@ pub fn apply_4x4_mds_matrix(x: &QM31Var) -> QM31Var {
 }
 
 pub fn apply_16x16_mds_matrix(state: [QM31Var; 4]) -> [QM31Var; 4] {
-    let p1 = apply_4x4_mds_matrix(&state[0]);
-    let p2 = apply_4x4_mds_matrix(&state[1]);
-    let p3 = apply_4x4_mds_matrix(&state[2]);
-    let p4 = apply_4x4_mds_matrix(&state[3]);
-
-    let mut t = &p1 + &p2;
-    t = &t + &p3;
-    t = &t + &p4;
-
-    [&p1 + &t, &p2 + &t, &p3 + &t, &p4 + &t]
+    apply_external_sum(std::array::from_fn(|i| apply_4x4_mds_matrix(&state[i])))
 }
 
 pub fn pow5m4(x: &QM31Var) -> QM31Var {
@@ -77,6 +68,13 @@ pub fn pow5(cs: &ConstraintSystemRef, variable: usize) -> usize {
     cs.do_pow5_gate(variable, b_var.variable)
 }
 
+/// The emulated Poseidon2 permutation on `PlonkWithoutPoseidon`, in which each QM31 variable
+/// holds four consecutive elements of the state.
+///
+/// The initial linear layer applies `M4` with the m4 gate, the external rounds fuse the S-boxes
+/// with `M4` in the pow5m4 gate, and the internal rounds use the pow5, Hadamard and grand sum
+/// gates. Once the constants are allocated, the permutation takes 401 rows: 11 for the initial
+/// linear layer, 19 for each external round and 17 for each internal round, and a swap adds 12.
 pub fn poseidon_permute_emulated(
     left: &Poseidon2HalfEmulatedVar,
     right: &Poseidon2HalfEmulatedVar,
@@ -113,99 +111,14 @@ pub fn poseidon_permute_emulated(
     ];
     state = apply_16x16_mds_matrix(state);
 
-    for r in 0..4 {
-        for i in 0..4 {
-            state[i] = &state[i]
-                + &QM31Var::new_constant(
-                    &cs,
-                    &QM31::from_m31(
-                        FIRST_FOUR_ROUND_RC[r][i * 4],
-                        FIRST_FOUR_ROUND_RC[r][i * 4 + 1],
-                        FIRST_FOUR_ROUND_RC[r][i * 4 + 2],
-                        FIRST_FOUR_ROUND_RC[r][i * 4 + 3],
-                    ),
-                );
-        }
-        for i in 0..4 {
-            state[i] = pow5m4(&state[i]);
-        }
-        let mut t = &state[0] + &state[1];
-        t = &t + &state[2];
-        t = &t + &state[3];
-        state = [
-            &state[0] + &t,
-            &state[1] + &t,
-            &state[2] + &t,
-            &state[3] + &t,
-        ];
+    for rc in FIRST_FOUR_ROUND_RC.iter() {
+        state = external_round(&state, rc);
     }
-
-    for r in 0..14 {
-        let mut first_limb_with_first_only = cs.do_hadamard(state[0].variable, 1);
-
-        let constant = QM31Var::new_constant(&cs, &QM31::from_u32_unchecked(0, 1, 1, 1));
-        let first_limb_without_first = cs.do_hadamard(state[0].variable, constant.variable);
-
-        let constant = M31Var::new_constant(&cs, &PARTIAL_ROUNDS_RC[r]);
-        first_limb_with_first_only = cs.add(first_limb_with_first_only, constant.variable);
-        first_limb_with_first_only = pow5(&cs, first_limb_with_first_only);
-        let tmp = cs.add(first_limb_with_first_only, first_limb_without_first);
-        state[0] = QM31Var {
-            cs: cs.clone(),
-            value: cs.get_value(tmp),
-            variable: tmp,
-        };
-
-        let sum_1 = cs.do_grandsum_gate(state[0].variable, state[1].variable);
-        let sum_2 = cs.do_grandsum_gate(state[2].variable, state[3].variable);
-        let sum = cs.add(sum_1, sum_2);
-
-        for i in 0..4 {
-            let constant = QM31Var::new_constant(
-                &cs,
-                &QM31::from_m31_array([
-                    MAT_DIAG16_M_1[i * 4],
-                    MAT_DIAG16_M_1[i * 4 + 1],
-                    MAT_DIAG16_M_1[i * 4 + 2],
-                    MAT_DIAG16_M_1[i * 4 + 3],
-                ]),
-            );
-            let mut v = cs.do_hadamard(state[i].variable, constant.variable);
-            v = cs.add(sum, v);
-
-            state[i] = QM31Var {
-                cs: cs.clone(),
-                value: cs.get_value(v),
-                variable: v,
-            }
-        }
+    for &rc in PARTIAL_ROUNDS_RC.iter() {
+        internal_round(&mut state, rc);
     }
-
-    for r in 0..4 {
-        for i in 0..4 {
-            state[i] = &state[i]
-                + &QM31Var::new_constant(
-                    &cs,
-                    &QM31::from_m31(
-                        LAST_FOUR_ROUNDS_RC[r][i * 4],
-                        LAST_FOUR_ROUNDS_RC[r][i * 4 + 1],
-                        LAST_FOUR_ROUNDS_RC[r][i * 4 + 2],
-                        LAST_FOUR_ROUNDS_RC[r][i * 4 + 3],
-                    ),
-                );
-        }
-        for i in 0..4 {
-            state[i] = pow5m4(&state[i]);
-        }
-        let mut t = &state[0] + &state[1];
-        t = &t + &state[2];
-        t = &t + &state[3];
-        state = [
-            &state[0] + &t,
-            &state[1] + &t,
-            &state[2] + &t,
-            &state[3] + &t,
-        ];
+    for rc in LAST_FOUR_ROUNDS_RC.iter() {
+        state = external_round(&state, rc);
     }
 
     let out_left = Poseidon2HalfEmulatedVar {
@@ -220,6 +133,75 @@ pub fn poseidon_permute_emulated(
     (out_left, out_right)
 }
 
+/// Adds the round constants and applies the S-boxes fused with `M4` by the pow5m4 gate, followed
+/// by the rest of the external linear layer.
+fn external_round(state: &[QM31Var; 4], rc: &[M31; 16]) -> [QM31Var; 4] {
+    let cs = state[0].cs();
+    let p = std::array::from_fn(|i| {
+        let constant = QM31Var::new_constant(
+            &cs,
+            &QM31::from_m31(rc[i * 4], rc[i * 4 + 1], rc[i * 4 + 2], rc[i * 4 + 3]),
+        );
+        pow5m4(&(&state[i] + &constant))
+    });
+    apply_external_sum(p)
+}
+
+/// Completes the external linear layer `circ(2 M4, M4, M4, M4)` from the `M4` images of the
+/// four blocks.
+fn apply_external_sum(p: [QM31Var; 4]) -> [QM31Var; 4] {
+    let mut t = &p[0] + &p[1];
+    t = &t + &p[2];
+    t = &t + &p[3];
+
+    std::array::from_fn(|i| &p[i] + &t)
+}
+
+/// Applies the S-box to the first element with the round constant, and the internal linear
+/// layer `1 + diag(MAT_DIAG16_M_1)`.
+fn internal_round(state: &mut [QM31Var; 4], rc: M31) {
+    let cs = state[0].cs();
+
+    // the arithmetic gate against the unit computes `rc * (a + 1) + (1 - rc) * a = a + rc`, so
+    // that the round constants do not need to be allocated
+    let first_only = cs.do_hadamard(state[0].variable, 1);
+    let first_only = cs.do_arith_gate(first_only, 1, rc);
+    let first_only = pow5(&cs, first_only);
+
+    let constant = QM31Var::new_constant(&cs, &QM31::from_u32_unchecked(0, 1, 1, 1));
+    let without_first = cs.do_hadamard(state[0].variable, constant.variable);
+    let first = cs.add(first_only, without_first);
+    state[0] = QM31Var {
+        cs: cs.clone(),
+        value: cs.get_value(first),
+        variable: first,
+    };
+
+    let sum_1 = cs.do_grandsum_gate(state[0].variable, state[1].variable);
+    let sum_2 = cs.do_grandsum_gate(state[2].variable, state[3].variable);
+    let sum = cs.add(sum_1, sum_2);
+
+    for i in 0..4 {
+        let constant = QM31Var::new_constant(
+            &cs,
+            &QM31::from_m31_array([
+                MAT_DIAG16_M_1[i * 4],
+                MAT_DIAG16_M_1[i * 4 + 1],
+                MAT_DIAG16_M_1[i * 4 + 2],
+                MAT_DIAG16_M_1[i * 4 + 3],
+            ]),
+        );
+        let mut v = cs.do_hadamard(state[i].variable, constant.variable);
+        v = cs.add(sum, v);
+
+        state[i] = QM31Var {
+            cs: cs.clone(),
+            value: cs.get_value(v),
+            variable: v,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,4 +278,32 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_poseidon2_emulated_permute_num_rows() {
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+
+        let left: [M31Var; 8] = std::array::from_fn(|i| M31Var::new_witness(&cs, &M31::from(i)));
+        let right: [M31Var; 8] =
+            std::array::from_fn(|i| M31Var::new_witness(&cs, &M31::from(i + 8)));
+
+        let left = Poseidon2HalfVar::from_m31(&left);
+        let right = Poseidon2HalfVar::from_m31(&right);
+
+        // allocates the constants
+        let expected = Poseidon2HalfVar::permute(&left, &right, false, false, None);
+
+        let num_rows = cs.num_plonk_rows();
+        Poseidon2HalfVar::permute(&left, &right, false, false, None);
+        assert_eq!(cs.num_plonk_rows() - num_rows, 401);
+
+        let num_rows = cs.num_plonk_rows();
+        let result = Poseidon2HalfVar::permute(&right, &left, false, false, Some((true, 1)));
+        assert_eq!(cs.num_plonk_rows() - num_rows, 413);
+        assert_eq!(result.0.value(), expected.0.value());
+        assert_eq!(result.1.value(), expected.1.value());
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
 }
@@ -12,6 +12,9 @@ mod emulated;
 pub mod implementation;
 mod parameters;
 
+pub mod sponge;
+pub use sponge::*;
+
 #[derive(Debug, Clone)]
 pub enum Poseidon2HalfVar {
     Native(Poseidon2HalfNativeVar),
@@ -0,0 +1,391 @@
+use crate::implementation::poseidon2_permute;
+use crate::Poseidon2HalfVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use num_traits::{One, Zero};
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
+
+/// A Poseidon2 sponge over the 16-element state, in which the first half is the rate and the
+/// second half is the capacity.
+///
+/// Each chunk of eight elements overwrites the rate before the permutation. A chunk is only
+/// permuted once more data arrives or the sponge is squeezed, so that the last permutation can
+/// output either half.
+///
+/// A sponge from `new` pads the last chunk with zeros only, which is how stwo hashes the columns
+/// of a Merkle tree; such a sponge is only injective for inputs of a fixed length, since
+/// absorbing `[x]` and `[x, 0]` gives the same output. Other uses should start from `new_padded`,
+/// which appends a one before the zeros (the 10* padding).
+#[derive(Clone, Debug)]
+pub struct Poseidon2Sponge {
+    pub capacity: [M31; 8],
+    pub rate: Vec<M31>,
+    pub padded: bool,
+}
+
+impl Default for Poseidon2Sponge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Poseidon2Sponge {
+    pub fn new() -> Self {
+        Self {
+            capacity: [M31::zero(); 8],
+            rate: vec![],
+            padded: false,
+        }
+    }
+
+    pub fn new_padded() -> Self {
+        Self {
+            padded: true,
+            ..Self::new()
+        }
+    }
+
+    /// Starts a padded sponge with the separator in the first element of the capacity.
+    pub fn new_with_domain_separator(separator: M31) -> Self {
+        let mut sponge = Self::new_padded();
+        sponge.capacity[0] = separator;
+        sponge
+    }
+
+    pub fn absorb_m31(&mut self, elems: &[M31]) {
+        for &elem in elems.iter() {
+            if self.rate.len() == 8 {
+                self.capacity = self.permute()[8..].try_into().unwrap();
+            }
+            self.rate.push(elem);
+        }
+    }
+
+    pub fn absorb_qm31(&mut self, elems: &[QM31]) {
+        for elem in elems.iter() {
+            self.absorb_m31(&elem.to_m31_array());
+        }
+    }
+
+    /// Returns the rate after permuting the remaining data, and continues from the capacity.
+    pub fn squeeze(&mut self) -> [M31; 8] {
+        self.pad();
+        let state = self.permute();
+        self.capacity = state[8..].try_into().unwrap();
+        state[..8].try_into().unwrap()
+    }
+
+    pub fn squeeze_get_rate(mut self) -> [M31; 8] {
+        self.pad();
+        self.permute()[..8].try_into().unwrap()
+    }
+
+    pub fn squeeze_get_capacity(mut self) -> [M31; 8] {
+        self.pad();
+        self.permute()[8..].try_into().unwrap()
+    }
+
+    fn pad(&mut self) {
+        if self.padded {
+            self.absorb_m31(&[M31::one()]);
+        }
+    }
+
+    fn permute(&mut self) -> [M31; 16] {
+        let mut state = [M31::zero(); 16];
+        state[..self.rate.len()].copy_from_slice(&self.rate);
+        state[8..].copy_from_slice(&self.capacity);
+        self.rate.clear();
+
+        poseidon2_permute(&mut state);
+        state
+    }
+}
+
+/// The in-circuit counterpart of `Poseidon2Sponge`.
+///
+/// The absorbed elements are grouped into QM31 elements, four at a time, so that absorbing
+/// aligned QM31 elements does not need to decompose them.
+#[derive(Clone, Debug)]
+pub struct Poseidon2SpongeVar {
+    pub cs: ConstraintSystemRef,
+    pub capacity: Poseidon2HalfVar,
+    pub rate: Vec<QM31Var>,
+    pub pending: Vec<M31Var>,
+    pub padded: bool,
+}
+
+impl Var for Poseidon2SpongeVar {
+    type Value = Poseidon2Sponge;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl Poseidon2SpongeVar {
+    pub fn new(cs: &ConstraintSystemRef) -> Self {
+        Self {
+            cs: cs.clone(),
+            capacity: Poseidon2HalfVar::zero(cs),
+            rate: vec![],
+            pending: vec![],
+            padded: false,
+        }
+    }
+
+    pub fn new_padded(cs: &ConstraintSystemRef) -> Self {
+        Self {
+            padded: true,
+            ..Self::new(cs)
+        }
+    }
+
+    /// Starts a padded sponge with the separator in the first element of the capacity.
+    pub fn new_with_domain_separator(cs: &ConstraintSystemRef, separator: M31) -> Self {
+        let mut capacity = vec![M31Var::new_constant(cs, &separator)];
+        capacity.resize(8, M31Var::zero(cs));
+
+        Self {
+            capacity: Poseidon2HalfVar::from_m31(&capacity),
+            ..Self::new_padded(cs)
+        }
+    }
+
+    pub fn value(&self) -> Poseidon2Sponge {
+        let mut rate = vec![];
+        for elem in self.rate.iter() {
+            rate.extend_from_slice(&elem.value.to_m31_array());
+        }
+        for elem in self.pending.iter() {
+            rate.push(elem.value);
+        }
+
+        Poseidon2Sponge {
+            capacity: self.capacity.value(),
+            rate,
+            padded: self.padded,
+        }
+    }
+
+    pub fn absorb_m31(&mut self, elems: &[M31Var]) {
+        for elem in elems.iter() {
+            self.cs = self.cs.and(&elem.cs());
+            if self.rate.len() == 2 {
+                self.permute_rate();
+            }
+
+            self.pending.push(elem.clone());
+            if self.pending.len() == 4 {
+                let group = QM31Var::from_m31(
+                    &self.pending[0],
+                    &self.pending[1],
+                    &self.pending[2],
+                    &self.pending[3],
+                );
+                self.pending.clear();
+                self.rate.push(group);
+            }
+        }
+    }
+
+    pub fn absorb_qm31(&mut self, elems: &[QM31Var]) {
+        for elem in elems.iter() {
+            if self.pending.is_empty() {
+                self.cs = self.cs.and(&elem.cs());
+                if self.rate.len() == 2 {
+                    self.permute_rate();
+                }
+                self.rate.push(elem.clone());
+            } else {
+                self.absorb_m31(&elem.decompose_m31());
+            }
+        }
+    }
+
+    /// Returns the rate after permuting the remaining data, and continues from the capacity.
+    pub fn squeeze(&mut self) -> Poseidon2HalfVar {
+        let chunk = self.take_last_chunk();
+        let (rate, capacity) =
+            Poseidon2HalfVar::permute(&chunk, &self.capacity, false, false, None);
+        self.capacity = capacity;
+        rate
+    }
+
+    pub fn squeeze_get_rate(mut self) -> Poseidon2HalfVar {
+        let chunk = self.take_last_chunk();
+        Poseidon2HalfVar::permute_get_rate(&chunk, &self.capacity)
+    }
+
+    pub fn squeeze_get_capacity(mut self) -> Poseidon2HalfVar {
+        let chunk = self.take_last_chunk();
+        Poseidon2HalfVar::permute_get_capacity(&chunk, &self.capacity)
+    }
+
+    fn permute_rate(&mut self) {
+        let chunk = Poseidon2HalfVar::from_qm31(&self.rate[0], &self.rate[1]);
+        self.rate.clear();
+        self.capacity = Poseidon2HalfVar::permute_get_capacity(&chunk, &self.capacity);
+    }
+
+    fn take_last_chunk(&mut self) -> Poseidon2HalfVar {
+        let cs = self.cs();
+        if self.padded {
+            self.absorb_m31(&[M31Var::one(&cs)]);
+        }
+
+        // pending elements only exist while the rate is not full
+        if !self.pending.is_empty() {
+            self.pending.resize(4, M31Var::zero(&cs));
+            let group = QM31Var::from_m31(
+                &self.pending[0],
+                &self.pending[1],
+                &self.pending[2],
+                &self.pending[3],
+            );
+            self.pending.clear();
+            self.rate.push(group);
+        }
+        self.rate.resize(2, QM31Var::zero(&cs));
+
+        let chunk = Poseidon2HalfVar::from_qm31(&self.rate[0], &self.rate[1]);
+        self.rate.clear();
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Poseidon2Sponge, Poseidon2SpongeVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::{M31Var, QM31Var};
+    use num_traits::Zero;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
+
+    #[test]
+    fn test_poseidon2_sponge() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        // the native sponge is consistent with the column hashes of the Merkle hasher
+        for len in [1, 7, 8, 13, 16, 25] {
+            let elems = (0..len).map(|_| prng.gen()).collect::<Vec<M31>>();
+
+            let mut sponge = Poseidon2Sponge::new();
+            sponge.absorb_m31(&elems);
+            assert_eq!(
+                sponge.clone().squeeze_get_rate(),
+                Poseidon31MerkleHasher::hash_column_get_rate(&elems).0
+            );
+            assert_eq!(
+                sponge.squeeze_get_capacity(),
+                Poseidon31MerkleHasher::hash_column_get_capacity(&elems).0
+            );
+        }
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            let mut sponge = Poseidon2Sponge::new_with_domain_separator(M31::from(7));
+            let mut sponge_var = Poseidon2SpongeVar::new_with_domain_separator(&cs, M31::from(7));
+
+            // unaligned QM31 elements are split into M31 elements
+            for (n_m31, n_qm31) in [(3, 2), (0, 3), (9, 1)] {
+                let m31 = (0..n_m31).map(|_| prng.gen()).collect::<Vec<M31>>();
+                let qm31 = (0..n_qm31).map(|_| prng.gen()).collect::<Vec<QM31>>();
+
+                let m31_var = m31
+                    .iter()
+                    .map(|v| M31Var::new_witness(&cs, v))
+                    .collect::<Vec<_>>();
+                let qm31_var = qm31
+                    .iter()
+                    .map(|v| QM31Var::new_witness(&cs, v))
+                    .collect::<Vec<_>>();
+
+                sponge.absorb_m31(&m31);
+                sponge.absorb_qm31(&qm31);
+                sponge_var.absorb_m31(&m31_var);
+                sponge_var.absorb_qm31(&qm31_var);
+                assert_eq!(sponge_var.value().rate, sponge.rate);
+
+                assert_eq!(sponge_var.squeeze().value(), sponge.squeeze());
+                assert_eq!(sponge_var.squeeze().value(), sponge.squeeze());
+            }
+
+            let elems = (0..10).map(|_| prng.gen()).collect::<Vec<M31>>();
+            let elems_var = elems
+                .iter()
+                .map(|v| M31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>();
+            sponge.absorb_m31(&elems);
+            sponge_var.absorb_m31(&elems_var);
+            assert_eq!(
+                sponge_var.clone().squeeze_get_rate().value(),
+                sponge.clone().squeeze_get_rate()
+            );
+            assert_eq!(
+                sponge_var.squeeze_get_capacity().value(),
+                sponge.squeeze_get_capacity()
+            );
+
+            // the domain separator changes the output
+            assert_ne!(
+                Poseidon2Sponge::new_padded().squeeze_get_rate(),
+                Poseidon2Sponge::new_with_domain_separator(M31::from(7)).squeeze_get_rate()
+            );
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+
+    #[test]
+    fn test_poseidon2_sponge_padding() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        // without the padding, trailing zeros are not absorbed
+        let x: M31 = prng.gen();
+        let hash = |padded: bool, elems: &[M31]| {
+            let mut sponge = if padded {
+                Poseidon2Sponge::new_padded()
+            } else {
+                Poseidon2Sponge::new()
+            };
+            sponge.absorb_m31(elems);
+            sponge.squeeze_get_rate()
+        };
+        assert_eq!(hash(false, &[x]), hash(false, &[x, M31::zero()]));
+        assert_ne!(hash(true, &[x]), hash(true, &[x, M31::zero()]));
+        assert_ne!(hash(true, &[]), hash(true, &[M31::zero(); 8]));
+
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+        for len in [0, 1, 4, 7, 8, 9, 16] {
+            let elems = (0..len).map(|_| prng.gen()).collect::<Vec<M31>>();
+            let elems_var = elems
+                .iter()
+                .map(|v| M31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>();
+
+            let mut sponge = Poseidon2Sponge::new_padded();
+            let mut sponge_var = Poseidon2SpongeVar::new_padded(&cs);
+            sponge.absorb_m31(&elems);
+            sponge_var.absorb_m31(&elems_var);
+            assert_eq!(sponge_var.squeeze().value(), sponge.squeeze());
+            assert_eq!(
+                sponge_var.squeeze_get_capacity().value(),
+                sponge.squeeze_get_capacity()
+            );
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -1,13 +1,13 @@
-use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_bits::{BitsVar, RangeCheckVar};
 use circle_plonk_dsl_circle::CirclePointM31Var;
-use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
-use circle_plonk_dsl_fields::M31Var;
+use circle_plonk_dsl_constraint_system::var::Var;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
 use itertools::Itertools;
-use num_traits::One;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Index, Neg, RangeInclusive};
 use stwo_prover::core::circle::CirclePoint;
 use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::pcs::PcsConfig;
 use stwo_prover::core::poly::circle::CanonicCoset;
 
 pub struct QueryPositionsPerLogSizeVar {
@@ -16,15 +16,53 @@ pub struct QueryPositionsPerLogSizeVar {
 }
 
 impl QueryPositionsPerLogSizeVar {
+    /// Returns the log sizes that the queries go through, from the largest column down to the
+    /// layer that is folded into the last FRI layer.
+    pub fn log_size_range(
+        pcs_config: PcsConfig,
+        column_log_sizes: &BTreeSet<u32>,
+    ) -> RangeInclusive<u32> {
+        let fri_config = pcs_config.fri_config;
+        let min_log_size =
+            fri_config.log_last_layer_degree_bound + fri_config.log_blowup_factor + 1;
+        let max_log_size = *column_log_sizes.last().unwrap();
+        assert!(
+            *column_log_sizes.first().unwrap() >= min_log_size,
+            "columns must be larger than the last FRI layer"
+        );
+        min_log_size..=max_log_size
+    }
+
+    pub fn from_pcs_config(
+        pcs_config: PcsConfig,
+        column_log_sizes: &BTreeSet<u32>,
+        raw_queries: &[M31Var],
+    ) -> Self {
+        Self::new(
+            Self::log_size_range(pcs_config, column_log_sizes),
+            raw_queries,
+        )
+    }
+
+    /// Sorts the queries in the circuit at the largest log size, so that the queries are in
+    /// increasing order at every log size, with one slot per query, as the Merkle multi-proofs
+    /// require.
     pub fn new(range: RangeInclusive<u32>, raw_queries: &[M31Var]) -> Self {
         let max_degree = *range.end();
         let min_degree = *range.start();
 
+        let mut positions = raw_queries
+            .iter()
+            .map(|raw_query| BitsVar::from_m31(raw_query, 31).compose_range(0..max_degree as usize))
+            .collect_vec();
+        sort_positions(&mut positions, max_degree);
+
         let mut elems = vec![];
-        for raw_query in raw_queries {
-            elems.push(PointCarryingQueryVar::new(
-                BitsVar::from_m31(&raw_query, 31).index_range(0..max_degree as usize),
-            ));
+        for position in positions.iter() {
+            elems.push(PointCarryingQueryVar::new(BitsVar::from_m31(
+                position,
+                max_degree as usize,
+            )));
         }
         let mut points = BTreeMap::new();
         points.insert(max_degree, elems.clone());
@@ -36,6 +74,70 @@ impl QueryPositionsPerLogSizeVar {
 
         Self { range, points }
     }
+
+    /// Returns the positions of the queries at `log_size` in increasing order, one per query,
+    /// including the queries that are at the same position.
+    pub fn positions(&self, log_size: u32) -> Vec<M31Var> {
+        self[log_size]
+            .iter()
+            .map(|query| query.bits.compose_range(0..log_size as usize))
+            .collect()
+    }
+
+    /// Returns the positions at `log_size`, which are in increasing order, with each slot marked
+    /// with whether it starts a new position.
+    ///
+    /// The answer stages read the distinct positions off the marked slots to compare them with
+    /// the deduplicated queries of the hints, while the Merkle proofs and the folding work per
+    /// slot.
+    pub fn sorted_positions(&self, log_size: u32) -> SortedQueryPositionsVar {
+        let positions = self.positions(log_size);
+        let n = positions.len();
+
+        let mut is_first = Vec::with_capacity(n);
+        for (i, position) in positions.iter().enumerate() {
+            if i == 0 {
+                is_first.push(M31Var::one(&position.cs()));
+            } else {
+                let is_repeat = position.is_eq(&positions[i - 1]);
+                is_first.push(&M31Var::one(&position.cs()) - &is_repeat);
+            }
+        }
+
+        SortedQueryPositionsVar {
+            log_size,
+            positions,
+            is_first,
+        }
+    }
+}
+
+/// The query positions at a log size in increasing order, with one slot per query.
+///
+/// The queries at the same position are not merged: every slot carries its own Merkle proof and
+/// its own folded value, so the slots at a repeated position are checked independently and do
+/// not need an index to the first of them.
+#[derive(Clone)]
+pub struct SortedQueryPositionsVar {
+    pub log_size: u32,
+    pub positions: Vec<M31Var>,
+    /// For each slot, a bit indicating whether its position differs from the one in the slot
+    /// before, so the distinct positions are those in the slots with the bit set.
+    pub is_first: Vec<M31Var>,
+}
+
+/// Sorts `positions`, which are smaller than `2^log_size`, by an odd-even transposition network
+/// of comparators, so that the layout only depends on the number of positions.
+pub fn sort_positions(positions: &mut [M31Var], log_size: u32) {
+    let n = positions.len();
+    for round in 0..n {
+        for i in (round % 2..n.saturating_sub(1)).step_by(2) {
+            let min = positions[i].min(&positions[i + 1], log_size as usize);
+            let max = &(&positions[i] + &positions[i + 1]) - &min;
+            positions[i] = min;
+            positions[i + 1] = max;
+        }
+    }
 }
 
 impl Index<u32> for QueryPositionsPerLogSizeVar {
@@ -69,71 +171,18 @@ impl PointCarryingQueryVar {
             cur = cur.double();
         }
 
-        let combs = steps
+        let selectors = bits.value[1..]
             .iter()
-            .zip(bits.value[1..].iter().rev())
-            .zip(bits.variables[1..].iter().rev())
+            .rev()
+            .zip_eq(bits.variables[1..].iter().rev())
+            .map(|(&bit_value, &bit_variable)| (bit_value, bit_variable))
             .collect_vec();
-
-        let mut cur = CirclePointM31Var::new_constant(&cs, &initial);
-        for chunk in combs.chunks(2) {
-            if chunk.len() == 1 {
-                let point =
-                    CirclePointM31Var::select(&cs, chunk[0].0 .0, *chunk[0].0 .1, *chunk[0].1);
-                cur = &point + &cur;
-            } else {
-                let p00 = CirclePoint::<M31>::zero();
-                let p01 = chunk[0].0 .0.clone();
-                let p10 = chunk[1].0 .0.clone();
-                let p11 = p01 + p10;
-
-                let value = match (*chunk[0].0 .1, *chunk[1].0 .1) {
-                    (false, false) => p00,
-                    (true, false) => p01,
-                    (false, true) => p10,
-                    (true, true) => p11,
-                };
-
-                let a = *chunk[0].1;
-                let b = *chunk[1].1;
-                let one_minus_a = cs.add(1, cs.mul_constant(a, M31::one().neg()));
-                let one_minus_b = cs.add(1, cs.mul_constant(b, M31::one().neg()));
-
-                let b00 = cs.mul(one_minus_a, one_minus_b);
-                let b01 = cs.mul(a, one_minus_b);
-                let b10 = cs.mul(one_minus_a, b);
-                let b11 = cs.mul(a, b);
-
-                let mut x = cs.mul_constant(b00, p00.x);
-                x = cs.add(x, cs.mul_constant(b01, p01.x));
-                x = cs.add(x, cs.mul_constant(b10, p10.x));
-                x = cs.add(x, cs.mul_constant(b11, p11.x));
-
-                let mut y = cs.mul_constant(b00, p00.y);
-                y = cs.add(y, cs.mul_constant(b01, p01.y));
-                y = cs.add(y, cs.mul_constant(b10, p10.y));
-                y = cs.add(y, cs.mul_constant(b11, p11.y));
-
-                let point = CirclePointM31Var {
-                    x: M31Var {
-                        cs: cs.clone(),
-                        value: value.x,
-                        variable: x,
-                    },
-                    y: M31Var {
-                        cs: cs.clone(),
-                        value: value.y,
-                        variable: y,
-                    },
-                };
-                cur = &point + &cur;
-            }
-        }
+        let point = CirclePointM31Var::sum_of_selected_constants(&cs, &initial, &steps, &selectors);
 
         PointCarryingQueryVar {
             bits,
             last_step: steps.last().unwrap().neg(),
-            point: cur,
+            point,
         }
     }
 
@@ -166,4 +215,191 @@ impl PointCarryingQueryVar {
     pub fn get_absolute_point(&self) -> CirclePointM31Var {
         self.point.clone()
     }
+
+    /// Folds the values at the query and its sibling in the first FRI layer, where the column is
+    /// a circle evaluation, with `alpha`.
+    pub fn fold_circle(
+        &self,
+        self_val: &QM31Var,
+        sibling_val: &QM31Var,
+        alpha: &QM31Var,
+    ) -> QM31Var {
+        let y_inv = self.get_absolute_point().double().y.inv();
+        self.fold(self_val, sibling_val, &y_inv, alpha)
+    }
+
+    /// Folds the values at the query and its sibling in an inner FRI layer, where the column is a
+    /// line evaluation, with `alpha`.
+    pub fn fold_line(&self, self_val: &QM31Var, sibling_val: &QM31Var, alpha: &QM31Var) -> QM31Var {
+        let x_inv = self.get_absolute_point().x.inv();
+        self.fold(self_val, sibling_val, &x_inv, alpha)
+    }
+
+    fn fold(
+        &self,
+        self_val: &QM31Var,
+        sibling_val: &QM31Var,
+        twiddle_inv: &M31Var,
+        alpha: &QM31Var,
+    ) -> QM31Var {
+        let (left_val, right_val) = QM31Var::swap(
+            self_val,
+            sibling_val,
+            self.bits.value[0],
+            self.bits.variables[0],
+        );
+
+        let new_left_val = &left_val + &right_val;
+        let new_right_val = &(&left_val - &right_val) * twiddle_inv;
+
+        &new_left_val + &(&new_right_val * alpha)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{sort_positions, QueryPositionsPerLogSizeVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use itertools::Itertools;
+    use std::collections::BTreeSet;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fri::FriConfig;
+    use stwo_prover::core::pcs::PcsConfig;
+    use stwo_prover::core::poly::circle::CanonicCoset;
+    use stwo_prover::core::utils::bit_reverse_index;
+
+    #[test]
+    fn test_query_positions_with_duplicates() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+
+        // 37 is at the same position as 5 at log size 5, and 12 meets 13 one log size below
+        let raw_queries = [5u32, 12, 37, 13, 5].map(|q| M31Var::new_witness(&cs, &M31::from(q)));
+        let queries = QueryPositionsPerLogSizeVar::new(3..=5, &raw_queries);
+
+        let values = |positions: &[M31Var]| positions.iter().map(|p| p.value.0).collect_vec();
+        assert_eq!(values(&queries.positions(5)), vec![5, 5, 5, 12, 13]);
+        assert_eq!(values(&queries.positions(4)), vec![2, 2, 2, 6, 6]);
+        assert_eq!(values(&queries.positions(3)), vec![1, 1, 1, 3, 3]);
+
+        let sorted = queries.sorted_positions(4);
+        assert_eq!(values(&sorted.positions), vec![2, 2, 2, 6, 6]);
+        assert_eq!(values(&sorted.is_first), vec![1, 0, 0, 1, 0]);
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_query_positions_from_pcs_config() {
+        let pcs_config = PcsConfig {
+            pow_bits: 0,
+            fri_config: FriConfig::new(1, 1, 6),
+        };
+        let raw_values = [3u32, 1000, 77, 1 << 20, 65, 12345];
+
+        // a single column log size, and column log sizes with a gap
+        for column_log_sizes in [BTreeSet::from([5]), BTreeSet::from([4, 8])] {
+            let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+            let raw_queries = raw_values.map(|q| M31Var::new_witness(&cs, &M31::from(q)));
+            let queries = QueryPositionsPerLogSizeVar::from_pcs_config(
+                pcs_config,
+                &column_log_sizes,
+                &raw_queries,
+            );
+
+            let max_log_size = *column_log_sizes.last().unwrap();
+            assert_eq!(queries.range, 3..=max_log_size);
+            assert_eq!(
+                queries.points.keys().copied().collect_vec(),
+                (3..=max_log_size).collect_vec()
+            );
+
+            for log_size in queries.range.clone() {
+                let domain = CanonicCoset::new(log_size).circle_domain();
+                let positions = queries.positions(log_size);
+                let mut expected_positions = raw_values
+                    .iter()
+                    .map(|&raw| {
+                        (raw as usize & ((1 << max_log_size) - 1)) >> (max_log_size - log_size)
+                    })
+                    .collect_vec();
+                expected_positions.sort_unstable();
+                for (i, query) in queries[log_size].iter().enumerate() {
+                    let expected = expected_positions[i];
+                    assert_eq!(positions[i].value.0 as usize, expected);
+                    assert_eq!(
+                        query.get_next_point().value(),
+                        domain.at(bit_reverse_index(expected, log_size))
+                    );
+                }
+
+                let sorted = queries.sorted_positions(log_size);
+                let mut expected = positions.iter().map(|p| p.value.0).collect_vec();
+                expected.sort_unstable();
+                assert_eq!(
+                    sorted.positions.iter().map(|p| p.value.0).collect_vec(),
+                    expected
+                );
+                expected.dedup();
+                assert_eq!(
+                    sorted
+                        .positions
+                        .iter()
+                        .zip(sorted.is_first.iter())
+                        .filter(|(_, is_first)| is_first.value.0 == 1)
+                        .map(|(p, _)| p.value.0)
+                        .collect_vec(),
+                    expected
+                );
+            }
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "columns must be larger than the last FRI layer")]
+    fn test_query_positions_range_below_last_layer() {
+        let pcs_config = PcsConfig {
+            pow_bits: 0,
+            fri_config: FriConfig::new(1, 1, 6),
+        };
+        let _ = QueryPositionsPerLogSizeVar::log_size_range(pcs_config, &BTreeSet::from([2, 6]));
+    }
+
+    #[test]
+    fn test_sort_positions_cost() {
+        let cs = ConstraintSystemRef::new_plonk_with_poseidon_ref();
+        let new_positions = |values: &[u32]| {
+            values
+                .iter()
+                .map(|&v| M31Var::new_witness(&cs, &M31::from(v)))
+                .collect_vec()
+        };
+
+        // a single comparator, once a first one has allocated the constants
+        sort_positions(&mut new_positions(&[3, 1]), 5);
+        let mut pair = new_positions(&[7, 2]);
+        let num_rows = cs.num_plonk_rows();
+        sort_positions(&mut pair, 5);
+        let comparator_rows = cs.num_plonk_rows() - num_rows;
+
+        // the network has n(n - 1)/2 comparators whatever the order of the positions
+        for values in [[9u32, 3, 27, 3, 14, 0, 31], [0, 1, 2, 3, 4, 5, 6]] {
+            let mut positions = new_positions(&values);
+            let num_rows = cs.num_plonk_rows();
+            sort_positions(&mut positions, 5);
+            assert_eq!(cs.num_plonk_rows() - num_rows, 7 * 6 / 2 * comparator_rows);
+
+            let mut expected = values.to_vec();
+            expected.sort_unstable();
+            assert_eq!(positions.iter().map(|p| p.value.0).collect_vec(), expected);
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
 }
@@ -0,0 +1,134 @@
+use crate::{Sha256DigestVar, Sha256HasherVar};
+use circle_plonk_dsl_channel::{ByteChannelVar, ByteHasherVar};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_uint::U8Var;
+
+impl ByteHasherVar for Sha256HasherVar {
+    type DigestVar = Sha256DigestVar;
+
+    fn hash_bytes(cs: &ConstraintSystemRef, bytes: &[U8Var]) -> Sha256DigestVar {
+        Self::hash(cs, bytes)
+    }
+
+    fn digest_to_bytes(digest: &Sha256DigestVar) -> Vec<U8Var> {
+        digest.to_bytes()
+    }
+}
+
+/// The in-circuit counterpart of stwo's SHA-256 channel.
+pub type Sha256ChannelVar = ByteChannelVar<Sha256HasherVar>;
+
+#[cfg(test)]
+mod test {
+    use crate::{Sha256ChannelVar, Sha256DigestVar};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::QM31Var;
+    use circle_plonk_dsl_uint::U32Var;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::channel::{Channel, MerkleChannel, Sha256Channel};
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::vcs::sha256_hash::Sha256Hash;
+    use stwo_prover::core::vcs::sha256_merkle::Sha256MerkleChannel;
+
+    #[test]
+    fn test_sha256_channel() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+        let mut channel = Sha256Channel::default();
+        let mut channel_var = Sha256ChannelVar::default(&cs);
+
+        let root: [u8; 32] = prng.gen();
+        Sha256MerkleChannel::mix_root(&mut channel, Sha256Hash(root));
+        channel_var.mix_root(&Sha256DigestVar::new_witness(&cs, &root));
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        let felts: [QM31; 3] = prng.gen();
+        channel.mix_felts(&felts);
+        channel_var.mix_felts(
+            &felts
+                .iter()
+                .map(|felt| QM31Var::new_witness(&cs, felt))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        assert_eq!(channel_var.draw_felt().value, channel.draw_felt());
+        for (a, b) in channel_var.draw_felts(3).iter().zip(channel.draw_felts(3)) {
+            assert_eq!(a.value, b);
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_sha256_channel_nonce_and_queries() {
+        const POW_BITS: u32 = 8;
+
+        let cs = ConstraintSystemRef::new_plonk_without_poseidon_ref();
+        let mut channel = Sha256Channel::default();
+        let mut channel_var = Sha256ChannelVar::default(&cs);
+
+        channel.mix_u64(12345);
+        channel_var.mix_u64(12345);
+        channel.mix_u32s(&[1, 2, 3]);
+        channel_var.mix_u32s(&[1u32, 2, 3].map(|w| U32Var::new_witness(&cs, &w)));
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        let nonce = (0u64..)
+            .find(|&nonce| {
+                let mut channel = channel.clone();
+                channel.mix_u64(nonce);
+                channel.trailing_zeros() >= POW_BITS
+            })
+            .unwrap();
+        channel.mix_u64(nonce);
+
+        let nonce_var = [nonce as u32, (nonce >> 32) as u32].map(|w| U32Var::new_witness(&cs, &w));
+        channel_var.mix_nonce(&nonce_var);
+        channel_var.verify_pow_nonce(POW_BITS);
+        assert_eq!(channel_var.digest.value, channel.digest().0);
+
+        // the queries are the lower bits of the little-endian words of the random bytes
+        let mut expected = vec![];
+        while expected.len() < 10 {
+            for chunk in channel.draw_random_bytes().chunks_exact(4) {
+                expected.push(u32::from_le_bytes(chunk.try_into().unwrap()) & ((1 << 20) - 1));
+            }
+        }
+        expected.truncate(10);
+
+        let queries = channel_var.draw_queries(10, 20);
+        assert_eq!(
+            queries
+                .iter()
+                .map(|query| query.get_value().0)
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        // the raw queries are the limbs of the felts, drawn two at a time
+        let mut expected = vec![];
+        while expected.len() < 10 {
+            for felt in channel.draw_felts(2) {
+                expected.extend(felt.to_m31_array());
+            }
+        }
+        expected.truncate(10);
+
+        let raw_queries = channel_var.draw_raw_queries(10);
+        assert_eq!(
+            raw_queries
+                .iter()
+                .map(|raw_query| raw_query.value)
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -0,0 +1,301 @@
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_uint::{U32Var, U8Var};
+use num_traits::One;
+use std::ops::Neg;
+use stwo_prover::core::fields::m31::M31;
+
+pub mod merkle;
+pub use merkle::*;
+
+pub mod channel;
+pub use channel::*;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// A SHA-256 digest, stored as the eight big-endian words of the final state.
+#[derive(Debug, Clone)]
+pub struct Sha256DigestVar {
+    pub value: [u8; 32],
+    pub words: [U32Var; 8],
+}
+
+impl Var for Sha256DigestVar {
+    type Value = [u8; 32];
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.words[0].cs()
+    }
+}
+
+impl AllocVar for Sha256DigestVar {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let words = std::array::from_fn(|i| {
+            let word = u32::from_be_bytes(value[i * 4..i * 4 + 4].try_into().unwrap());
+            U32Var::new_variables(cs, &word, mode)
+        });
+
+        Self {
+            value: *value,
+            words,
+        }
+    }
+}
+
+impl Sha256DigestVar {
+    pub fn from_words(words: [U32Var; 8]) -> Self {
+        let mut value = [0u8; 32];
+        for (chunk, word) in value.chunks_mut(4).zip(words.iter()) {
+            chunk.copy_from_slice(&word.value.to_be_bytes());
+        }
+
+        Self { value, words }
+    }
+
+    pub fn to_bytes(&self) -> Vec<U8Var> {
+        self.words
+            .iter()
+            .flat_map(|word| word.to_bytes_be())
+            .collect()
+    }
+
+    pub fn equalverify(&self, rhs: &Sha256DigestVar) {
+        for (a, b) in self.words.iter().zip(rhs.words.iter()) {
+            a.equalverify(b);
+        }
+    }
+
+    /// Returns `(a, b)` if the bit is zero, and `(b, a)` otherwise.
+    pub fn swap(
+        a: &Sha256DigestVar,
+        b: &Sha256DigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+    ) -> (Sha256DigestVar, Sha256DigestVar) {
+        let (left, right): (Vec<_>, Vec<_>) = a
+            .words
+            .iter()
+            .zip(b.words.iter())
+            .map(|(x, y)| U32Var::swap(x, y, bit_value, bit_variable))
+            .unzip();
+
+        (
+            Self::from_words(left.try_into().ok().unwrap()),
+            Self::from_words(right.try_into().ok().unwrap()),
+        )
+    }
+}
+
+pub struct Sha256HasherVar;
+
+impl Sha256HasherVar {
+    pub fn initial_state(cs: &ConstraintSystemRef) -> [U32Var; 8] {
+        std::array::from_fn(|i| U32Var::new_constant(cs, &IV[i]))
+    }
+
+    /// Applies the SHA-256 compression function to a block of sixteen big-endian words.
+    pub fn compress(state: &[U32Var; 8], block: &[U32Var; 16]) -> [U32Var; 8] {
+        let cs = state[0].cs().and(&block[0].cs());
+
+        let mut w = block.to_vec();
+        for t in 16..64 {
+            let s0 = xor3(
+                &w[t - 15].bits.rotate_right(7),
+                &w[t - 15].bits.rotate_right(18),
+                &w[t - 15].bits.shift_right(3),
+            );
+            let s1 = xor3(
+                &w[t - 2].bits.rotate_right(17),
+                &w[t - 2].bits.rotate_right(19),
+                &w[t - 2].bits.shift_right(10),
+            );
+            let next = BitsVar::sum_u32(&[&w[t - 16].bits, &s0, &w[t - 7].bits, &s1]);
+            w.push(U32Var::from_bits(&next));
+        }
+
+        let mut s = state.clone();
+        for (k, w) in K.iter().zip(w.iter()) {
+            let [a, b, c, d, e, f, g, h] = &s;
+
+            let big_s1 = xor3(
+                &e.bits.rotate_right(6),
+                &e.bits.rotate_right(11),
+                &e.bits.rotate_right(25),
+            );
+            let ch = ch(&e.bits, &f.bits, &g.bits);
+            let big_s0 = xor3(
+                &a.bits.rotate_right(2),
+                &a.bits.rotate_right(13),
+                &a.bits.rotate_right(22),
+            );
+            let maj = maj(&a.bits, &b.bits, &c.bits);
+            let k = U32Var::new_constant(&cs, k);
+
+            // e' = d + T1 and a' = T1 + T2 are each computed as a single multi-operand sum
+            let t1 = [&h.bits, &big_s1, &ch, &k.bits, &w.bits];
+            let mut new_e = vec![&d.bits];
+            new_e.extend_from_slice(&t1);
+            let mut new_a = t1.to_vec();
+            new_a.extend_from_slice(&[&big_s0, &maj]);
+
+            let new_a = U32Var::from_bits(&BitsVar::sum_u32(&new_a));
+            let new_e = U32Var::from_bits(&BitsVar::sum_u32(&new_e));
+
+            s = [
+                new_a,
+                a.clone(),
+                b.clone(),
+                c.clone(),
+                new_e,
+                e.clone(),
+                f.clone(),
+                g.clone(),
+            ];
+        }
+
+        std::array::from_fn(|i| state[i].wrapping_add(&s[i]))
+    }
+
+    /// Hashes a message whose length is fixed by the circuit.
+    pub fn hash(cs: &ConstraintSystemRef, message: &[U8Var]) -> Sha256DigestVar {
+        Self::hash_from_state(&Self::initial_state(cs), 0, message)
+    }
+
+    /// Continues hashing from an intermediate state after `absorbed_len` bytes, which must be a
+    /// multiple of the block size.
+    pub fn hash_from_state(
+        state: &[U32Var; 8],
+        absorbed_len: usize,
+        message: &[U8Var],
+    ) -> Sha256DigestVar {
+        assert_eq!(absorbed_len % 64, 0);
+        let cs = state[0].cs();
+
+        let mut bytes = message.to_vec();
+
+        // padding: a single one bit, zeros, and the bit length as a big-endian u64
+        let bit_len = ((absorbed_len + message.len()) as u64) * 8;
+        bytes.push(U8Var::new_constant(&cs, &0x80));
+        while bytes.len() % 64 != 56 {
+            bytes.push(U8Var::new_constant(&cs, &0));
+        }
+        for byte in bit_len.to_be_bytes() {
+            bytes.push(U8Var::new_constant(&cs, &byte));
+        }
+
+        let mut state = state.clone();
+        for chunk in bytes.chunks(64) {
+            let block = std::array::from_fn(|i| U32Var::from_bytes_be(&chunk[i * 4..i * 4 + 4]));
+            state = Self::compress(&state, &block);
+        }
+
+        Sha256DigestVar::from_words(state)
+    }
+}
+
+fn xor3(a: &BitsVar, b: &BitsVar, c: &BitsVar) -> BitsVar {
+    a.xor(b).xor(c)
+}
+
+/// Computes `(e and f) xor (not e and g)` as `g + e * (f - g)`, in four rows per bit.
+fn ch(e: &BitsVar, f: &BitsVar, g: &BitsVar) -> BitsVar {
+    let cs = e.cs().and(&f.cs()).and(&g.cs());
+
+    let mut value = Vec::with_capacity(32);
+    let mut variables = Vec::with_capacity(32);
+    for i in 0..32 {
+        value.push(if e.value[i] { f.value[i] } else { g.value[i] });
+
+        let neg_g = cs.mul_constant(g.variables[i], M31::one().neg());
+        let diff = cs.add(f.variables[i], neg_g);
+        let product = cs.mul(e.variables[i], diff);
+        variables.push(cs.add(product, g.variables[i]));
+    }
+
+    BitsVar {
+        cs,
+        value,
+        variables,
+    }
+}
+
+/// Computes the majority of three bits as `ab + c * (a xor b)`, in five rows per bit.
+fn maj(a: &BitsVar, b: &BitsVar, c: &BitsVar) -> BitsVar {
+    let cs = a.cs().and(&b.cs()).and(&c.cs());
+
+    let mut value = Vec::with_capacity(32);
+    let mut variables = Vec::with_capacity(32);
+    for i in 0..32 {
+        value.push(
+            (a.value[i] & b.value[i]) | (a.value[i] & c.value[i]) | (b.value[i] & c.value[i]),
+        );
+
+        let product = cs.mul(a.variables[i], b.variables[i]);
+        // with op = -1, the gate computes 2ab - (a + b), which is the negated xor
+        let neg_xor = cs.do_arith_gate(a.variables[i], b.variables[i], M31::one().neg());
+        let t = cs.mul(c.variables[i], neg_xor);
+        let neg_t = cs.mul_constant(t, M31::one().neg());
+        variables.push(cs.add(product, neg_t));
+    }
+
+    BitsVar {
+        cs,
+        value,
+        variables,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Sha256HasherVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_uint::U8Var;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_sha256() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            // the empty message, a single block, and padding that spills into a second block
+            for len in [0, 3, 60] {
+                let message = (0..len).map(|_| prng.gen()).collect::<Vec<u8>>();
+                let message_var = message
+                    .iter()
+                    .map(|b| U8Var::new_witness(&cs, b))
+                    .collect::<Vec<_>>();
+
+                let digest = Sha256HasherVar::hash(&cs, &message_var);
+                let expected = Sha256::digest(&message);
+                assert_eq!(digest.value.as_slice(), expected.as_slice());
+
+                for (byte, &expected) in digest.to_bytes().iter().zip(expected.iter()) {
+                    assert_eq!(byte.value, expected);
+                }
+            }
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+}
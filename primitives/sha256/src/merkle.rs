@@ -0,0 +1,782 @@
+use crate::{Sha256DigestVar, Sha256HasherVar, IV};
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use circle_plonk_dsl_merkle::{
+    verify_merkle_multi_proof, verify_single_pair_merkle_proof, verify_single_path_merkle_proof,
+    MerkleColumnVar, MerkleHasherVar, Poseidon31MerkleHasherVar,
+};
+use circle_plonk_dsl_uint::{U32Var, U8Var};
+use num_traits::Zero;
+use sha2::digest::generic_array::GenericArray;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::marker::PhantomData;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
+use stwo_prover::core::vcs::ops::MerkleHasher;
+use stwo_prover::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
+use stwo_prover::core::vcs::prover::MerkleDecommitment;
+use stwo_prover::core::vcs::sha256_hash::Sha256Hash;
+use stwo_prover::core::vcs::sha256_merkle::Sha256MerkleHasher;
+use stwo_prover::core::vcs::sha256_poseidon31_merkle::Sha256Poseidon31MerkleHasher;
+
+/// A Merkle hasher of stwo that hashes a node with SHA-256, over the children hashes followed by
+/// the bytes of its columns, so that the path and pair proofs below serve all such hashers.
+pub trait Sha256BasedMerkleHasher: MerkleHasher<Hash = Sha256Hash> {
+    type HasherVar: MerkleHasherVar<HashVar = Sha256DigestVar>;
+
+    /// Returns the bytes that a node with these column values hashes after the children hashes.
+    fn column_bytes(columns: &[M31]) -> Vec<u8>;
+}
+
+impl Sha256BasedMerkleHasher for Sha256MerkleHasher {
+    type HasherVar = Sha256MerkleHasherVar;
+
+    fn column_bytes(columns: &[M31]) -> Vec<u8> {
+        columns.iter().flat_map(|v| v.0.to_le_bytes()).collect()
+    }
+}
+
+impl Sha256BasedMerkleHasher for Sha256Poseidon31MerkleHasher {
+    type HasherVar = Sha256Poseidon31MerkleHasherVar;
+
+    fn column_bytes(columns: &[M31]) -> Vec<u8> {
+        hybrid_columns(columns)
+            .iter()
+            .flat_map(|v| v.0.to_le_bytes())
+            .collect()
+    }
+}
+
+/// Compresses the column values of a node for `Sha256Poseidon31MerkleHasher`: up to eight values
+/// are zero-padded into one or two QM31 elements, and more values are replaced by their Poseidon31
+/// hash.
+pub fn hybrid_columns(columns: &[M31]) -> Vec<M31> {
+    if columns.len() > 8 {
+        Poseidon31MerkleHasher::hash_column_get_rate(columns)
+            .0
+            .to_vec()
+    } else {
+        let mut values = columns.to_vec();
+        values.resize(columns.len().div_ceil(4) * 4, M31::zero());
+        values
+    }
+}
+
+/// Returns the SHA-256 state after absorbing the children hashes, which form exactly one block.
+///
+/// The hash of a node with columns is then obtained by continuing from this state, so that a
+/// sibling with columns can be decommitted with this state in place of its hash.
+pub fn hash_children_get_state(left: &Sha256Hash, right: &Sha256Hash) -> Sha256Hash {
+    let mut block = left.0.to_vec();
+    block.extend_from_slice(&right.0);
+
+    let mut state = IV;
+    sha2::compress256(&mut state, &[GenericArray::clone_from_slice(&block)]);
+
+    let mut res = [0u8; 32];
+    for (chunk, word) in res.chunks_mut(4).zip(state.iter()) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    Sha256Hash(res)
+}
+
+/// Finishes the hash of a node with columns from the state after its children.
+pub fn hash_columns_from_state<H: Sha256BasedMerkleHasher>(
+    state: &Sha256Hash,
+    columns: &[M31],
+) -> Sha256Hash {
+    let mut state: [u32; 8] =
+        std::array::from_fn(|i| u32::from_be_bytes(state.0[i * 4..i * 4 + 4].try_into().unwrap()));
+
+    let mut bytes = H::column_bytes(columns);
+    let bit_len = ((64 + bytes.len()) as u64) * 8;
+    bytes.push(0x80);
+    while bytes.len() % 64 != 56 {
+        bytes.push(0);
+    }
+    bytes.extend_from_slice(&bit_len.to_be_bytes());
+
+    for chunk in bytes.chunks(64) {
+        sha2::compress256(&mut state, &[GenericArray::clone_from_slice(chunk)]);
+    }
+
+    let mut res = [0u8; 32];
+    for (chunk, word) in res.chunks_mut(4).zip(state.iter()) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    Sha256Hash(res)
+}
+
+/// The SHA-256 Merkle hasher, which hashes the children hashes followed by the column values,
+/// each as a little-endian u32.
+pub struct Sha256MerkleHasherVar;
+
+impl MerkleHasherVar for Sha256MerkleHasherVar {
+    type HashVar = Sha256DigestVar;
+
+    fn hash_value(hash: &Sha256DigestVar) -> [u8; 32] {
+        hash.value
+    }
+
+    fn hash_node<C: MerkleColumnVar>(
+        cs: &ConstraintSystemRef,
+        children: Option<(&Sha256DigestVar, &Sha256DigestVar)>,
+        columns: &[C],
+    ) -> Sha256DigestVar {
+        hash_node_with_column_bytes(cs, children, column_bytes(columns))
+    }
+
+    fn hash_node_with_swap<C: MerkleColumnVar>(
+        cur: &Sha256DigestVar,
+        sibling: &Sha256DigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+        columns: &[C],
+    ) -> Sha256DigestVar {
+        let (left, right) = Sha256DigestVar::swap(cur, sibling, bit_value, bit_variable);
+        Self::hash_node(&cur.cs(), Some((&left, &right)), columns)
+    }
+
+    /// The digest of the children is the state after them, as computed by
+    /// `hash_children_get_state`.
+    fn hash_node_from_children_digest<C: MerkleColumnVar>(
+        children_digest: &Sha256DigestVar,
+        columns: &[C],
+    ) -> Sha256DigestVar {
+        Sha256HasherVar::hash_from_state(&children_digest.words, 64, &column_bytes(columns))
+    }
+
+    fn select(
+        a: &Sha256DigestVar,
+        b: &Sha256DigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+    ) -> Sha256DigestVar {
+        Sha256DigestVar::swap(a, b, bit_value, bit_variable).0
+    }
+
+    fn equalverify(lhs: &Sha256DigestVar, rhs: &Sha256DigestVar) {
+        lhs.equalverify(rhs)
+    }
+}
+
+/// The hybrid Merkle hasher of the last layer, which hashes the children hashes with SHA-256
+/// followed by the column values compressed as in `hybrid_columns`.
+pub struct Sha256Poseidon31MerkleHasherVar;
+
+impl MerkleHasherVar for Sha256Poseidon31MerkleHasherVar {
+    type HashVar = Sha256DigestVar;
+
+    fn hash_value(hash: &Sha256DigestVar) -> [u8; 32] {
+        hash.value
+    }
+
+    fn hash_node<C: MerkleColumnVar>(
+        cs: &ConstraintSystemRef,
+        children: Option<(&Sha256DigestVar, &Sha256DigestVar)>,
+        columns: &[C],
+    ) -> Sha256DigestVar {
+        hash_node_with_column_bytes(cs, children, hybrid_column_bytes(columns))
+    }
+
+    fn hash_node_with_swap<C: MerkleColumnVar>(
+        cur: &Sha256DigestVar,
+        sibling: &Sha256DigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+        columns: &[C],
+    ) -> Sha256DigestVar {
+        let (left, right) = Sha256DigestVar::swap(cur, sibling, bit_value, bit_variable);
+        Self::hash_node(&cur.cs(), Some((&left, &right)), columns)
+    }
+
+    /// The digest of the children is the state after them, as computed by
+    /// `hash_children_get_state`.
+    fn hash_node_from_children_digest<C: MerkleColumnVar>(
+        children_digest: &Sha256DigestVar,
+        columns: &[C],
+    ) -> Sha256DigestVar {
+        Sha256HasherVar::hash_from_state(&children_digest.words, 64, &hybrid_column_bytes(columns))
+    }
+
+    fn select(
+        a: &Sha256DigestVar,
+        b: &Sha256DigestVar,
+        bit_value: bool,
+        bit_variable: usize,
+    ) -> Sha256DigestVar {
+        Sha256DigestVar::swap(a, b, bit_value, bit_variable).0
+    }
+
+    fn equalverify(lhs: &Sha256DigestVar, rhs: &Sha256DigestVar) {
+        lhs.equalverify(rhs)
+    }
+}
+
+fn hash_node_with_column_bytes(
+    cs: &ConstraintSystemRef,
+    children: Option<(&Sha256DigestVar, &Sha256DigestVar)>,
+    column_bytes: Vec<U8Var>,
+) -> Sha256DigestVar {
+    let mut bytes = vec![];
+    if let Some((left, right)) = children {
+        bytes.extend(left.to_bytes());
+        bytes.extend(right.to_bytes());
+    }
+    bytes.extend(column_bytes);
+    Sha256HasherVar::hash(cs, &bytes)
+}
+
+fn column_bytes<C: MerkleColumnVar>(columns: &[C]) -> Vec<U8Var> {
+    words_to_bytes(&C::to_m31(columns))
+}
+
+fn hybrid_column_bytes<C: MerkleColumnVar>(columns: &[C]) -> Vec<U8Var> {
+    let values = C::to_m31(columns);
+    if values.len() > 8 {
+        let hash = Poseidon31MerkleHasherVar::hash_m31_columns_get_rate(&values);
+        let words = hash
+            .to_qm31()
+            .iter()
+            .flat_map(|v| v.decompose_m31())
+            .collect::<Vec<_>>();
+        words_to_bytes(&words)
+    } else if values.is_empty() {
+        vec![]
+    } else {
+        let cs = values[0].cs();
+        let mut values = values;
+        values.resize(values.len().div_ceil(4) * 4, M31Var::zero(&cs));
+        words_to_bytes(&values)
+    }
+}
+
+fn words_to_bytes(words: &[M31Var]) -> Vec<U8Var> {
+    words
+        .iter()
+        .flat_map(|v| U32Var::from_m31(v).to_bytes_le())
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha256SinglePathMerkleProof<H: Sha256BasedMerkleHasher = Sha256MerkleHasher> {
+    pub query: usize,
+
+    pub sibling_hashes: Vec<Sha256Hash>,
+    pub columns: BTreeMap<usize, Vec<M31>>,
+
+    pub root: Sha256Hash,
+    pub depth: usize,
+
+    pub phantom: PhantomData<H>,
+}
+
+impl<H: Sha256BasedMerkleHasher> Sha256SinglePathMerkleProof<H> {
+    pub fn verify(&self) {
+        let mut cur_hash = H::hash_node(None, self.columns.get(&self.depth).unwrap_or(&vec![]));
+
+        for i in 0..self.depth {
+            let h = self.depth - i - 1;
+
+            cur_hash = H::hash_node(
+                if (self.query >> i) & 1 == 0 {
+                    Some((cur_hash, self.sibling_hashes[i]))
+                } else {
+                    Some((self.sibling_hashes[i], cur_hash))
+                },
+                self.columns.get(&h).unwrap_or(&vec![]),
+            );
+        }
+
+        assert_eq!(cur_hash, self.root);
+    }
+
+    /// Splits a decommitment of stwo into one path per query, in the order of `raw_queries`,
+    /// which may repeat.
+    pub fn from_stwo_proof(
+        max_log_size: u32,
+        raw_queries: &[usize],
+        values: &[M31],
+        root: Sha256Hash,
+        n_columns_per_log_size: &BTreeMap<u32, usize>,
+        merkle_decommitment: &MerkleDecommitment<H>,
+    ) -> Vec<Self> {
+        // find out all the queried positions and sort them
+        let mut queries = raw_queries.to_vec();
+        queries.sort_unstable();
+        queries.dedup();
+
+        let mut value_iterator = values.iter();
+
+        let mut queries_values_map = HashMap::new();
+        for &query in queries.iter() {
+            let mut v = vec![];
+            for _ in 0..*n_columns_per_log_size.get(&max_log_size).unwrap() {
+                v.push(*value_iterator.next().unwrap());
+            }
+            queries_values_map.insert(query, v);
+        }
+
+        // require the column witness to be empty
+        // (all the values are provided)
+        assert_eq!(merkle_decommitment.column_witness.len(), 0);
+
+        let mut hash_iterator = merkle_decommitment.hash_witness.iter();
+
+        // the leaf layer, followed by the nodes of each layer that the proof touches
+        let mut hash_layers: Vec<HashMap<usize, Sha256Hash>> = vec![];
+        let mut hash_layer = HashMap::new();
+        for (&query, value) in queries_values_map.iter() {
+            hash_layer.insert(query, H::hash_node(None, value));
+        }
+        hash_layers.push(hash_layer);
+
+        let mut positions = queries.clone();
+        let mut column_layers: Vec<HashMap<usize, Vec<M31>>> = vec![];
+        for i in 0..max_log_size as usize {
+            let mut layer = HashMap::new();
+            let mut parents = BTreeSet::new();
+            let mut column_layer = HashMap::new();
+
+            for &position in positions.iter() {
+                if layer.contains_key(&(position >> 1)) {
+                    continue;
+                }
+
+                let columns = if let Some(&num_columns) =
+                    n_columns_per_log_size.get(&(max_log_size - 1 - i as u32))
+                {
+                    (0..num_columns)
+                        .map(|_| *value_iterator.next().unwrap())
+                        .collect()
+                } else {
+                    vec![]
+                };
+
+                let sibling = match hash_layers[i].get(&(position ^ 1)) {
+                    Some(&sibling) => sibling,
+                    None => {
+                        let sibling = *hash_iterator.next().unwrap();
+                        hash_layers[i].insert(position ^ 1, sibling);
+                        sibling
+                    }
+                };
+                let cur = hash_layers[i][&position];
+                let children = if position & 1 == 0 {
+                    (cur, sibling)
+                } else {
+                    (sibling, cur)
+                };
+
+                layer.insert(position >> 1, H::hash_node(Some(children), &columns));
+                column_layer.insert(position >> 1, columns);
+                parents.insert(position >> 1);
+            }
+
+            column_layers.push(column_layer);
+            hash_layers.push(layer);
+            positions = parents.into_iter().collect();
+        }
+
+        assert_eq!(hash_iterator.next(), None);
+        assert_eq!(value_iterator.next(), None);
+        assert_eq!(hash_layers[max_log_size as usize][&0], root);
+
+        // cherry-pick the paths of the queries
+        let mut res = vec![];
+        for &query in raw_queries.iter() {
+            let mut sibling_hashes = vec![];
+            let mut cur = query;
+            for layer in hash_layers.iter().take(max_log_size as usize) {
+                sibling_hashes.push(layer[&(cur ^ 1)]);
+                cur >>= 1;
+            }
+
+            let mut columns = BTreeMap::new();
+            columns.insert(max_log_size as usize, queries_values_map[&query].clone());
+
+            let mut cur = query >> 1;
+            for (i, layer) in column_layers.iter().enumerate() {
+                let data = &layer[&cur];
+                if !data.is_empty() {
+                    columns.insert(max_log_size as usize - i - 1, data.clone());
+                }
+                cur >>= 1;
+            }
+
+            res.push(Self {
+                query,
+                sibling_hashes,
+                columns,
+                root,
+                depth: max_log_size as usize,
+                phantom: PhantomData,
+            });
+        }
+        res
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha256SinglePathMerkleProofVar<H: Sha256BasedMerkleHasher = Sha256MerkleHasher> {
+    pub cs: ConstraintSystemRef,
+    pub value: Sha256SinglePathMerkleProof<H>,
+    pub sibling_hashes: Vec<Sha256DigestVar>,
+    pub columns: BTreeMap<usize, Vec<M31Var>>,
+}
+
+impl<H: Sha256BasedMerkleHasher> Var for Sha256SinglePathMerkleProofVar<H> {
+    type Value = Sha256SinglePathMerkleProof<H>;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl<H: Sha256BasedMerkleHasher> AllocVar for Sha256SinglePathMerkleProofVar<H> {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let mut sibling_hashes = vec![];
+        for sibling_hash in value.sibling_hashes.iter() {
+            sibling_hashes.push(Sha256DigestVar::new_variables(cs, &sibling_hash.0, mode));
+        }
+
+        let mut columns = BTreeMap::new();
+        for (k, v) in value.columns.iter() {
+            let mut v_var = vec![];
+            for vv in v.iter() {
+                v_var.push(M31Var::new_variables(cs, vv, mode));
+            }
+            columns.insert(*k, v_var);
+        }
+
+        Self {
+            cs: cs.clone(),
+            value: value.clone(),
+            sibling_hashes,
+            columns,
+        }
+    }
+}
+
+impl<H: Sha256BasedMerkleHasher> Sha256SinglePathMerkleProofVar<H> {
+    pub fn new(cs: &ConstraintSystemRef, value: &Sha256SinglePathMerkleProof<H>) -> Self {
+        Self::new_witness(cs, value)
+    }
+
+    pub fn get_values(&self) -> &BTreeMap<usize, Vec<M31Var>> {
+        &self.columns
+    }
+
+    pub fn verify(&self, root: &Sha256DigestVar, query: &BitsVar) {
+        // verify that the Merkle proof is valid
+        self.value.verify();
+        assert_eq!(root.value, self.value.root.0);
+        assert_eq!(query.get_value().0, self.value.query as u32);
+
+        verify_single_path_merkle_proof::<H::HasherVar>(
+            root,
+            query,
+            self.value.depth,
+            &self.sibling_hashes,
+            &self.columns,
+        );
+    }
+
+    /// Verifies the proofs of all the queries together with `verify_merkle_multi_proof`, where
+    /// the proofs are in the order of the queries, which are in increasing order.
+    pub fn verify_all(proofs: &[Self], root: &Sha256DigestVar, queries: &[BitsVar]) {
+        assert_eq!(proofs.len(), queries.len());
+        for (proof, query) in proofs.iter().zip(queries.iter()) {
+            proof.value.verify();
+            assert_eq!(root.value, proof.value.root.0);
+            assert_eq!(query.get_value().0, proof.value.query as u32);
+        }
+
+        let depth = proofs[0].value.depth;
+        let sibling_hashes = proofs
+            .iter()
+            .map(|proof| {
+                proof
+                    .sibling_hashes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, hash)| (depth - i, hash.clone()))
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+        let columns = proofs
+            .iter()
+            .map(|proof| proof.columns.clone())
+            .collect::<Vec<_>>();
+
+        verify_merkle_multi_proof::<H::HasherVar, M31Var>(
+            root,
+            queries,
+            depth,
+            &sibling_hashes,
+            &columns,
+            &vec![BTreeMap::new(); proofs.len()],
+        );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha256SinglePairMerkleProof<H: Sha256BasedMerkleHasher = Sha256MerkleHasher> {
+    pub query: usize,
+
+    /// For a layer where the sibling has columns, the entry is the state after the sibling's
+    /// children, as computed by `hash_children_get_state`. The root has no sibling, so there is
+    /// no entry for it unless it has columns.
+    pub sibling_hashes: Vec<Sha256Hash>,
+    pub self_columns: BTreeMap<usize, QM31>,
+    pub siblings_columns: BTreeMap<usize, QM31>,
+
+    pub root: Sha256Hash,
+    pub depth: usize,
+
+    pub phantom: PhantomData<H>,
+}
+
+impl<H: Sha256BasedMerkleHasher> Sha256SinglePairMerkleProof<H> {
+    pub fn verify(&self) {
+        let mut self_hash = H::hash_node(
+            None,
+            &self
+                .self_columns
+                .get(&self.depth)
+                .map_or(vec![], |v| v.to_m31_array().to_vec()),
+        );
+        let mut sibling_hash = H::hash_node(
+            None,
+            &self
+                .siblings_columns
+                .get(&self.depth)
+                .map_or(vec![], |v| v.to_m31_array().to_vec()),
+        );
+
+        for i in 0..self.depth {
+            let h = self.depth - i - 1;
+
+            let children = if (self.query >> i) & 1 == 0 {
+                Some((self_hash, sibling_hash))
+            } else {
+                Some((sibling_hash, self_hash))
+            };
+
+            if !self.self_columns.contains_key(&h) {
+                self_hash = H::hash_node(children, &[]);
+                if i != self.depth - 1 {
+                    sibling_hash = self.sibling_hashes[i];
+                }
+            } else {
+                self_hash =
+                    H::hash_node(children, &self.self_columns.get(&h).unwrap().to_m31_array());
+                sibling_hash = hash_columns_from_state::<H>(
+                    &self.sibling_hashes[i],
+                    &self.siblings_columns.get(&h).unwrap().to_m31_array(),
+                );
+            }
+        }
+
+        assert_eq!(self_hash, self.root);
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha256SinglePairMerkleProofVar<H: Sha256BasedMerkleHasher = Sha256MerkleHasher> {
+    pub cs: ConstraintSystemRef,
+    pub value: Sha256SinglePairMerkleProof<H>,
+    pub sibling_hashes: Vec<Sha256DigestVar>,
+    pub self_columns: BTreeMap<usize, QM31Var>,
+    pub siblings_columns: BTreeMap<usize, QM31Var>,
+}
+
+impl<H: Sha256BasedMerkleHasher> Var for Sha256SinglePairMerkleProofVar<H> {
+    type Value = Sha256SinglePairMerkleProof<H>;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl<H: Sha256BasedMerkleHasher> AllocVar for Sha256SinglePairMerkleProofVar<H> {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let mut sibling_hashes = vec![];
+        for sibling_hash in value.sibling_hashes.iter() {
+            sibling_hashes.push(Sha256DigestVar::new_variables(cs, &sibling_hash.0, mode));
+        }
+
+        let mut self_columns = BTreeMap::new();
+        for (k, v) in value.self_columns.iter() {
+            self_columns.insert(*k, QM31Var::new_variables(cs, v, mode));
+        }
+
+        let mut siblings_columns = BTreeMap::new();
+        for (k, v) in value.siblings_columns.iter() {
+            siblings_columns.insert(*k, QM31Var::new_variables(cs, v, mode));
+        }
+
+        Self {
+            cs: cs.clone(),
+            value: value.clone(),
+            sibling_hashes,
+            self_columns,
+            siblings_columns,
+        }
+    }
+}
+
+impl<H: Sha256BasedMerkleHasher> Sha256SinglePairMerkleProofVar<H> {
+    pub fn new(cs: &ConstraintSystemRef, value: &Sha256SinglePairMerkleProof<H>) -> Self {
+        Self::new_witness(cs, value)
+    }
+
+    pub fn verify(&self, root: &Sha256DigestVar, query: &BitsVar) {
+        // verify that the Merkle proof is valid
+        self.value.verify();
+        assert_eq!(root.value, self.value.root.0);
+        assert_eq!(query.get_value().0, self.value.query as u32);
+
+        verify_single_pair_merkle_proof::<H::HasherVar>(
+            root,
+            query,
+            self.value.depth,
+            &self.sibling_hashes,
+            &self.self_columns,
+            &self.siblings_columns,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        hash_children_get_state, Sha256BasedMerkleHasher, Sha256DigestVar,
+        Sha256SinglePairMerkleProof, Sha256SinglePairMerkleProofVar, Sha256SinglePathMerkleProof,
+        Sha256SinglePathMerkleProofVar,
+    };
+    use circle_plonk_dsl_bits::BitsVar;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+    use std::marker::PhantomData;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::vcs::sha256_hash::Sha256Hash;
+    use stwo_prover::core::vcs::sha256_merkle::Sha256MerkleHasher;
+    use stwo_prover::core::vcs::sha256_poseidon31_merkle::Sha256Poseidon31MerkleHasher;
+
+    fn check_merkle_proofs<H: Sha256BasedMerkleHasher>(n_leaf_columns: usize) {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        // a tree of depth 3 with columns at the leaves and one column at the layer above
+        let depth = 3;
+        let leaves: Vec<Vec<M31>> = (0..8)
+            .map(|_| (0..n_leaf_columns).map(|_| prng.gen()).collect())
+            .collect();
+        let middle: Vec<Vec<M31>> = (0..4).map(|_| vec![prng.gen()]).collect();
+
+        let mut layers: Vec<Vec<Sha256Hash>> =
+            vec![leaves.iter().map(|v| H::hash_node(None, v)).collect()];
+        for h in (0..depth).rev() {
+            let prev = layers.last().unwrap();
+            let layer = (0..1 << h)
+                .map(|j| {
+                    let columns = if h == 2 { middle[j].clone() } else { vec![] };
+                    H::hash_node(Some((prev[2 * j], prev[2 * j + 1])), &columns)
+                })
+                .collect();
+            layers.push(layer);
+        }
+        let root = layers[depth][0];
+
+        let query = 5;
+        let path_proof = Sha256SinglePathMerkleProof::<H> {
+            query,
+            sibling_hashes: (0..depth).map(|i| layers[i][(query >> i) ^ 1]).collect(),
+            columns: BTreeMap::from([(3, leaves[query].clone()), (2, middle[query >> 1].clone())]),
+            root,
+            depth,
+            phantom: PhantomData,
+        };
+
+        // a pair of QM31 leaves, and a QM31 column in the layer above
+        let qm31_leaves: Vec<QM31> = (0..8).map(|_| prng.gen()).collect();
+        let qm31_middle: Vec<QM31> = (0..4).map(|_| prng.gen()).collect();
+        let mut layers: Vec<Vec<Sha256Hash>> = vec![qm31_leaves
+            .iter()
+            .map(|v| H::hash_node(None, &v.to_m31_array()))
+            .collect()];
+        let mut states = vec![];
+        for h in (0..depth).rev() {
+            let prev = layers.last().unwrap();
+            states.push(
+                (0..1 << h)
+                    .map(|j| hash_children_get_state(&prev[2 * j], &prev[2 * j + 1]))
+                    .collect::<Vec<_>>(),
+            );
+            let layer = (0..1 << h)
+                .map(|j| {
+                    let columns = if h == 2 {
+                        qm31_middle[j].to_m31_array().to_vec()
+                    } else {
+                        vec![]
+                    };
+                    H::hash_node(Some((prev[2 * j], prev[2 * j + 1])), &columns)
+                })
+                .collect();
+            layers.push(layer);
+        }
+        let pair_root = layers[depth][0];
+
+        // the leaves are hashed from the columns, and the root has no sibling
+        let pair_proof = Sha256SinglePairMerkleProof::<H> {
+            query,
+            sibling_hashes: vec![states[0][(query >> 1) ^ 1], layers[2][(query >> 2) ^ 1]],
+            self_columns: BTreeMap::from([(3, qm31_leaves[query]), (2, qm31_middle[query >> 1])]),
+            siblings_columns: BTreeMap::from([
+                (3, qm31_leaves[query ^ 1]),
+                (2, qm31_middle[(query >> 1) ^ 1]),
+            ]),
+            root: pair_root,
+            depth,
+            phantom: PhantomData,
+        };
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            let query_bits = (0..depth)
+                .map(|i| (query >> i) & 1 != 0)
+                .collect::<Vec<_>>();
+            let query_var = BitsVar::new_witness(&cs, &query_bits);
+
+            let root_var = Sha256DigestVar::new_witness(&cs, &root.0);
+            Sha256SinglePathMerkleProofVar::new(&cs, &path_proof).verify(&root_var, &query_var);
+
+            let pair_root_var = Sha256DigestVar::new_witness(&cs, &pair_root.0);
+            Sha256SinglePairMerkleProofVar::new(&cs, &pair_proof)
+                .verify(&pair_root_var, &query_var);
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+
+    #[test]
+    fn test_sha256_merkle_proofs() {
+        check_merkle_proofs::<Sha256MerkleHasher>(4);
+    }
+
+    #[test]
+    fn test_sha256_poseidon31_merkle_proofs() {
+        // five leaf columns are padded to eight, and ten are hashed with Poseidon31
+        check_merkle_proofs::<Sha256Poseidon31MerkleHasher>(5);
+        check_merkle_proofs::<Sha256Poseidon31MerkleHasher>(10);
+    }
+}
@@ -0,0 +1,99 @@
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::M31Var;
+use stwo_prover::core::fields::m31::M31;
+
+pub mod uint8;
+pub use uint8::*;
+
+pub mod uint32;
+pub use uint32::*;
+
+/// Bits of a constant, which directly use the reserved zero and one variables.
+pub(crate) fn constant_bits(cs: &ConstraintSystemRef, bools: Vec<bool>) -> BitsVar {
+    let variables = bools.iter().map(|&b| b as usize).collect();
+    BitsVar {
+        cs: cs.clone(),
+        value: bools,
+        variables,
+    }
+}
+
+pub(crate) fn concat_bits(lo: &BitsVar, hi: &BitsVar) -> BitsVar {
+    let mut value = lo.value.clone();
+    value.extend_from_slice(&hi.value);
+    let mut variables = lo.variables.clone();
+    variables.extend_from_slice(&hi.variables);
+
+    BitsVar {
+        cs: lo.cs.and(&hi.cs),
+        value,
+        variables,
+    }
+}
+
+/// Returns the bits of `a - b` modulo `2^(limb_bits * n)`, where `a` and `b` are given as `n`
+/// little-endian limbs of `limb_bits` bits, and a bit indicating whether the subtraction borrows.
+pub(crate) fn overflowing_sub_limbs(
+    a: &[M31Var],
+    b: &[M31Var],
+    limb_bits: usize,
+) -> (BitsVar, M31Var) {
+    assert_eq!(a.len(), b.len());
+    let cs = a[0].cs().and(&b[0].cs());
+
+    // a_i - b_i + 2^limb_bits - 1 plus the carry from the limb below has its top bit set iff the
+    // limb does not borrow, where the lowest limb takes a carry of one
+    let shift = M31Var::new_constant(&cs, &M31::from(1 << limb_bits));
+    let shift_minus_one = M31Var::new_constant(&cs, &M31::from((1 << limb_bits) - 1));
+
+    let mut bits: Option<BitsVar> = None;
+    let mut carry: Option<M31Var> = None;
+    for (a, b) in a.iter().zip(b.iter()) {
+        let diff = match carry {
+            None => &(a - b) + &shift,
+            Some(carry) => &(&(a - b) + &shift_minus_one) + &carry,
+        };
+
+        let diff_bits = BitsVar::from_m31(&diff, limb_bits + 1);
+        carry = Some(diff_bits.get_bit(limb_bits));
+        bits = Some(match bits {
+            None => diff_bits.index_range(0..limb_bits),
+            Some(bits) => concat_bits(&bits, &diff_bits.index_range(0..limb_bits)),
+        });
+    }
+
+    let borrow = &M31Var::one(&cs) - &carry.unwrap();
+    (bits.unwrap(), borrow)
+}
+
+/// Returns the bits of `a * b` modulo `2^(8 * n)`, where `a` and `b` are given as `n <= 4`
+/// little-endian 8-bit limbs.
+pub(crate) fn wrapping_mul_limbs(a: &[M31Var], b: &[M31Var]) -> BitsVar {
+    assert_eq!(a.len(), b.len());
+    assert!(a.len() <= 4);
+
+    // schoolbook multiplication over 8-bit limbs, where each column sum together with the
+    // carry from the previous column stays below 2^19
+    let mut bits: Option<BitsVar> = None;
+    let mut carry: Option<M31Var> = None;
+    for k in 0..a.len() {
+        let mut acc = &a[0] * &b[k];
+        for i in 1..=k {
+            acc = &acc + &(&a[i] * &b[k - i]);
+        }
+        if let Some(carry) = carry {
+            acc = &acc + &carry;
+        }
+
+        let acc_bits = BitsVar::from_m31(&acc, 19);
+        carry = Some(acc_bits.compose_range(8..19));
+        bits = Some(match bits {
+            None => acc_bits.index_range(0..8),
+            Some(bits) => concat_bits(&bits, &acc_bits.index_range(0..8)),
+        });
+    }
+
+    bits.unwrap()
+}
@@ -0,0 +1,256 @@
+use crate::{concat_bits, constant_bits, overflowing_sub_limbs, wrapping_mul_limbs, U8Var};
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::{M31Var, QM31Var};
+use num_traits::One;
+use std::ops::Neg;
+use stwo_prover::core::fields::m31::M31;
+
+#[derive(Debug, Clone)]
+pub struct U32Var {
+    pub value: u32,
+    pub bits: BitsVar,
+}
+
+impl Var for U32Var {
+    type Value = u32;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.bits.cs()
+    }
+}
+
+impl AllocVar for U32Var {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let bools = (0..32).map(|i| (*value >> i) & 1 != 0).collect::<Vec<_>>();
+        let bits = if mode == AllocationMode::Constant {
+            constant_bits(cs, bools)
+        } else {
+            BitsVar::new_variables(cs, &bools, mode)
+        };
+
+        Self {
+            value: *value,
+            bits,
+        }
+    }
+}
+
+impl U32Var {
+    pub fn from_bits(bits: &BitsVar) -> Self {
+        assert_eq!(bits.variables.len(), 32);
+        let value = bits
+            .value
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | ((b as u32) << i));
+
+        Self {
+            value,
+            bits: bits.clone(),
+        }
+    }
+
+    pub fn from_m31(v: &M31Var) -> Self {
+        let cs = v.cs();
+        let bits = BitsVar::from_m31(v, 31);
+        Self::from_bits(&concat_bits(&bits, &constant_bits(&cs, vec![false])))
+    }
+
+    /// Returns the lower and the higher 16-bit limbs.
+    pub fn to_limbs_m31(&self) -> [M31Var; 2] {
+        [
+            self.bits.compose_range(0..16),
+            self.bits.compose_range(16..32),
+        ]
+    }
+
+    /// Returns the value reduced modulo the M31 prime.
+    pub fn to_m31(&self) -> M31Var {
+        let [lo, hi] = self.to_limbs_m31();
+        &lo + &hi.mul_constant(M31::from(1 << 16))
+    }
+
+    /// Packs every two words into a QM31 element, as four 16-bit limbs.
+    pub fn pack_qm31(values: &[U32Var]) -> Vec<QM31Var> {
+        assert!(!values.is_empty());
+        let cs = values[0].cs();
+
+        values
+            .chunks(2)
+            .map(|chunk| {
+                let [a0, a1] = chunk[0].to_limbs_m31();
+                let [a2, a3] = if chunk.len() == 2 {
+                    chunk[1].to_limbs_m31()
+                } else {
+                    [M31Var::zero(&cs), M31Var::zero(&cs)]
+                };
+                QM31Var::from_m31(&a0, &a1, &a2, &a3)
+            })
+            .collect()
+    }
+
+    pub fn unpack_qm31(v: &QM31Var) -> [U32Var; 2] {
+        let limbs = v.decompose_m31().map(|limb| BitsVar::from_m31(&limb, 16));
+        [
+            Self::from_bits(&concat_bits(&limbs[0], &limbs[1])),
+            Self::from_bits(&concat_bits(&limbs[2], &limbs[3])),
+        ]
+    }
+
+    pub fn to_bytes_le(&self) -> [U8Var; 4] {
+        std::array::from_fn(|i| U8Var::from_bits(&self.bits.index_range(i * 8..i * 8 + 8)))
+    }
+
+    pub fn to_bytes_be(&self) -> [U8Var; 4] {
+        let mut bytes = self.to_bytes_le();
+        bytes.reverse();
+        bytes
+    }
+
+    pub fn from_bytes_le(bytes: &[U8Var]) -> Self {
+        assert_eq!(bytes.len(), 4);
+        let mut bits = bytes[0].bits.clone();
+        for byte in bytes.iter().skip(1) {
+            bits = concat_bits(&bits, &byte.bits);
+        }
+        Self::from_bits(&bits)
+    }
+
+    pub fn from_bytes_be(bytes: &[U8Var]) -> Self {
+        let mut bytes = bytes.to_vec();
+        bytes.reverse();
+        Self::from_bytes_le(&bytes)
+    }
+
+    pub fn wrapping_add(&self, rhs: &U32Var) -> U32Var {
+        Self::from_bits(&self.bits.add_u32(&rhs.bits))
+    }
+
+    /// Returns `self - rhs` modulo 2^32, and a bit indicating whether the subtraction borrows.
+    pub fn overflowing_sub(&self, rhs: &U32Var) -> (U32Var, M31Var) {
+        let (bits, borrow) = overflowing_sub_limbs(&self.to_limbs_m31(), &rhs.to_limbs_m31(), 16);
+        (Self::from_bits(&bits), borrow)
+    }
+
+    pub fn wrapping_sub(&self, rhs: &U32Var) -> U32Var {
+        self.overflowing_sub(rhs).0
+    }
+
+    pub fn wrapping_mul(&self, rhs: &U32Var) -> U32Var {
+        let a: [M31Var; 4] = std::array::from_fn(|i| self.bits.compose_range(i * 8..i * 8 + 8));
+        let b: [M31Var; 4] = std::array::from_fn(|i| rhs.bits.compose_range(i * 8..i * 8 + 8));
+        Self::from_bits(&wrapping_mul_limbs(&a, &b))
+    }
+
+    pub fn is_less_than(&self, rhs: &U32Var) -> M31Var {
+        self.overflowing_sub(rhs).1
+    }
+
+    pub fn is_eq(&self, rhs: &U32Var) -> M31Var {
+        let [a_lo, a_hi] = self.to_limbs_m31();
+        let [b_lo, b_hi] = rhs.to_limbs_m31();
+        &a_lo.is_eq(&b_lo) * &a_hi.is_eq(&b_hi)
+    }
+
+    pub fn equalverify(&self, rhs: &U32Var) {
+        for i in 0..32 {
+            self.bits.get_bit(i).equalverify(&rhs.bits.get_bit(i));
+        }
+    }
+
+    /// Returns `(a, b)` if the bit is zero, and `(b, a)` otherwise.
+    pub fn swap(a: &U32Var, b: &U32Var, bit_value: bool, bit_variable: usize) -> (U32Var, U32Var) {
+        let cs = a.cs().and(&b.cs());
+
+        let mut left_variables = Vec::with_capacity(32);
+        let mut right_variables = Vec::with_capacity(32);
+        for i in 0..32 {
+            // left = a + bit * (b - a), right = b - bit * (b - a)
+            let neg_a = cs.mul_constant(a.bits.variables[i], M31::one().neg());
+            let diff = cs.add(b.bits.variables[i], neg_a);
+            let t = cs.mul(bit_variable, diff);
+            left_variables.push(cs.add(a.bits.variables[i], t));
+            let neg_t = cs.mul_constant(t, M31::one().neg());
+            right_variables.push(cs.add(b.bits.variables[i], neg_t));
+        }
+
+        let (left_value, right_value) = if bit_value {
+            (b.bits.value.clone(), a.bits.value.clone())
+        } else {
+            (a.bits.value.clone(), b.bits.value.clone())
+        };
+
+        let left = BitsVar {
+            cs: cs.clone(),
+            value: left_value,
+            variables: left_variables,
+        };
+        let right = BitsVar {
+            cs,
+            value: right_value,
+            variables: right_variables,
+        };
+        (Self::from_bits(&left), Self::from_bits(&right))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::U32Var;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_fields::M31Var;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+
+    #[test]
+    fn test_u32_arithmetics() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            for _ in 0..10 {
+                let a: u32 = prng.gen();
+                let b: u32 = prng.gen();
+
+                let a_var = U32Var::new_witness(&cs, &a);
+                let b_var = U32Var::new_witness(&cs, &b);
+
+                assert_eq!(a_var.wrapping_add(&b_var).value, a.wrapping_add(b));
+                assert_eq!(a_var.wrapping_sub(&b_var).value, a.wrapping_sub(b));
+                assert_eq!(a_var.wrapping_mul(&b_var).value, a.wrapping_mul(b));
+                assert_eq!(a_var.is_less_than(&b_var).value, M31::from((a < b) as u32));
+                assert_eq!(a_var.is_eq(&b_var).value, M31::from((a == b) as u32));
+                assert_eq!(a_var.is_eq(&a_var).value, M31::from(1));
+                assert_eq!(a_var.to_m31().value, M31::from(a));
+
+                let bytes = a_var.to_bytes_be();
+                for (byte, expected) in bytes.iter().zip(a.to_be_bytes()) {
+                    assert_eq!(byte.value, expected);
+                }
+                U32Var::from_bytes_be(&bytes).equalverify(&a_var);
+                U32Var::from_bytes_le(&a_var.to_bytes_le()).equalverify(&a_var);
+
+                let packed = U32Var::pack_qm31(&[a_var.clone(), b_var.clone()]);
+                let [a_unpacked, b_unpacked] = U32Var::unpack_qm31(&packed[0]);
+                a_unpacked.equalverify(&a_var);
+                b_unpacked.equalverify(&b_var);
+
+                let c = M31::from(prng.gen_range(0..(1u32 << 31) - 1));
+                let c_var = U32Var::from_m31(&M31Var::new_witness(&cs, &c));
+                assert_eq!(c_var.value, c.0);
+
+                let d_var = U32Var::new_constant(&cs, &a);
+                d_var.equalverify(&a_var);
+            }
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+}
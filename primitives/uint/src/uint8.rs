@@ -0,0 +1,140 @@
+use crate::{constant_bits, overflowing_sub_limbs, wrapping_mul_limbs};
+use circle_plonk_dsl_bits::BitsVar;
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_fields::M31Var;
+
+#[derive(Debug, Clone)]
+pub struct U8Var {
+    pub value: u8,
+    pub bits: BitsVar,
+}
+
+impl Var for U8Var {
+    type Value = u8;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.bits.cs()
+    }
+}
+
+impl AllocVar for U8Var {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let bools = (0..8).map(|i| (*value >> i) & 1 != 0).collect::<Vec<_>>();
+        let bits = if mode == AllocationMode::Constant {
+            constant_bits(cs, bools)
+        } else {
+            BitsVar::new_variables(cs, &bools, mode)
+        };
+
+        Self {
+            value: *value,
+            bits,
+        }
+    }
+}
+
+impl U8Var {
+    pub fn from_bits(bits: &BitsVar) -> Self {
+        assert_eq!(bits.variables.len(), 8);
+        let value = bits
+            .value
+            .iter()
+            .enumerate()
+            .fold(0u8, |acc, (i, &b)| acc | ((b as u8) << i));
+
+        Self {
+            value,
+            bits: bits.clone(),
+        }
+    }
+
+    pub fn from_m31(v: &M31Var) -> Self {
+        Self::from_bits(&BitsVar::from_m31(v, 8))
+    }
+
+    pub fn to_m31(&self) -> M31Var {
+        self.bits.compose_range(0..8)
+    }
+
+    pub fn xor(&self, rhs: &U8Var) -> U8Var {
+        Self::from_bits(&self.bits.xor(&rhs.bits))
+    }
+
+    pub fn and(&self, rhs: &U8Var) -> U8Var {
+        Self::from_bits(&self.bits.and(&rhs.bits))
+    }
+
+    pub fn wrapping_add(&self, rhs: &U8Var) -> U8Var {
+        let sum = &self.to_m31() + &rhs.to_m31();
+        Self::from_bits(&BitsVar::from_m31(&sum, 9).index_range(0..8))
+    }
+
+    /// Returns `self - rhs` modulo 2^8, and a bit indicating whether the subtraction borrows.
+    pub fn overflowing_sub(&self, rhs: &U8Var) -> (U8Var, M31Var) {
+        let (bits, borrow) = overflowing_sub_limbs(&[self.to_m31()], &[rhs.to_m31()], 8);
+        (Self::from_bits(&bits), borrow)
+    }
+
+    pub fn wrapping_sub(&self, rhs: &U8Var) -> U8Var {
+        self.overflowing_sub(rhs).0
+    }
+
+    pub fn wrapping_mul(&self, rhs: &U8Var) -> U8Var {
+        Self::from_bits(&wrapping_mul_limbs(&[self.to_m31()], &[rhs.to_m31()]))
+    }
+
+    pub fn is_less_than(&self, rhs: &U8Var) -> M31Var {
+        self.overflowing_sub(rhs).1
+    }
+
+    pub fn is_eq(&self, rhs: &U8Var) -> M31Var {
+        self.to_m31().is_eq(&rhs.to_m31())
+    }
+
+    pub fn equalverify(&self, rhs: &U8Var) {
+        self.to_m31().equalverify(&rhs.to_m31());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::U8Var;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo_prover::core::fields::m31::M31;
+
+    #[test]
+    fn test_u8_arithmetics() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        for cs in [
+            ConstraintSystemRef::new_plonk_with_poseidon_ref(),
+            ConstraintSystemRef::new_plonk_without_poseidon_ref(),
+        ] {
+            // the edge cases together with random bytes
+            let mut pairs = vec![(0u8, 0u8), (0, 255), (255, 0), (255, 255), (128, 128)];
+            pairs.extend((0..10).map(|_| (prng.gen(), prng.gen())));
+
+            for (a, b) in pairs {
+                let a_var = U8Var::new_witness(&cs, &a);
+                let b_var = U8Var::new_witness(&cs, &b);
+
+                assert_eq!(a_var.wrapping_add(&b_var).value, a.wrapping_add(b));
+                assert_eq!(a_var.wrapping_sub(&b_var).value, a.wrapping_sub(b));
+                assert_eq!(a_var.wrapping_mul(&b_var).value, a.wrapping_mul(b));
+                assert_eq!(a_var.is_less_than(&b_var).value, M31::from((a < b) as u32));
+                assert_eq!(a_var.is_eq(&b_var).value, M31::from((a == b) as u32));
+                assert_eq!(a_var.xor(&b_var).value, a ^ b);
+                assert_eq!(a_var.and(&b_var).value, a & b);
+
+                U8Var::new_constant(&cs, &a).equalverify(&a_var);
+            }
+
+            cs.pad();
+            cs.check_arithmetics();
+        }
+    }
+}